@@ -0,0 +1,2 @@
+ALTER TABLE backtests ADD COLUMN symbols TEXT NOT NULL DEFAULT '[]';
+UPDATE backtests SET symbols = json_array(symbol);
@@ -0,0 +1,3 @@
+ALTER TABLE backtests ADD COLUMN allow_short INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE backtests ADD COLUMN borrow_fee_rate REAL NOT NULL DEFAULT 0.0;
+ALTER TABLE backtests ADD COLUMN short_margin_rate REAL NOT NULL DEFAULT 0.5;
@@ -0,0 +1 @@
+ALTER TABLE backtest_trades ADD COLUMN exit_reason TEXT;
@@ -0,0 +1,2 @@
+-- Existing rows were filled at the signal bar's close
+ALTER TABLE backtests ADD COLUMN fill_model TEXT NOT NULL DEFAULT 'same_bar_close';
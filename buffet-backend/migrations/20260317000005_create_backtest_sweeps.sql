@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS backtest_sweeps (
+    id TEXT PRIMARY KEY NOT NULL,
+    strategy_id TEXT NOT NULL,
+    grid TEXT NOT NULL, -- JSON object of parameter name → values
+    objective TEXT NOT NULL, -- 'sharpe', 'total_return', 'return_over_drawdown'
+    run_count INTEGER NOT NULL DEFAULT 0,
+    skipped_count INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (strategy_id) REFERENCES strategies (id)
+);
+
+-- Parameters a run used instead of its strategy's, when overridden
+ALTER TABLE backtests ADD COLUMN parameters TEXT;
+ALTER TABLE backtests ADD COLUMN sweep_id TEXT REFERENCES backtest_sweeps (id);
+
+CREATE INDEX IF NOT EXISTS idx_backtests_sweep_id ON backtests(sweep_id);
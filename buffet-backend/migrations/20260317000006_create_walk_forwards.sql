@@ -0,0 +1,44 @@
+ALTER TABLE backtests ADD COLUMN warmup_start_time TEXT;
+
+CREATE TABLE IF NOT EXISTS walk_forwards (
+    id TEXT PRIMARY KEY NOT NULL,
+    strategy_id TEXT NOT NULL,
+    mode TEXT NOT NULL, -- 'rolling', 'anchored'
+    fold_count INTEGER NOT NULL,
+    train_ratio REAL NOT NULL,
+    grid TEXT NOT NULL, -- JSON object of parameter name → values
+    objective TEXT NOT NULL,
+    settings TEXT NOT NULL, -- JSON of the shared backtest settings
+    initial_balance REAL NOT NULL,
+    status TEXT NOT NULL, -- 'pending', 'running', 'completed', 'failed'
+    error_message TEXT,
+    final_balance REAL,
+    total_return REAL,
+    sharpe_ratio REAL,
+    max_drawdown REAL,
+    trade_count INTEGER,
+    win_rate REAL,
+    profit_factor REAL,
+    equity_curve TEXT, -- JSON array of stitched out-of-sample equity points
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (strategy_id) REFERENCES strategies (id)
+);
+
+CREATE TABLE IF NOT EXISTS walk_forward_folds (
+    id TEXT PRIMARY KEY NOT NULL,
+    walk_forward_id TEXT NOT NULL,
+    fold_index INTEGER NOT NULL,
+    train_start TEXT NOT NULL,
+    train_end TEXT NOT NULL,
+    test_start TEXT NOT NULL,
+    test_end TEXT NOT NULL,
+    sweep_id TEXT, -- in-sample optimisation
+    backtest_id TEXT, -- out-of-sample evaluation
+    parameters TEXT, -- JSON of the winning parameters
+    train_score REAL,
+    FOREIGN KEY (walk_forward_id) REFERENCES walk_forwards (id) ON DELETE CASCADE,
+    FOREIGN KEY (sweep_id) REFERENCES backtest_sweeps (id),
+    FOREIGN KEY (backtest_id) REFERENCES backtests (id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_walk_forward_folds_walk_forward_id ON walk_forward_folds(walk_forward_id);
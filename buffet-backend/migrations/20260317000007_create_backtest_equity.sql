@@ -0,0 +1,9 @@
+-- Per-bar equity curve of a backtest, in order of `seq`
+CREATE TABLE IF NOT EXISTS backtest_equity (
+    backtest_id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    timestamp TEXT NOT NULL,
+    equity REAL NOT NULL,
+    PRIMARY KEY (backtest_id, seq),
+    FOREIGN KEY (backtest_id) REFERENCES backtests (id) ON DELETE CASCADE
+);
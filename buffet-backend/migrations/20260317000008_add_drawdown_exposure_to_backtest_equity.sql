@@ -0,0 +1,3 @@
+-- Drawdown from the running peak and gross exposure, both as fractions of equity
+ALTER TABLE backtest_equity ADD COLUMN drawdown REAL NOT NULL DEFAULT 0;
+ALTER TABLE backtest_equity ADD COLUMN exposure REAL NOT NULL DEFAULT 0;
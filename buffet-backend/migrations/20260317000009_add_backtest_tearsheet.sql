@@ -0,0 +1,16 @@
+-- Tearsheet metrics of completed backtests (see utils::metrics::Tearsheet)
+ALTER TABLE backtests ADD COLUMN cagr REAL;
+ALTER TABLE backtests ADD COLUMN annualized_volatility REAL;
+ALTER TABLE backtests ADD COLUMN annualized_sharpe_ratio REAL;
+ALTER TABLE backtests ADD COLUMN sortino_ratio REAL;
+ALTER TABLE backtests ADD COLUMN calmar_ratio REAL;
+ALTER TABLE backtests ADD COLUMN max_drawdown_duration_secs INTEGER;
+ALTER TABLE backtests ADD COLUMN recovery_time_secs INTEGER;
+ALTER TABLE backtests ADD COLUMN exposure_time REAL;
+ALTER TABLE backtests ADD COLUMN turnover REAL;
+ALTER TABLE backtests ADD COLUMN average_win REAL;
+ALTER TABLE backtests ADD COLUMN average_loss REAL;
+ALTER TABLE backtests ADD COLUMN expectancy REAL;
+ALTER TABLE backtests ADD COLUMN largest_win REAL;
+ALTER TABLE backtests ADD COLUMN largest_loss REAL;
+ALTER TABLE backtests ADD COLUMN max_consecutive_losses INTEGER;
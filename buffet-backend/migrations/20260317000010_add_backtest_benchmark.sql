@@ -0,0 +1,10 @@
+-- Benchmark the strategy is compared against (buy-and-hold of one symbol)
+ALTER TABLE backtests ADD COLUMN benchmark_symbol TEXT;
+ALTER TABLE backtests ADD COLUMN benchmark_return REAL;
+ALTER TABLE backtests ADD COLUMN alpha REAL;
+ALTER TABLE backtests ADD COLUMN beta REAL;
+ALTER TABLE backtests ADD COLUMN information_ratio REAL;
+ALTER TABLE backtests ADD COLUMN tracking_error REAL;
+ALTER TABLE backtests ADD COLUMN excess_return REAL;
+
+ALTER TABLE backtest_equity ADD COLUMN benchmark_equity REAL;
@@ -0,0 +1,3 @@
+-- Progress of a running backtest, updated periodically by its worker
+ALTER TABLE backtests ADD COLUMN bars_processed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE backtests ADD COLUMN bars_total INTEGER;
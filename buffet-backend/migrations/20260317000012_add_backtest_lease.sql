@@ -0,0 +1,3 @@
+-- Lease held by the worker running a backtest, renewed by its heartbeat
+ALTER TABLE backtests ADD COLUMN lease_id TEXT;
+ALTER TABLE backtests ADD COLUMN heartbeat_at TEXT;
@@ -0,0 +1,2 @@
+-- Commission schedule and fees (JSON); null means a flat commission_rate
+ALTER TABLE backtests ADD COLUMN commission_model TEXT;
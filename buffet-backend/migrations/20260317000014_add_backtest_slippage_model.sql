@@ -0,0 +1,2 @@
+-- Market impact and participation cap (JSON); null means a fixed slippage_bps
+ALTER TABLE backtests ADD COLUMN slippage_model TEXT;
@@ -0,0 +1,2 @@
+-- Leverage, margin calls, interest and funding (JSON); null means a cash account
+ALTER TABLE backtests ADD COLUMN margin_model TEXT;
@@ -0,0 +1,2 @@
+-- Trading calendar for every symbol of the run; null uses each symbol's asset type
+ALTER TABLE backtests ADD COLUMN calendar TEXT;
@@ -0,0 +1,2 @@
+-- Existing rows ran through the event-driven loop
+ALTER TABLE backtests ADD COLUMN engine TEXT NOT NULL DEFAULT 'event';
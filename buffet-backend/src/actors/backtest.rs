@@ -1,25 +1,629 @@
 use crate::actors::messages::{ActorError, ActorResult, RunBacktest, SignalType, TimeSeriesRef};
-use crate::actors::storage::{QueryOHLCV, TimeSeriesStorageActor};
-use crate::actors::strategy::{MovingAverageCrossover, StrategyLogic};
-use crate::broker::{Broker, BacktestBroker};
-use crate::models::backtest::{Backtest, BacktestStatus, BacktestTrade};
+use crate::actors::storage::{
+    QueryAssetType, QueryCorporateActions, QueryOHLCV, TimeSeriesStorageActor,
+};
+use crate::actors::strategy::{Hedge, SignalKind, StrategyLogic, StrategySignal};
+use crate::broker::{BacktestBroker, Broker, FillModel, FillResult, Liquidity, MarginModel};
+use crate::calendar::{self, TradingCalendar};
+use crate::config::BacktestRecovery;
+use crate::error::AppError;
+use crate::models::backtest::{Backtest, BacktestEngine, BacktestTrade, EquityPoint, ExitReason};
 use crate::models::order::OrderSide;
-use crate::models::strategy::{Strategy, StrategyType};
+use crate::models::strategy::Strategy;
+use crate::strategies::{ExitRules, PositionSizer, StopTracker, StrategyRegistry};
+use crate::utils::adjustments::{back_adjust, restate_dividends};
 use crate::utils::metrics::{
-    calculate_max_drawdown, calculate_profit_factor, calculate_sharpe_ratio, calculate_win_rate,
+    BenchmarkComparison, SECONDS_PER_YEAR, Tearsheet, calculate_max_drawdown,
+    calculate_profit_factor, calculate_sharpe_ratio, calculate_win_rate, median_spacing_secs,
 };
+use crate::utils::vectorized::{self, VectorizedStrategy};
+use crate::models::market_data::{AssetType, CorporateAction, OHLCV, PriceAdjustment, Ticker};
+use chrono::{DateTime, Utc};
 use kameo::Actor;
-use kameo::actor::ActorRef;
+use kameo::actor::{ActorRef, Spawn};
+use kameo::error::SendError;
+use kameo::mailbox;
 use kameo::message::{Context, Message};
 use sqlx::{Pool, Sqlite};
-use std::str::FromStr;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use tracing::{info, warn};
 
+/// How often a running backtest records its progress and checks whether it
+/// has been cancelled.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often a run renews its lease in the background, well inside the
+/// shortest lease the lease monitor is configured with in practice.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A position held in one symbol of the backtest portfolio.
+struct OpenPosition {
+    /// Signed quantity: negative for shorts.
+    quantity: f64,
+    entry_price: f64,
+    /// The trade this position opened, saved with the run's results.
+    trade: BacktestTrade,
+    /// Borrow fees accrued while short, charged against the trade's PnL.
+    borrow_cost: f64,
+    /// Dividends received (or, while short, paid), credited to the trade's PnL.
+    dividends: f64,
+    /// Perpetual funding paid (or, when negative, received) while open.
+    funding: f64,
+    /// Commission and fees paid on the trade's entry and exit fills.
+    commission: f64,
+    /// Risk exits, when the strategy configures any.
+    stops: Option<StopTracker>,
+    /// Units and value sold (or bought back) so far by an exit filling
+    /// over several bars.
+    exited_quantity: f64,
+    exit_notional: f64,
+}
+
+/// The unfilled rest of an order that exceeded its bar's participation cap,
+/// retried on the symbol's following bars until it fills or a new signal,
+/// stop or the end of data replaces it. Signals arriving while an exit is
+/// working act on the flat book it leaves, once it has filled.
+struct WorkingOrder {
+    side: OrderSide,
+    quantity: f64,
+    /// Why the position is being closed; `None` for an entry.
+    exit_reason: Option<ExitReason>,
+    /// The entry to open once this exit has filled, e.g. the other side of
+    /// a flip.
+    then_open: Option<PendingEntry>,
+}
+
+/// An entry waiting for the exit ahead of it in the same symbol.
+struct PendingEntry {
+    side: OrderSide,
+    hedge: Option<Hedge>,
+}
+
+/// One symbol's bar in the merged event stream of a run.
+struct Bar {
+    symbol: String,
+    /// Split-adjusted prices, which the portfolio trades and is marked at.
+    candle: OHLCV,
+    /// Split- and dividend-adjusted prices, which the strategy sees so
+    /// corporate actions never look like price moves.
+    signal: OHLCV,
+    /// Cash per (split-adjusted) share paid to positions held into this bar.
+    dividend: f64,
+}
+
+/// Zip a symbol's split-adjusted `series` with its signal prices, paying
+/// each dividend on the first bar at or after its ex-date.
+fn build_bars(symbol: &str, series: Vec<OHLCV>, actions: &[CorporateAction]) -> Vec<Bar> {
+    let dividends = restate_dividends(actions);
+    let signals = back_adjust(&series, &dividends, PriceAdjustment::Total);
+    let mut ex_dates = dividends.iter().peekable();
+    series
+        .into_iter()
+        .zip(signals)
+        .map(|(candle, signal)| {
+            let mut dividend = 0.0;
+            while let Some(paid) = ex_dates.next_if(|d| d.timestamp <= candle.timestamp) {
+                dividend += paid.value;
+            }
+            Bar {
+                symbol: symbol.to_string(),
+                candle,
+                signal,
+                dividend,
+            }
+        })
+        .collect()
+}
+
+/// Cash plus every open position marked at its symbol's last close.
+fn portfolio_equity(
+    balance: f64,
+    positions: &HashMap<String, OpenPosition>,
+    last_prices: &HashMap<String, f64>,
+) -> f64 {
+    balance
+        + positions
+            .iter()
+            .map(|(symbol, p)| p.quantity * last_prices.get(symbol).copied().unwrap_or(0.0))
+            .sum::<f64>()
+}
+
+/// Cash held as collateral against open shorts: the short proceeds plus
+/// `margin_rate` of the short's current value.
+fn short_collateral(
+    positions: &HashMap<String, OpenPosition>,
+    last_prices: &HashMap<String, f64>,
+    margin_rate: f64,
+) -> f64 {
+    positions
+        .iter()
+        .filter(|(_, p)| p.quantity < 0.0)
+        .map(|(symbol, p)| {
+            -p.quantity * last_prices.get(symbol).copied().unwrap_or(0.0) * (1.0 + margin_rate)
+        })
+        .sum()
+}
+
+/// Cash available for new positions: the balance, plus what the margin
+/// account lends against open longs beyond `initial_margin`, less the
+/// collateral held against shorts.
+fn free_cash(
+    balance: f64,
+    positions: &HashMap<String, OpenPosition>,
+    last_prices: &HashMap<String, f64>,
+    short_margin_rate: f64,
+    initial_margin: f64,
+) -> f64 {
+    let long_value: f64 = positions
+        .iter()
+        .filter(|(_, p)| p.quantity > 0.0)
+        .map(|(symbol, p)| p.quantity * last_prices.get(symbol).copied().unwrap_or(0.0))
+        .sum();
+    balance + long_value * (1.0 - initial_margin)
+        - short_collateral(positions, last_prices, short_margin_rate)
+}
+
+/// Largest quantity up to `wanted` whose cash outlay — `cash_factor` of its
+/// value at the broker's price plus commission — fits in `free_cash`.
+/// Commission schedules and market impact need not be linear (tickets,
+/// minimums, tiers, square-root impact), so the cap is found by bisection.
+fn affordable_quantity(
+    broker: &BacktestBroker,
+    side: &OrderSide,
+    cash_factor: f64,
+    free_cash: f64,
+    wanted: f64,
+) -> f64 {
+    let base = broker.current_price;
+    let cost = |quantity: f64| {
+        let price = broker.apply_slippage(base, side, broker.fillable(quantity));
+        quantity * price * cash_factor
+            + broker.apply_commission(side, quantity, price, Liquidity::Taker)
+    };
+    if cost(wanted) <= free_cash {
+        return wanted;
+    }
+    if free_cash <= 0.0 {
+        return 0.0;
+    }
+
+    let mut low = 0.0;
+    let mut high = if base * cash_factor > 0.0 {
+        wanted.min(free_cash / (base * cash_factor))
+    } else {
+        wanted
+    };
+    if cost(high) <= free_cash {
+        return high;
+    }
+    for _ in 0..64 {
+        let mid = (low + high) / 2.0;
+        if cost(mid) <= free_cash {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// How a signal changes the position held in its symbol: whether to close
+/// what is held, and which side (if any) to open afterwards.
+///
+/// Target-state strategies flip straight from long to short (and back) when
+/// shorting is allowed; event strategies close and open in separate signals.
+fn position_change(
+    kind: SignalKind,
+    signal_type: SignalType,
+    held: f64,
+    allow_short: bool,
+) -> (bool, Option<OrderSide>) {
+    let flip = kind == SignalKind::Target;
+    match signal_type {
+        SignalType::Buy if held < 0.0 => (true, flip.then_some(OrderSide::Buy)),
+        SignalType::Buy if held == 0.0 => (false, Some(OrderSide::Buy)),
+        SignalType::Sell if held > 0.0 => (true, (flip && allow_short).then_some(OrderSide::Sell)),
+        SignalType::Sell if held == 0.0 && allow_short => (false, Some(OrderSide::Sell)),
+        _ => (false, None),
+    }
+}
+
+/// Cash flow of a fill: negative for buys, net of commission.
+fn cash_flow(side: &OrderSide, fill: &FillResult) -> f64 {
+    let notional = fill.fill_price * fill.fill_quantity;
+    let commission = fill.commission.unwrap_or(0.0);
+    match side {
+        OrderSide::Buy => -notional - commission,
+        OrderSide::Sell => notional - commission,
+    }
+}
+
+/// Value `initial_balance` bought into the benchmark at its first close at
+/// every point of `curve`, marked at the latest close known by then.
+fn fill_benchmark(curve: &mut [EquityPoint], benchmark: &[OHLCV], initial_balance: f64) {
+    let Some(first) = benchmark.first().filter(|bar| bar.close > 0.0) else {
+        return;
+    };
+    let units = initial_balance / first.close;
+    let mut bars = benchmark.iter().peekable();
+    let mut close = first.close;
+    for point in curve {
+        while let Some(bar) = bars.next_if(|bar| bar.timestamp <= point.timestamp) {
+            close = bar.close;
+        }
+        point.benchmark_equity = Some(units * close);
+    }
+}
+
+/// Cash, open positions and closed trades of one run, together with the
+/// broker, sizer and exit rules that fill, size and protect its orders.
+struct Simulation<'a> {
+    backtest: &'a Backtest,
+    broker: BacktestBroker,
+    sizer: PositionSizer,
+    exits: ExitRules,
+    margin: MarginModel,
+    balance: f64,
+    positions: HashMap<String, OpenPosition>,
+    last_prices: HashMap<String, f64>,
+    /// Latest bar of each symbol, whose volume caps exits priced off it.
+    last_bars: HashMap<String, &'a OHLCV>,
+    closed_trades: Vec<BacktestTrade>,
+    /// Partially filled orders, at most one per symbol.
+    working: HashMap<String, WorkingOrder>,
+    /// Time up to which interest on cash has been paid.
+    accrued_to: DateTime<Utc>,
+    /// Net interest credited (negative when charged) over the run.
+    interest: f64,
+}
+
+impl Simulation<'_> {
+    fn equity(&self) -> f64 {
+        portfolio_equity(self.balance, &self.positions, &self.last_prices)
+    }
+
+    /// Value of every open position, long or short, at the last prices.
+    fn gross_value(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(|(symbol, p)| {
+                (p.quantity * self.last_prices.get(symbol).copied().unwrap_or(0.0)).abs()
+            })
+            .sum()
+    }
+
+    /// Gross position value as a fraction of equity.
+    fn exposure(&self) -> f64 {
+        let equity = self.equity();
+        if equity > 0.0 { self.gross_value() / equity } else { 0.0 }
+    }
+
+    /// Pay interest on cash from `accrued_to` up to `time`. Short proceeds
+    /// are held by the lender, so they earn nothing.
+    fn accrue_interest(&mut self, time: DateTime<Utc>) {
+        let years = (time - self.accrued_to).num_seconds() as f64 / SECONDS_PER_YEAR;
+        let cash = self.balance - short_collateral(&self.positions, &self.last_prices, 0.0);
+        let interest = self.margin.interest(cash, years);
+        self.balance += interest;
+        self.interest += interest;
+        self.accrued_to = time;
+    }
+
+    /// Liquidate every position at its last price once equity falls below
+    /// the maintenance margin.
+    async fn check_margin(&mut self, time: DateTime<Utc>) -> ActorResult<()> {
+        let (equity, gross) = (self.equity(), self.gross_value());
+        if !self.margin.is_margin_call(equity, gross) {
+            return Ok(());
+        }
+        warn!(
+            "Backtest {}: margin call at {} (equity {:.2} on {:.2} of positions)",
+            self.backtest.id, time, equity, gross
+        );
+        let mut symbols: Vec<String> = self.positions.keys().cloned().collect();
+        symbols.sort();
+        for symbol in symbols {
+            self.set_exit_price(&symbol, self.last_prices[&symbol]);
+            self.close(&symbol, time, ExitReason::MarginCall).await?;
+        }
+        Ok(())
+    }
+
+    /// Price exits in `symbol` at exactly `price`, capped by the volume of
+    /// its latest bar like any other fill.
+    fn set_exit_price(&mut self, symbol: &str, price: f64) {
+        match self.last_bars.get(symbol) {
+            Some(bar) => {
+                self.broker.set_bar(bar);
+                self.broker.set_exit_price(price);
+            }
+            None => self.broker.set_price(price),
+        }
+    }
+
+    fn equity_point(&self, timestamp: DateTime<Utc>) -> EquityPoint {
+        EquityPoint::new(timestamp, self.equity(), self.exposure())
+    }
+
+    async fn submit(
+        &self,
+        symbol: &str,
+        side: &OrderSide,
+        quantity: f64,
+    ) -> ActorResult<FillResult> {
+        self.broker
+            .submit_market_order(symbol, side, quantity)
+            .await
+            .map_err(|e| ActorError::Internal(e.to_string()))
+    }
+
+    /// Flatten the position in `symbol`, if any, at the broker's current
+    /// price. What the bar's volume cannot absorb is left working.
+    async fn close(
+        &mut self,
+        symbol: &str,
+        exit_time: DateTime<Utc>,
+        exit_reason: ExitReason,
+    ) -> ActorResult<()> {
+        self.working.remove(symbol);
+        let Some(held) = self.positions.get(symbol).map(|p| p.quantity) else {
+            return Ok(());
+        };
+        let side = if held > 0.0 {
+            OrderSide::Sell
+        } else {
+            OrderSide::Buy
+        };
+        let fill = self.submit(symbol, &side, held.abs()).await?;
+        self.balance += cash_flow(&side, &fill);
+
+        let position = self
+            .positions
+            .get_mut(symbol)
+            .expect("position checked above");
+        position.exited_quantity += fill.fill_quantity;
+        position.exit_notional += fill.fill_price * fill.fill_quantity;
+        position.commission += fill.commission.unwrap_or(0.0);
+        if !fill.filled {
+            position.quantity -= held.signum() * fill.fill_quantity;
+            self.working.insert(
+                symbol.to_string(),
+                WorkingOrder {
+                    side,
+                    quantity: position.quantity.abs(),
+                    exit_reason: Some(exit_reason),
+                    then_open: None,
+                },
+            );
+            return Ok(());
+        }
+
+        let mut position = self
+            .positions
+            .remove(symbol)
+            .expect("position checked above");
+        // The trade exits at the average price of all its exit fills
+        let exit_price = position.exit_notional / position.exited_quantity;
+        position.trade.close(
+            exit_price,
+            exit_time,
+            position.borrow_cost + position.funding + position.commission - position.dividends,
+            exit_reason,
+        );
+        self.sizer
+            .record_trade(position.trade.percentage_return.unwrap_or(0.0));
+        self.closed_trades.push(position.trade);
+        Ok(())
+    }
+
+    /// Buy or sell up to `wanted` units of `symbol` at the broker's current
+    /// price, capped by free cash: longs pay their initial margin, shorts
+    /// must post margin on top of proceeds. Fills add to a position already held on
+    /// the same side; what the bar's volume cannot absorb is left working.
+    async fn enter(
+        &mut self,
+        symbol: &str,
+        side: OrderSide,
+        wanted: f64,
+        time: DateTime<Utc>,
+    ) -> ActorResult<()> {
+        self.working.remove(symbol);
+        let margin_rate = self.backtest.short_margin_rate;
+        let free_cash = free_cash(
+            self.balance,
+            &self.positions,
+            &self.last_prices,
+            margin_rate,
+            self.margin.initial_margin,
+        );
+        let cash_factor = match side {
+            OrderSide::Buy => self.margin.initial_margin,
+            OrderSide::Sell => margin_rate,
+        };
+        let quantity = affordable_quantity(&self.broker, &side, cash_factor, free_cash, wanted);
+        if quantity <= 0.0 {
+            return Ok(());
+        }
+
+        let fill = self.submit(symbol, &side, quantity).await?;
+        if !fill.filled {
+            self.working.insert(
+                symbol.to_string(),
+                WorkingOrder {
+                    side: side.clone(),
+                    quantity: quantity - fill.fill_quantity,
+                    exit_reason: None,
+                    then_open: None,
+                },
+            );
+        }
+        if fill.fill_quantity <= 0.0 {
+            return Ok(());
+        }
+        self.balance += cash_flow(&side, &fill);
+        let signed_quantity = match side {
+            OrderSide::Buy => fill.fill_quantity,
+            OrderSide::Sell => -fill.fill_quantity,
+        };
+
+        if let Some(position) = self.positions.get_mut(symbol) {
+            let total = position.quantity + signed_quantity;
+            position.entry_price = (position.entry_price * position.quantity.abs()
+                + fill.fill_price * fill.fill_quantity)
+                / total.abs();
+            position.quantity = total;
+            position.commission += fill.commission.unwrap_or(0.0);
+            position.trade.quantity = total.abs();
+            position.trade.entry_price = position.entry_price;
+            return Ok(());
+        }
+
+        let trade = BacktestTrade::open(
+            &self.backtest.id,
+            symbol,
+            &side.to_string(),
+            fill.fill_quantity,
+            fill.fill_price,
+            time,
+        );
+        let stops = self
+            .exits
+            .track(symbol, signed_quantity.signum(), fill.fill_price);
+        self.positions.insert(
+            symbol.to_string(),
+            OpenPosition {
+                quantity: signed_quantity,
+                entry_price: fill.fill_price,
+                trade,
+                borrow_cost: 0.0,
+                dividends: 0.0,
+                funding: 0.0,
+                commission: fill.commission.unwrap_or(0.0),
+                stops,
+                exited_quantity: 0.0,
+                exit_notional: 0.0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Carry on filling the working order in `symbol`, if any, at the
+    /// broker's current bar.
+    async fn resume(&mut self, symbol: &str, time: DateTime<Utc>) -> ActorResult<()> {
+        let Some(order) = self.working.remove(symbol) else {
+            return Ok(());
+        };
+        match order.exit_reason {
+            Some(reason) => {
+                self.close_then_open(symbol, time, reason, order.then_open)
+                    .await
+            }
+            None => self.enter(symbol, order.side, order.quantity, time).await,
+        }
+    }
+
+    /// Close `symbol`, then open `entry` once the old position is fully
+    /// out. Until then the entry waits on the exit left working.
+    async fn close_then_open(
+        &mut self,
+        symbol: &str,
+        time: DateTime<Utc>,
+        reason: ExitReason,
+        entry: Option<PendingEntry>,
+    ) -> ActorResult<()> {
+        self.close(symbol, time, reason).await?;
+        let Some(entry) = entry else {
+            return Ok(());
+        };
+        if self.positions.contains_key(symbol) {
+            if let Some(order) = self.working.get_mut(symbol) {
+                order.then_open = Some(entry);
+            }
+            return Ok(());
+        }
+        self.open(symbol, entry.side, entry.hedge.as_ref(), time)
+            .await
+    }
+
+    /// Trade `signal` at the broker's current price.
+    async fn apply_signal(
+        &mut self,
+        kind: SignalKind,
+        signal: &StrategySignal,
+        time: DateTime<Utc>,
+    ) -> ActorResult<()> {
+        let (symbol, signal_type) = (signal.symbol.as_str(), signal.signal_type);
+        let allow_short = self.backtest.allow_short;
+        let entry = |side| PendingEntry {
+            side,
+            hedge: signal.hedge.clone(),
+        };
+
+        // An exit still working finishes first, and the signal then acts on
+        // the flat book it leaves
+        if signal_type != SignalType::Hold
+            && let Some(order) = self
+                .working
+                .get_mut(symbol)
+                .filter(|order| order.exit_reason.is_some())
+        {
+            let (_, open) = position_change(kind, signal_type, 0.0, allow_short);
+            order.then_open = open.map(entry);
+            return Ok(());
+        }
+
+        let held = self.positions.get(symbol).map_or(0.0, |p| p.quantity);
+        match position_change(kind, signal_type, held, allow_short) {
+            (true, open) => {
+                self.close_then_open(symbol, time, ExitReason::Signal, open.map(entry))
+                    .await
+            }
+            (false, Some(side)) => self.open(symbol, side, signal.hedge.as_ref(), time).await,
+            (false, None) => Ok(()),
+        }
+    }
+
+    /// Open `side` in `symbol`, sized by the strategy's policy or, for a
+    /// hedge leg, by the other leg.
+    async fn open(
+        &mut self,
+        symbol: &str,
+        side: OrderSide,
+        hedge: Option<&Hedge>,
+        time: DateTime<Utc>,
+    ) -> ActorResult<()> {
+        // Size with the strategy's policy on what the order would pay now,
+        // against the buying power leverage gives the equity
+        let equity = self.equity() / self.margin.initial_margin;
+        let expected_price = self
+            .broker
+            .apply_slippage(self.broker.current_price, &side, 0.0);
+        let quantity = match hedge {
+            Some(hedge) => self.hedge_quantity(hedge, equity),
+            None => self.sizer.quantity(symbol, equity, expected_price),
+        };
+        self.enter(symbol, side, quantity, time).await
+    }
+
+    /// Units a hedge leg opens: its ratio times what an entry in the other
+    /// leg is sized at, at that leg's last price.
+    fn hedge_quantity(&self, hedge: &Hedge, equity: f64) -> f64 {
+        self.last_prices.get(&hedge.symbol).map_or(0.0, |&anchor| {
+            hedge.ratio * self.sizer.quantity(&hedge.symbol, equity, anchor)
+        })
+    }
+}
+
 #[derive(Actor)]
 #[actor(name = "BacktestActor")]
 pub struct BacktestActor {
     pool: Pool<Sqlite>,
     storage_actor: ActorRef<TimeSeriesStorageActor>,
+    /// Runs queued on or running in this actor, shared with `BacktestWorkers`.
+    queued: Arc<AtomicUsize>,
 }
 
 impl BacktestActor {
@@ -27,18 +631,498 @@ impl BacktestActor {
         Self {
             pool,
             storage_actor,
+            queued: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Persist progress as the run's heartbeat, failing with `Cancelled`
+    /// once the run has been cancelled or lost its lease so the caller stops.
+    async fn report_progress(
+        &self,
+        backtest_id: &str,
+        lease_id: &str,
+        bars_processed: i64,
+        bars_total: i64,
+    ) -> ActorResult<()> {
+        let running = Backtest::record_progress(
+            backtest_id,
+            lease_id,
+            bars_processed,
+            bars_total,
+            &self.pool,
+        )
+        .await
+        .map_err(|e| ActorError::DatabaseError(e.to_string()))?;
+        if !running {
+            info!(
+                "Backtest {} stopped after {}/{} bars: cancelled or lease lost",
+                backtest_id, bars_processed, bars_total
+            );
+            return Err(ActorError::Cancelled(backtest_id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// The calendar `symbol` trades on: the run's when it names one, else
+    /// the one its stored asset type implies. Symbols of unknown type are
+    /// taken to trade around the clock.
+    async fn symbol_calendar(
+        &self,
+        backtest: &Backtest,
+        symbol: &str,
+    ) -> ActorResult<TradingCalendar> {
+        if let Some(name) = backtest.calendar.as_deref() {
+            return calendar::by_name(name).ok_or_else(|| {
+                ActorError::InvalidInput(format!("Unknown trading calendar '{}'", name))
+            });
+        }
+        let asset_type = self
+            .storage_actor
+            .ask(QueryAssetType {
+                symbol: symbol.to_string(),
+            })
+            .await
+            .map_err(|e| ActorError::TsdbError(e.to_string()))?;
+        Ok(asset_type
+            .and_then(|asset_type| asset_type.parse::<AssetType>().ok())
+            .map_or_else(TradingCalendar::crypto, |asset_type| {
+                Ticker::new(symbol.to_string(), None, asset_type).calendar()
+            }))
+    }
+
+    /// Score a simulated run and persist its results, the same way whichever
+    /// engine produced `equity_curve` and `trades`. Trades still open when
+    /// the data ran out are saved but left out of the metrics.
+    #[allow(clippy::too_many_arguments)]
+    async fn finish(
+        &self,
+        backtest: &Backtest,
+        lease_id: &str,
+        mut equity_curve: Vec<EquityPoint>,
+        trades: &[BacktestTrade],
+        final_equity: f64,
+        run_config: String,
+        calendar: &TradingCalendar,
+    ) -> ActorResult<Vec<EquityPoint>> {
+        let backtest_id = backtest.id.as_str();
+        EquityPoint::fill_drawdowns(&mut equity_curve);
+        let closed_trades: Vec<&BacktestTrade> =
+            trades.iter().filter(|t| t.exit_time.is_some()).collect();
+
+        // ── 11. Calculate Metrics ─────────────────────────────────────────────
+        let returns: Vec<f64> = closed_trades
+            .iter()
+            .map(|t| t.percentage_return.unwrap_or(0.0))
+            .collect();
+        let trades_pnl: Vec<f64> = closed_trades.iter().map(|t| t.pnl.unwrap_or(0.0)).collect();
+        let trade_count = trades_pnl.len() as i64;
+
+        let (total_return, sharpe, mdd, win_rate, profit_factor) = if trade_count == 0 {
+            // Zero-trade edge case
+            warn!(
+                "Backtest {}: simulation produced zero completed trades.",
+                backtest_id
+            );
+            (0.0, 0.0, 0.0, 0.0, 0.0)
+        } else {
+            let tr = (final_equity - backtest.initial_balance) / backtest.initial_balance;
+            let s = calculate_sharpe_ratio(&returns, 0.0);
+            let equity: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+            let d = calculate_max_drawdown(&equity);
+            let wr = calculate_win_rate(&trades_pnl);
+            let pf = {
+                let raw = calculate_profit_factor(&trades_pnl);
+                // Persist f64::INFINITY as a very large finite number (SQLite REAL limitation)
+                if raw.is_infinite() { f64::MAX } else { raw }
+            };
+            (tr, s, d, wr, pf)
+        };
+
+        let timestamps: Vec<DateTime<Utc>> = equity_curve.iter().map(|p| p.timestamp).collect();
+        let equity: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+        let exposure: Vec<f64> = equity_curve.iter().map(|p| p.exposure).collect();
+        let traded_notional: f64 = closed_trades
+            .iter()
+            .map(|t| t.quantity * (t.entry_price + t.exit_price.unwrap_or(0.0)))
+            .sum();
+        let tearsheet = Tearsheet::compute(
+            &timestamps,
+            &equity,
+            &exposure,
+            &trades_pnl,
+            traded_notional,
+            calendar,
+        );
+
+        // Buy-and-hold of the benchmark over the same period, without warm-up
+        let benchmark_symbol = backtest
+            .benchmark_symbol
+            .clone()
+            .unwrap_or_else(|| backtest.symbol.clone());
+        let benchmark = self
+            .storage_actor
+            .ask(QueryOHLCV {
+                symbol: benchmark_symbol.clone(),
+                ts_ref: TimeSeriesRef::new(
+                    "ohlcv".to_string(),
+                    vec![],
+                    backtest.start_time,
+                    backtest.end_time,
+                ),
+                adjustment: PriceAdjustment::Total,
+            })
+            .await;
+        let comparison = match benchmark {
+            Ok(series) if !series.is_empty() => {
+                fill_benchmark(&mut equity_curve, &series, backtest.initial_balance);
+                let benchmark_equity: Vec<f64> = equity_curve
+                    .iter()
+                    .map(|p| p.benchmark_equity.unwrap_or(backtest.initial_balance))
+                    .collect();
+                Some(BenchmarkComparison::compute(
+                    &timestamps,
+                    &equity,
+                    &benchmark_equity,
+                    calendar,
+                ))
+            }
+            Ok(_) => {
+                warn!(
+                    "Backtest {}: no data for benchmark {}; skipping comparison",
+                    backtest_id, benchmark_symbol
+                );
+                None
+            }
+            Err(e) => {
+                warn!(
+                    "Backtest {}: failed to load benchmark {}: {}",
+                    backtest_id, benchmark_symbol, e
+                );
+                None
+            }
+        };
+
+        // ── 12. Persist Results ───────────────────────────────────────────────
+        // Trades, curve and metrics are saved together or not at all, and
+        // only while this worker still holds the run
+        let saved = async {
+            let mut tx = self.pool.begin().await.map_err(AppError::Database)?;
+            BacktestTrade::save_trades(backtest_id, trades, &mut tx).await?;
+            EquityPoint::save_curve(backtest_id, &equity_curve, &mut tx).await?;
+            if let Some(comparison) = &comparison {
+                Backtest::update_benchmark(backtest_id, comparison, &mut tx).await?;
+            }
+            let completed = Backtest::update_results(
+                backtest_id,
+                lease_id,
+                final_equity,
+                total_return,
+                sharpe,
+                mdd,
+                Some(run_config),
+                trade_count,
+                win_rate,
+                profit_factor,
+                &tearsheet,
+                &mut tx,
+            )
+            .await?;
+            if completed {
+                tx.commit().await.map_err(AppError::Database)?;
+            } else {
+                tx.rollback().await.map_err(AppError::Database)?;
+            }
+            Ok(completed)
+        };
+        self.check_saved(backtest_id, lease_id, saved.await).await?;
+
+        info!(
+            "Backtest {} completed. Trades: {}, Final Equity: {:.2}, Return: {:.2}%, \
+             Sharpe: {:.3}, MDD: {:.2}%, Win Rate: {:.1}%, Profit Factor: {:.3}",
+            backtest_id,
+            trade_count,
+            final_equity,
+            total_return * 100.0,
+            sharpe,
+            mdd * 100.0,
+            win_rate * 100.0,
+            profit_factor,
+        );
+
+        Ok(equity_curve)
+    }
+
+    /// Fail the run when its results could not be saved, and stop it when it
+    /// was cancelled or reassigned first. Results are written in one
+    /// transaction, so neither case leaves anything of the run behind.
+    async fn check_saved(
+        &self,
+        backtest_id: &str,
+        lease_id: &str,
+        saved: crate::error::Result<bool>,
+    ) -> ActorResult<()> {
+        let e = match saved {
+            Ok(true) => return Ok(()),
+            Ok(false) => {
+                info!(
+                    "Backtest {} was cancelled or lost its lease before its results were saved",
+                    backtest_id
+                );
+                return Err(ActorError::Cancelled(backtest_id.to_string()));
+            }
+            Err(e) => e,
+        };
+        let err_msg = format!("Failed to save backtest results: {}", e);
+        self.fail(backtest_id, lease_id, &err_msg).await;
+        Err(ActorError::DatabaseError(err_msg))
+    }
+
+    /// Fail the run held under `lease_id`. A run cancelled or handed to
+    /// another worker meanwhile keeps the status its new owner gives it.
+    async fn fail(&self, backtest_id: &str, lease_id: &str, err_msg: &str) {
+        if let Err(e) = Backtest::fail(backtest_id, lease_id, err_msg, &self.pool).await {
+            warn!("Failed to mark backtest {} failed: {}", backtest_id, e);
         }
     }
 }
 
+/// Renews a run's lease in the background for as long as it is held, so
+/// loading data, vectorized runs and the final save, which never reach the
+/// event loop's progress reports, do not look stalled to the lease monitor.
+struct Heartbeat(tokio::task::JoinHandle<()>);
+
+impl Heartbeat {
+    fn start(pool: Pool<Sqlite>, backtest_id: String, lease_id: String) -> Self {
+        Self(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+            // The first tick completes at once, and the run has just started
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match Backtest::heartbeat(&backtest_id, &lease_id, &pool).await {
+                    Ok(true) => {}
+                    // Cancelled or reassigned: the run finds out when it next
+                    // reports progress or saves
+                    Ok(false) => break,
+                    Err(e) => warn!("Failed to renew lease of backtest {}: {}", backtest_id, e),
+                }
+            }
+        }))
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Releases a run's place in its worker's queue when the run ends, however
+/// it ends.
+struct QueueSlot(Arc<AtomicUsize>);
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct Worker {
+    actor: ActorRef<BacktestActor>,
+    queued: Arc<AtomicUsize>,
+}
+
+/// A pool of `BacktestActor`s. Each actor runs one backtest at a time, so
+/// each run goes to the worker with the fewest runs queued, letting short
+/// runs pass a long one and independent backtests (e.g. the runs of a
+/// sweep) execute in parallel.
+#[derive(Clone)]
+pub struct BacktestWorkers {
+    workers: Arc<[Worker]>,
+}
+
+impl BacktestWorkers {
+    /// Spawn `count` workers (at least one) sharing the database and storage actor.
+    pub fn spawn(
+        count: usize,
+        pool: Pool<Sqlite>,
+        storage_actor: ActorRef<TimeSeriesStorageActor>,
+        mailbox_size: usize,
+    ) -> Self {
+        let workers: Vec<Worker> = (0..count.max(1))
+            .map(|_| {
+                let actor = BacktestActor::new(pool.clone(), storage_actor.clone());
+                let queued = actor.queued.clone();
+                Worker {
+                    actor: BacktestActor::spawn_with_mailbox(actor, mailbox::bounded(mailbox_size)),
+                    queued,
+                }
+            })
+            .collect();
+        Self {
+            workers: workers.into(),
+        }
+    }
+
+    /// The least busy worker, with the run about to be sent counted against
+    /// it. The worker releases the count when the run ends.
+    fn next_worker(&self) -> &Worker {
+        let worker = self
+            .workers
+            .iter()
+            .min_by_key(|w| w.queued.load(Ordering::Relaxed))
+            .expect("at least one worker");
+        worker.queued.fetch_add(1, Ordering::Relaxed);
+        worker
+    }
+
+    /// Queue a backtest on the least busy worker (fire-and-forget).
+    pub async fn run(&self, backtest_id: String) {
+        let worker = self.next_worker();
+        if let Err(e) = worker
+            .actor
+            .tell(RunBacktest {
+                backtest_id: backtest_id.clone(),
+            })
+            .send()
+            .await
+        {
+            worker.queued.fetch_sub(1, Ordering::Relaxed);
+            tracing::error!("Failed to queue backtest {}: {}", backtest_id, e);
+        }
+    }
+
+    /// Recover the backtests a previous process left pending or running,
+    /// returning how many were found.
+    pub async fn recover_orphans(
+        &self,
+        pool: &Pool<Sqlite>,
+        recovery: BacktestRecovery,
+    ) -> crate::error::Result<usize> {
+        let orphans = Backtest::find_unfinished(pool).await?;
+        let count = orphans.len();
+        self.recover(
+            orphans,
+            Utc::now(),
+            "interrupted by a server restart",
+            recovery,
+            pool,
+        )
+        .await;
+        Ok(count)
+    }
+
+    /// Recover running backtests whose worker has not heartbeated for
+    /// `lease`, returning how many were found.
+    pub async fn recover_stale(
+        &self,
+        pool: &Pool<Sqlite>,
+        lease: Duration,
+        recovery: BacktestRecovery,
+    ) -> crate::error::Result<usize> {
+        let lease_age = chrono::Duration::from_std(lease).unwrap_or(chrono::Duration::MAX);
+        let cutoff = Utc::now() - lease_age;
+        let stale = Backtest::find_stale(cutoff, pool).await?;
+        let count = stale.len();
+        let reason = format!("no heartbeat from its worker for {}s", lease.as_secs());
+        self.recover(stale, cutoff, &reason, recovery, pool).await;
+        Ok(count)
+    }
+
+    /// Check for stale backtests every half `lease` for as long as the
+    /// process runs.
+    pub fn spawn_lease_monitor(
+        &self,
+        pool: Pool<Sqlite>,
+        lease: Duration,
+        recovery: BacktestRecovery,
+    ) -> tokio::task::JoinHandle<()> {
+        let workers = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval((lease / 2).max(Duration::from_secs(1)));
+            loop {
+                interval.tick().await;
+                if let Err(e) = workers.recover_stale(&pool, lease, recovery).await {
+                    warn!("Failed to check backtest leases: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Resume or fail `backtests`, each only while it is still unfinished.
+    /// Failing also spares runs that heartbeated after `cutoff`.
+    async fn recover(
+        &self,
+        backtests: Vec<Backtest>,
+        cutoff: DateTime<Utc>,
+        reason: &str,
+        recovery: BacktestRecovery,
+        pool: &Pool<Sqlite>,
+    ) {
+        for backtest in backtests {
+            let result = match recovery {
+                BacktestRecovery::Resume => match Backtest::requeue(&backtest.id, pool).await {
+                    Ok(true) => {
+                        info!("Re-running backtest {}: {}", backtest.id, reason);
+                        self.run(backtest.id.clone()).await;
+                        Ok(())
+                    }
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                },
+                BacktestRecovery::Fail => {
+                    let message = format!("Backtest was {}", reason);
+                    match Backtest::fail_stale(&backtest.id, cutoff, &message, pool).await {
+                        Ok(true) => {
+                            warn!("Failed backtest {}: {}", backtest.id, reason);
+                            Ok(())
+                        }
+                        Ok(false) => Ok(()),
+                        Err(e) => Err(e),
+                    }
+                }
+            };
+            if let Err(e) = result {
+                warn!("Failed to recover backtest {}: {}", backtest.id, e);
+            }
+        }
+    }
+
+    /// Run a backtest on the least busy worker and wait for its equity curve.
+    pub async fn run_and_wait(&self, backtest_id: String) -> ActorResult<Vec<EquityPoint>> {
+        let worker = self.next_worker();
+        worker
+            .actor
+            .ask(RunBacktest { backtest_id })
+            .await
+            .map_err(|e| match e {
+                SendError::HandlerError(e) => e,
+                other => {
+                    // Undelivered, so the worker never took the run
+                    if matches!(
+                        other,
+                        SendError::ActorNotRunning(_)
+                            | SendError::MailboxFull(_)
+                            | SendError::Timeout(Some(_))
+                    ) {
+                        worker.queued.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    ActorError::ActorUnavailable(other.to_string())
+                }
+            })
+    }
+}
+
 impl Message<RunBacktest> for BacktestActor {
-    type Reply = ActorResult<()>;
+    /// The run's equity curve, one point per bar timestamp.
+    type Reply = ActorResult<Vec<EquityPoint>>;
 
     async fn handle(
         &mut self,
         msg: RunBacktest,
         _ctx: &mut Context<Self, Self::Reply>,
     ) -> Self::Reply {
+        let _slot = QueueSlot(self.queued.clone());
         let backtest_id = msg.backtest_id;
 
         // ── 1. Fetch Backtest metadata ────────────────────────────────────────
@@ -47,8 +1131,30 @@ impl Message<RunBacktest> for BacktestActor {
             .map_err(|e| ActorError::DatabaseError(e.to_string()))?;
 
         // ── 2. Update status → Running ────────────────────────────────────────
-        let _ =
-            Backtest::update_status(&backtest_id, BacktestStatus::Running, None, &self.pool).await;
+        let Some(lease_id) = Backtest::start(&backtest_id, &self.pool)
+            .await
+            .map_err(|e| ActorError::DatabaseError(e.to_string()))?
+        else {
+            info!("Backtest {} was cancelled before it started", backtest_id);
+            return Err(ActorError::Cancelled(backtest_id));
+        };
+        let _heartbeat = Heartbeat::start(self.pool.clone(), backtest_id.clone(), lease_id.clone());
+
+        // Failures the run has not already recorded, so none leaves it running
+        let result = self.run(backtest, &lease_id).await;
+        if let Err(e) = &result
+            && !matches!(e, ActorError::Cancelled(_))
+        {
+            self.fail(&backtest_id, &lease_id, &e.to_string()).await;
+        }
+        result
+    }
+}
+
+impl BacktestActor {
+    /// Simulate a started run under `lease_id` and save its results.
+    async fn run(&self, backtest: Backtest, lease_id: &str) -> ActorResult<Vec<EquityPoint>> {
+        let backtest_id = backtest.id.clone();
 
         // ── 3. Fetch Strategy metadata ────────────────────────────────────────
         let strategy_model = Strategy::find_by_id(&backtest.strategy_id, &self.pool)
@@ -56,341 +1162,626 @@ impl Message<RunBacktest> for BacktestActor {
             .map_err(|e| ActorError::DatabaseError(e.to_string()))?;
 
         // ── 4. Instantiate strategy logic ─────────────────────────────────────
-        let strategy_type = StrategyType::from_str(&strategy_model.strategy_type)
-            .map_err(|e| ActorError::InvalidInput(e))?;
-
-        let strategy_params: serde_json::Value =
-            serde_json::from_str(&strategy_model.parameters)
-                .unwrap_or_else(|_| serde_json::json!({}));
-
-        let (mut strategy, lookback): (Box<dyn StrategyLogic>, usize) = match strategy_type {
-            StrategyType::Classical => {
-                let fast = strategy_params["fast_period"].as_u64().unwrap_or(10) as usize;
-                let slow = strategy_params["slow_period"].as_u64().unwrap_or(20) as usize;
-                let lookback = slow;
-                (Box::new(MovingAverageCrossover::new(fast, slow)), lookback)
-            }
-            _ => {
-                let err_msg = "Unsupported strategy type for backtesting".to_string();
-                let _ = Backtest::update_status(
-                    &backtest_id,
-                    BacktestStatus::Failed,
-                    Some(err_msg.clone()),
-                    &self.pool,
-                )
-                .await;
+        // Sweep runs carry their own parameters in place of the strategy's
+        let parameters = backtest
+            .parameters
+            .as_deref()
+            .unwrap_or(&strategy_model.parameters);
+        let built = match StrategyRegistry::global().build(&strategy_model.strategy_type, parameters)
+        {
+            Ok(built) => built,
+            Err(e) => {
+                let err_msg = format!("Cannot backtest strategy: {}", e);
+                self.fail(&backtest_id, lease_id, &err_msg).await;
                 return Err(ActorError::InvalidInput(err_msg));
             }
         };
+        // Vectorized runs need the strategy's signal expression, and nothing
+        // the engine cannot express
+        let vectorized = match backtest.engine() {
+            BacktestEngine::Event => None,
+            BacktestEngine::Vectorized => match VectorizedStrategy::new(
+                &built,
+                backtest.symbol_list().len(),
+                &backtest.slippage_model(),
+                &backtest.margin_model(),
+            ) {
+                Ok(strategy) => Some(strategy),
+                Err(e) => {
+                    let err_msg = format!("Cannot backtest strategy: {}", e);
+                    self.fail(&backtest_id, lease_id, &err_msg).await;
+                    return Err(ActorError::InvalidInput(err_msg));
+                }
+            },
+        };
+        let sizer = built.sizer;
+        let exits = built.exits;
+        let lookback = built.warmup;
+
+        let symbols = backtest.symbol_list();
+        let legs = built.logic.symbols();
+
+        // Every leg the strategy trades must be part of the portfolio
+        let missing_legs: Vec<String> = legs
+            .iter()
+            .filter(|s| !symbols.contains(s))
+            .cloned()
+            .collect();
+        if !missing_legs.is_empty() {
+            let err_msg = format!(
+                "Strategy also trades {:?}, but this backtest only covers {:?}",
+                missing_legs, symbols
+            );
+            self.fail(&backtest_id, lease_id, &err_msg).await;
+            return Err(ActorError::InvalidInput(err_msg));
+        }
+
+        // Single-symbol strategies run one independent instance per symbol so
+        // their indicators never mix series; multi-leg strategies see every
+        // bar through the one instance.
+        let mut instances: HashMap<String, Box<dyn StrategyLogic>> = HashMap::new();
+        if legs.is_empty() {
+            for symbol in &symbols[1..] {
+                let instance = StrategyRegistry::global()
+                    .build(&strategy_model.strategy_type, parameters)
+                    .map_err(ActorError::InvalidInput)?;
+                instances.insert(symbol.clone(), instance.logic);
+            }
+            instances.insert(symbols[0].clone(), built.logic);
+        } else {
+            instances.insert(String::new(), built.logic);
+        }
+
+        // Session rules and annualisation follow each symbol's calendar
+        let mut calendars: HashMap<String, TradingCalendar> = HashMap::new();
+        for symbol in &symbols {
+            match self.symbol_calendar(&backtest, symbol).await {
+                Ok(calendar) => {
+                    calendars.insert(symbol.clone(), calendar);
+                }
+                Err(e) => {
+                    let err_msg = format!("Backtest failed - {}", e);
+                    self.fail(&backtest_id, lease_id, &err_msg).await;
+                    return Err(ActorError::InvalidInput(err_msg));
+                }
+            }
+        }
+        let calendar_names: HashMap<&String, &String> = calendars
+            .iter()
+            .map(|(symbol, calendar)| (symbol, &calendar.name))
+            .collect();
 
         // ── 5. Build run_config snapshot ──────────────────────────────────────
         let run_config = serde_json::json!({
             "strategy_name": strategy_model.name,
             "strategy_type": strategy_model.strategy_type,
-            "parameters": strategy_params,
+            "strategy_kind": built.kind,
+            "parameters": built.parameters,
+            "sizing": sizer.describe(),
+            "exits": exits.describe(),
             "commission_rate": backtest.commission_rate,
+            "commission_model": backtest.commission_model(),
             "slippage_bps": backtest.slippage_bps,
+            "slippage_model": backtest.slippage_model(),
+            "allow_short": backtest.allow_short,
+            "borrow_fee_rate": backtest.borrow_fee_rate,
+            "short_margin_rate": backtest.short_margin_rate,
+            "margin_model": backtest.margin_model(),
+            "fill_model": backtest.fill_model(),
+            "calendars": calendar_names,
+            "engine": backtest.engine(),
             "symbol": backtest.symbol,
+            "symbols": symbols,
             "start_time": backtest.start_time,
             "end_time": backtest.end_time,
+            "warmup_start_time": backtest.warmup_start_time,
+            "benchmark_symbol": backtest.benchmark_symbol,
         })
         .to_string();
 
-        // ── 6. Query historical data ──────────────────────────────────────────
-        let ohlcv_result = self
-            .storage_actor
-            .ask(QueryOHLCV {
-                symbol: backtest.symbol.clone(),
-                ts_ref: TimeSeriesRef::new(
-                    "ohlcv".to_string(),
-                    vec![],
-                    backtest.start_time,
-                    backtest.end_time,
-                ),
-            })
-            .await;
+        // ── 6. Query historical data for every symbol ─────────────────────────
+        // Bars before `start_time` only warm up indicators
+        let query_start = backtest
+            .warmup_start_time
+            .map_or(backtest.start_time, |warmup| warmup.min(backtest.start_time));
+        let mut bars: Vec<Bar> = Vec::new();
+        let mut empty_symbols: Vec<String> = Vec::new();
+        let mut longest_series = 0usize;
+        let mut corporate_actions = 0usize;
+        // Each symbol's bar size, for placing its bars within sessions
+        let mut bar_seconds: HashMap<String, f64> = HashMap::new();
 
-        let ohlcv_data = match ohlcv_result {
-            Ok(data) => data,
-            Err(e) => {
-                let err_msg = format!("Backtest failed - Storage error: {}", e);
-                let _ = Backtest::update_status(
-                    &backtest_id,
-                    BacktestStatus::Failed,
-                    Some(err_msg.clone()),
-                    &self.pool,
-                )
+        for symbol in &symbols {
+            let ts_ref =
+                TimeSeriesRef::new("ohlcv".to_string(), vec![], query_start, backtest.end_time);
+            let ohlcv_result = self
+                .storage_actor
+                .ask(QueryOHLCV {
+                    symbol: symbol.clone(),
+                    ts_ref: ts_ref.clone(),
+                    adjustment: PriceAdjustment::Splits,
+                })
                 .await;
-                return Err(ActorError::Internal(err_msg));
+            let actions_result = match ohlcv_result {
+                Ok(series) => self
+                    .storage_actor
+                    .ask(QueryCorporateActions {
+                        symbol: symbol.clone(),
+                        ts_ref,
+                    })
+                    .await
+                    .map(|actions| (series, actions))
+                    .map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+
+            let (series, actions) = match actions_result {
+                Ok(data) => data,
+                Err(e) => {
+                    let err_msg = format!("Backtest failed - Storage error: {}", e);
+                    self.fail(&backtest_id, lease_id, &err_msg).await;
+                    return Err(ActorError::Internal(err_msg));
+                }
+            };
+
+            if series.is_empty() {
+                empty_symbols.push(symbol.clone());
             }
-        };
+            longest_series = longest_series.max(series.len());
+            corporate_actions += actions.len();
+            let timestamps: Vec<DateTime<Utc>> = series.iter().map(|bar| bar.timestamp).collect();
+            bar_seconds.insert(
+                symbol.clone(),
+                median_spacing_secs(&timestamps).unwrap_or(0.0),
+            );
+            bars.extend(build_bars(symbol, series, &actions));
+        }
 
-        if ohlcv_data.is_empty() {
-            let err_msg = "No data found for the given period".to_string();
-            let _ = Backtest::update_status(
-                &backtest_id,
-                BacktestStatus::Failed,
-                Some(err_msg.clone()),
-                &self.pool,
-            )
-            .await;
+        if corporate_actions > 0 {
+            info!(
+                "Backtest {}: adjusting for {} corporate action(s)",
+                backtest_id, corporate_actions
+            );
+        }
+
+        if !empty_symbols.is_empty() {
+            let err_msg = if empty_symbols.len() == symbols.len() {
+                "No data found for the given period".to_string()
+            } else {
+                format!("No data found for {:?} in the given period", empty_symbols)
+            };
+            self.fail(&backtest_id, lease_id, &err_msg).await;
             return Err(ActorError::InvalidInput(err_msg));
         }
 
+        // Merge into one time-ordered event stream. The sort is stable, so bars
+        // sharing a timestamp keep the order of `symbols`.
+        bars.sort_by_key(|bar| bar.candle.timestamp);
+        let bars_total = bars.len() as i64;
+        self.report_progress(&backtest_id, lease_id, 0, bars_total)
+            .await?;
+
         // ── 7. Edge case: insufficient data for strategy lookback ─────────────
-        if ohlcv_data.len() < lookback {
+        if longest_series < lookback {
             warn!(
                 "Backtest {}: only {} data points but strategy requires {}. \
                  Completing with zero metrics.",
                 backtest_id,
-                ohlcv_data.len(),
+                longest_series,
                 lookback
             );
-            Backtest::update_results(
-                &backtest_id,
+            let equity_curve = vec![EquityPoint::new(
+                backtest.start_time,
                 backtest.initial_balance,
                 0.0,
-                0.0,
-                0.0,
-                Some(run_config),
-                0,
-                0.0,
-                0.0,
-                &self.pool,
-            )
-            .await
-            .map_err(|e| ActorError::DatabaseError(e.to_string()))?;
-            return Ok(());
+            )];
+            let saved = async {
+                let mut tx = self.pool.begin().await.map_err(AppError::Database)?;
+                EquityPoint::save_curve(&backtest_id, &equity_curve, &mut tx).await?;
+                let completed = Backtest::update_results(
+                    &backtest_id,
+                    lease_id,
+                    backtest.initial_balance,
+                    0.0,
+                    0.0,
+                    0.0,
+                    Some(run_config),
+                    0,
+                    0.0,
+                    0.0,
+                    &Tearsheet::default(),
+                    &mut tx,
+                )
+                .await?;
+                if completed {
+                    tx.commit().await.map_err(AppError::Database)?;
+                } else {
+                    tx.rollback().await.map_err(AppError::Database)?;
+                }
+                Ok(completed)
+            };
+            self.check_saved(&backtest_id, lease_id, saved.await)
+                .await?;
+            return Ok(equity_curve);
         }
 
         info!(
-            "Starting backtest simulation for {} with {} data points",
+            "Starting backtest simulation for {} with {} data points across {} symbol(s)",
             backtest_id,
-            ohlcv_data.len()
+            bars.len(),
+            symbols.len()
         );
 
-        // ── 8. Initialise broker and simulation state ─────────────────────────
-        let mut broker =
-            BacktestBroker::new(backtest.commission_rate, backtest.slippage_bps);
+        // Vectorized runs simulate every bar at once, without the loop
+        if let Some(strategy) = &vectorized {
+            let candles: Vec<OHLCV> = bars.iter().map(|bar| bar.candle.clone()).collect();
+            let signals: Vec<OHLCV> = bars.iter().map(|bar| bar.signal.clone()).collect();
+            let dividends: Vec<f64> = bars.iter().map(|bar| bar.dividend).collect();
+            let run = match vectorized::simulate(
+                &backtest,
+                strategy,
+                &symbols[0],
+                &candles,
+                &signals,
+                &dividends,
+            ) {
+                Ok(run) => run,
+                Err(e) => {
+                    let err_msg = format!("Backtest failed - {}", e);
+                    self.fail(&backtest_id, lease_id, &err_msg).await;
+                    return Err(ActorError::InvalidInput(err_msg));
+                }
+            };
+            return self
+                .finish(
+                    &backtest,
+                    lease_id,
+                    run.equity_curve,
+                    &run.trades,
+                    run.final_balance,
+                    run_config,
+                    &calendars[&symbols[0]],
+                )
+                .await;
+        }
 
-        let mut balance = backtest.initial_balance;
-        let mut equity_curve = vec![balance];
-        let mut returns: Vec<f64> = Vec::new();
-        let mut trades_pnl: Vec<f64> = Vec::new();
+        // ── 8. Initialise broker and simulation state ─────────────────────────
+        let fill_model = backtest.fill_model();
+        let mut sim = Simulation {
+            backtest: &backtest,
+            broker: BacktestBroker::new(backtest.commission_rate, backtest.slippage_bps)
+                .with_commission_model(backtest.commission_model())
+                .with_slippage_model(backtest.slippage_model())
+                .with_fill_model(fill_model),
+            sizer,
+            exits,
+            margin: backtest.margin_model(),
+            balance: backtest.initial_balance,
+            positions: HashMap::new(),
+            last_prices: HashMap::new(),
+            last_bars: HashMap::new(),
+            closed_trades: Vec::new(),
+            working: HashMap::new(),
+            accrued_to: backtest.start_time,
+            interest: 0.0,
+        };
+        let mut equity_curve = vec![sim.equity_point(backtest.start_time)];
 
-        let mut current_position_qty = 0.0f64;
-        let mut active_trade_id: Option<String> = None;
+        let mut last_times: HashMap<String, DateTime<Utc>> = HashMap::new();
+        // Signals waiting for their symbol's next bar under `NextBarOpen`
+        let mut pending: HashMap<String, Vec<(SignalKind, StrategySignal)>> = HashMap::new();
 
         // ── 9. Main simulation loop ───────────────────────────────────────────
-        for candle in &ohlcv_data {
-            let signal = strategy.update(candle);
-
-            if let Some(signal_type) = signal {
-                match signal_type {
-                    SignalType::Buy if current_position_qty == 0.0 => {
-                        // Set current price on broker then request a fill
-                        broker.set_price(candle.close);
-                        let fill = broker
-                            .submit_market_order(&backtest.symbol, &OrderSide::Buy, 1.0)
-                            .await
-                            .map_err(|e| ActorError::Internal(e.to_string()))?;
-
-                        // Determine how many units we can afford at the filled price
-                        let commission = fill.commission.unwrap_or(0.0);
-                        let affordable_qty =
-                            (balance - commission) / fill.fill_price;
-
-                        if affordable_qty <= 0.0 {
-                            // Cannot afford even one unit — skip
-                            continue;
-                        }
+        let mut last_report = Instant::now();
+        for (i, bar) in bars.iter().enumerate() {
+            let (bar_symbol, candle) = (&bar.symbol, &bar.candle);
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                self.report_progress(&backtest_id, lease_id, i as i64, bars_total)
+                    .await?;
+                last_report = Instant::now();
+            }
 
-                        let actual_cost = affordable_qty * fill.fill_price;
-                        let actual_commission = actual_cost * backtest.commission_rate;
-                        balance -= actual_cost + actual_commission;
-                        current_position_qty = affordable_qty;
-
-                        // Record trade entry
-                        let trade_result = BacktestTrade::create(
-                            &backtest_id,
-                            &backtest.symbol,
-                            "buy",
-                            current_position_qty,
-                            fill.fill_price,
-                            candle.timestamp,
-                            &self.pool,
-                        )
-                        .await;
-
-                        match trade_result {
-                            Ok(trade) => active_trade_id = Some(trade.id),
-                            Err(e) => {
-                                tracing::error!(
-                                    "Failed to create backtest trade record: {}",
-                                    e
-                                )
-                            }
-                        }
-                    }
+            // Cash earns (or, when borrowed, pays) interest from the start of
+            // the run; warm-up bars come before it
+            if candle.timestamp > sim.accrued_to {
+                sim.accrue_interest(candle.timestamp);
+            }
 
-                    SignalType::Sell if current_position_qty > 0.0 => {
-                        // Fill the sell order through the broker
-                        broker.set_price(candle.close);
-                        let fill = broker
-                            .submit_market_order(
-                                &backtest.symbol,
-                                &OrderSide::Sell,
-                                current_position_qty,
-                            )
-                            .await
-                            .map_err(|e| ActorError::Internal(e.to_string()))?;
-
-                        let gross_proceeds = fill.fill_price * fill.fill_quantity;
-                        let commission = fill.commission.unwrap_or(0.0);
-                        let net_proceeds = gross_proceeds - commission;
-                        balance += net_proceeds;
-
-                        // Close out the trade record and record metrics
-                        if let Some(trade_id) = active_trade_id.take() {
-                            match BacktestTrade::find_by_id(&trade_id, &self.pool).await {
-                                Ok(trade) => {
-                                    let pnl = (fill.fill_price - trade.entry_price)
-                                        * trade.quantity;
-                                    let pct_return = (fill.fill_price - trade.entry_price)
-                                        / trade.entry_price;
-                                    returns.push(pct_return);
-                                    trades_pnl.push(pnl);
-
-                                    let _ = BacktestTrade::close_trade(
-                                        &trade_id,
-                                        fill.fill_price,
-                                        candle.timestamp,
-                                        pnl,
-                                        pct_return,
-                                        &self.pool,
-                                    )
-                                    .await;
-                                }
-                                Err(e) => tracing::error!(
-                                    "Failed to find trade {} during backtest: {}",
-                                    trade_id,
-                                    e
-                                ),
-                            }
-                        }
+            // Positions held into an ex-date receive the dividend in cash;
+            // shorts pay it to the lender
+            if bar.dividend != 0.0
+                && let Some(position) = sim.positions.get_mut(bar_symbol)
+            {
+                let paid = position.quantity * bar.dividend;
+                sim.balance += paid;
+                position.dividends += paid;
+            }
 
-                        current_position_qty = 0.0;
-                    }
+            // Accrue borrow fees on a short for the time since its last bar
+            if let Some(position) = sim.positions.get_mut(bar_symbol)
+                && position.quantity < 0.0
+                && let Some(previous) = last_times.get(bar_symbol)
+            {
+                let years = (candle.timestamp - *previous).num_seconds() as f64 / SECONDS_PER_YEAR;
+                let fee = -position.quantity * candle.close * backtest.borrow_fee_rate * years;
+                sim.balance -= fee;
+                position.borrow_cost += fee;
+            }
 
-                    _ => {}
+            // Perpetual funding is exchanged at every interval boundary since
+            // the symbol's last bar: longs pay shorts when the rate is positive
+            if let Some(position) = sim.positions.get_mut(bar_symbol)
+                && let Some(previous) = last_times.get(bar_symbol)
+            {
+                let periods = sim.margin.funding_periods(*previous, candle.timestamp);
+                if periods > 0 {
+                    let payment =
+                        position.quantity * candle.close * sim.margin.funding_rate * periods as f64;
+                    sim.balance -= payment;
+                    position.funding += payment;
                 }
             }
 
-            let current_equity = balance + (current_position_qty * candle.close);
-            equity_curve.push(current_equity);
-        }
+            // Orders the volume cap held back carry on into this bar
+            if sim.working.contains_key(bar_symbol) {
+                sim.broker.set_bar(candle);
+                sim.resume(bar_symbol, candle.timestamp).await?;
+            }
 
-        // ── 10. Close any open position at the last candle price ──────────────
-        if current_position_qty > 0.0 {
-            let last_candle = ohlcv_data.last().unwrap();
-            broker.set_price(last_candle.close);
-            let fill = broker
-                .submit_market_order(
-                    &backtest.symbol,
-                    &OrderSide::Sell,
-                    current_position_qty,
-                )
-                .await
-                .map_err(|e| ActorError::Internal(e.to_string()))?;
+            // Orders signalled on the previous bar fill at this bar's open,
+            // sized on what was known before it
+            if let Some(orders) = pending.remove(bar_symbol) {
+                sim.broker.set_bar(candle);
+                for (kind, signal) in orders {
+                    sim.apply_signal(kind, &signal, candle.timestamp).await?;
+                }
+            }
 
-            let gross_proceeds = fill.fill_price * fill.fill_quantity;
-            let commission = fill.commission.unwrap_or(0.0);
-            balance += gross_proceeds - commission;
+            sim.last_prices.insert(bar_symbol.clone(), candle.close);
+            sim.last_bars.insert(bar_symbol.clone(), candle);
+            last_times.insert(bar_symbol.clone(), candle.timestamp);
+            sim.sizer.on_bar(bar_symbol, candle);
+            sim.exits.on_bar(bar_symbol, candle);
 
-            warn!(
-                "Backtest {}: simulation ended with open position; closed {} units @ {:.4}",
-                backtest_id, current_position_qty, fill.fill_price
+            // Risk exits trigger intrabar, before the strategy sees the close
+            let stop_hit = sim
+                .positions
+                .get_mut(bar_symbol)
+                .and_then(|p| p.stops.as_mut())
+                .and_then(|stops| stops.check(candle));
+            if let Some((reason, exit_price)) = stop_hit {
+                sim.set_exit_price(bar_symbol, exit_price);
+                sim.close(bar_symbol, candle.timestamp, reason).await?;
+            }
+            sim.check_margin(candle.timestamp).await?;
+
+            let strategy = match instances.get_mut(bar_symbol) {
+                Some(strategy) => strategy,
+                None => instances.get_mut("").expect("multi-leg instance"),
+            };
+            let kind = strategy.signal_kind();
+            let signals = strategy.on_bar(bar_symbol, &bar.signal);
+
+            // Warm-up bars feed indicators but never trade
+            if candle.timestamp < backtest.start_time {
+                continue;
+            }
+
+            // Session rules: be flat by the close, and open nothing on the
+            // bar that gets there
+            let closing = sim.exits.is_closing_bar(
+                candle.timestamp,
+                bar_seconds[bar_symbol],
+                &calendars[bar_symbol],
             );
+            if closing {
+                if sim.positions.contains_key(bar_symbol) {
+                    sim.set_exit_price(bar_symbol, candle.close);
+                    sim.close(bar_symbol, candle.timestamp, ExitReason::SessionClose)
+                        .await?;
+                } else {
+                    sim.working.remove(bar_symbol);
+                }
+            }
+            let signals = if closing { Vec::new() } else { signals };
 
-            if let Some(trade_id) = active_trade_id.take() {
-                if let Ok(trade) = BacktestTrade::find_by_id(&trade_id, &self.pool).await {
-                    let pnl = (fill.fill_price - trade.entry_price) * trade.quantity;
-                    let pct_return = (fill.fill_price - trade.entry_price) / trade.entry_price;
-                    returns.push(pct_return);
-                    trades_pnl.push(pnl);
-
-                    let _ = BacktestTrade::close_trade(
-                        &trade_id,
-                        fill.fill_price,
-                        last_candle.timestamp,
-                        pnl,
-                        pct_return,
-                        &self.pool,
-                    )
-                    .await;
+            for signal in signals {
+                if fill_model == FillModel::NextBarOpen {
+                    pending
+                        .entry(signal.symbol.clone())
+                        .or_default()
+                        .push((kind, signal));
+                    continue;
                 }
+
+                // Legs are only priced once their first bar has arrived
+                let Some(bar) = sim.last_bars.get(&signal.symbol).copied() else {
+                    continue;
+                };
+                sim.broker.set_bar(bar);
+                sim.apply_signal(kind, &signal, candle.timestamp).await?;
             }
 
-            // Update final equity curve entry
-            if let Some(last) = equity_curve.last_mut() {
-                *last = balance;
+            // One equity point per timestamp, once every symbol's bar for it is in
+            let next_timestamp = bars.get(i + 1).map(|next| next.candle.timestamp);
+            if next_timestamp != Some(candle.timestamp) {
+                equity_curve.push(sim.equity_point(candle.timestamp));
             }
         }
 
-        let final_equity = balance;
-
-        // ── 11. Calculate Metrics ─────────────────────────────────────────────
-        let trade_count = trades_pnl.len() as i64;
+        if !pending.is_empty() {
+            info!(
+                "Backtest {}: dropped orders for {:?} signalled on the final bar",
+                backtest_id,
+                pending.keys().collect::<Vec<_>>()
+            );
+        }
+        if !sim.working.is_empty() {
+            info!(
+                "Backtest {}: data ended before orders for {:?} filled in full",
+                backtest_id,
+                sim.working.keys().collect::<Vec<_>>()
+            );
+        }
 
-        let (total_return, sharpe, mdd, win_rate, profit_factor) = if trade_count == 0 {
-            // Zero-trade edge case
-            warn!(
-                "Backtest {}: simulation produced zero completed trades.",
-                backtest_id
+        if sim.interest != 0.0 {
+            info!(
+                "Backtest {}: net interest on cash {:.4}",
+                backtest_id, sim.interest
             );
-            (0.0, 0.0, 0.0, 0.0, 0.0)
-        } else {
-            let tr = (final_equity - backtest.initial_balance) / backtest.initial_balance;
-            let s = calculate_sharpe_ratio(&returns, 0.0);
-            let d = calculate_max_drawdown(&equity_curve);
-            let wr = calculate_win_rate(&trades_pnl);
-            let pf = {
-                let raw = calculate_profit_factor(&trades_pnl);
-                // Persist f64::INFINITY as a very large finite number (SQLite REAL limitation)
-                if raw.is_infinite() { f64::MAX } else { raw }
-            };
-            (tr, s, d, wr, pf)
-        };
+        }
 
-        // ── 12. Persist Results ───────────────────────────────────────────────
-        Backtest::update_results(
-            &backtest_id,
+        // ── 10. Close any open positions at their last prices ─────────────────
+        if !sim.positions.is_empty() {
+            let last_timestamp = bars.last().map(|b| b.candle.timestamp).unwrap();
+            let mut open: Vec<(String, f64)> = sim
+                .positions
+                .iter()
+                .map(|(symbol, p)| (symbol.clone(), p.quantity))
+                .collect();
+            open.sort_by(|a, b| a.0.cmp(&b.0));
+
+            for (symbol, quantity) in open {
+                sim.set_exit_price(&symbol, sim.last_prices[&symbol]);
+                sim.close(&symbol, last_timestamp, ExitReason::EndOfData)
+                    .await?;
+
+                warn!(
+                    "Backtest {}: simulation ended with open position; closing {} {} units @ {:.4}",
+                    backtest_id, quantity, symbol, sim.broker.current_price
+                );
+            }
+
+            // Update final equity curve entry, valuing what the volume cap
+            // kept open at its last price
+            if let Some(last) = equity_curve.last_mut() {
+                last.equity = sim.equity();
+                last.exposure = sim.exposure();
+            }
+        }
+        let final_equity = sim.equity();
+        // Exits the volume cap held back at the end leave their trades open
+        let mut trades = sim.closed_trades;
+        trades.extend(sim.positions.into_values().map(|p| p.trade));
+        // Annualised on the first symbol's calendar
+        self.finish(
+            &backtest,
+            lease_id,
+            equity_curve,
+            &trades,
             final_equity,
-            total_return,
-            sharpe,
-            mdd,
-            Some(run_config),
-            trade_count,
-            win_rate,
-            profit_factor,
-            &self.pool,
+            run_config,
+            &calendars[&symbols[0]],
         )
         .await
-        .map_err(|e| ActorError::DatabaseError(e.to_string()))?;
+    }
+}
 
-        info!(
-            "Backtest {} completed. Trades: {}, Final Equity: {:.2}, Return: {:.2}%, \
-             Sharpe: {:.3}, MDD: {:.2}%, Win Rate: {:.1}%, Profit Factor: {:.3}",
-            backtest_id,
-            trade_count,
-            final_equity,
-            total_return * 100.0,
-            sharpe,
-            mdd * 100.0,
-            win_rate * 100.0,
-            profit_factor,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_only_ignores_sell_when_flat() {
+        assert_eq!(
+            position_change(SignalKind::Target, SignalType::Sell, 0.0, false),
+            (false, None)
         );
+        assert_eq!(
+            position_change(SignalKind::Target, SignalType::Sell, 2.0, false),
+            (true, None)
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn test_target_signals_flip_when_shorting_allowed() {
+        assert_eq!(
+            position_change(SignalKind::Target, SignalType::Sell, 2.0, true),
+            (true, Some(OrderSide::Sell))
+        );
+        assert_eq!(
+            position_change(SignalKind::Target, SignalType::Buy, -2.0, true),
+            (true, Some(OrderSide::Buy))
+        );
+        assert_eq!(
+            position_change(SignalKind::Target, SignalType::Sell, -2.0, true),
+            (false, None)
+        );
+    }
+
+    #[test]
+    fn test_event_signals_close_without_reopening() {
+        assert_eq!(
+            position_change(SignalKind::Event, SignalType::Buy, -2.0, true),
+            (true, None)
+        );
+        assert_eq!(
+            position_change(SignalKind::Event, SignalType::Sell, 0.0, true),
+            (false, Some(OrderSide::Sell))
+        );
+    }
+
+    #[test]
+    fn test_short_collateral_covers_proceeds_and_margin() {
+        let mut positions = HashMap::new();
+        positions.insert(
+            "A".to_string(),
+            OpenPosition {
+                quantity: -10.0,
+                entry_price: 20.0,
+                trade: BacktestTrade::open("bt", "A", "sell", 10.0, 20.0, Utc::now()),
+                borrow_cost: 0.0,
+                dividends: 0.0,
+                funding: 0.0,
+                commission: 0.0,
+                stops: None,
+                exited_quantity: 0.0,
+                exit_notional: 0.0,
+            },
+        );
+        positions.insert(
+            "B".to_string(),
+            OpenPosition {
+                quantity: 5.0,
+                entry_price: 8.0,
+                trade: BacktestTrade::open("bt", "B", "buy", 5.0, 8.0, Utc::now()),
+                borrow_cost: 0.0,
+                dividends: 0.0,
+                funding: 0.0,
+                commission: 0.0,
+                stops: None,
+                exited_quantity: 0.0,
+                exit_notional: 0.0,
+            },
+        );
+        let prices = HashMap::from([("A".to_string(), 20.0), ("B".to_string(), 8.0)]);
+
+        assert!((short_collateral(&positions, &prices, 0.5) - 300.0).abs() < 1e-9);
+        assert!((portfolio_equity(1000.0, &positions, &prices) - 840.0).abs() < 1e-9);
+
+        // Half of the long's value can be borrowed against at 50% initial margin
+        assert!((free_cash(1000.0, &positions, &prices, 0.5, 1.0) - 700.0).abs() < 1e-9);
+        assert!((free_cash(1000.0, &positions, &prices, 0.5, 0.5) - 720.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_affordable_quantity_leaves_room_for_commission() {
+        let mut broker = BacktestBroker::new(0.01, 0.0);
+        broker.set_price(10.0);
+        let quantity = affordable_quantity(&broker, &OrderSide::Buy, 1.0, 1010.0, 500.0);
+        assert!((quantity - 100.0).abs() < 1e-6);
+
+        // A flat ticket comes off the top before any units are bought
+        let mut broker = BacktestBroker::new(0.0, 0.0).with_commission_model(
+            serde_json::from_value(serde_json::json!({ "type": "per_ticket", "fee": 10.0 }))
+                .unwrap(),
+        );
+        broker.set_price(10.0);
+        let quantity = affordable_quantity(&broker, &OrderSide::Buy, 1.0, 1010.0, 500.0);
+        assert!((quantity - 100.0).abs() < 1e-6);
+        assert_eq!(
+            affordable_quantity(&broker, &OrderSide::Buy, 1.0, 1010.0, 50.0),
+            50.0
+        );
     }
 }
@@ -1,12 +1,18 @@
 use crate::actors::messages::{ActorError, ActorResult, CollectHistorical, MarketDataUpdate};
-use crate::actors::storage::StoreOHLCV;
+use crate::actors::storage::{StoreCorporateActions, StoreOHLCV};
+use crate::calendar::TradingCalendar;
+use crate::models::market_data::{AssetType, OHLCV, Ticker};
 use crate::providers::normalize::normalize_ohlcv;
 use crate::providers::{MarketDataProvider, YahooProvider};
+use crate::utils::metrics::median_spacing_secs;
 use kameo::Actor;
 use kameo::actor::ActorRef;
 use kameo::message::{Context, Message};
 use tracing::{error, info, warn};
 
+/// Trading sessions `CollectData` fetches.
+const RECENT_SESSIONS: usize = 30;
+
 #[derive(Actor)]
 #[actor(name = "DataCollectorActor")]
 pub struct DataCollectorActor {
@@ -26,11 +32,68 @@ impl DataCollectorActor {
             provider: YahooProvider::new(),
         }
     }
+
+    /// Warn about stretches of open market time `data` has no bars for.
+    /// Nights, weekends and holidays of `calendar` are not gaps.
+    fn report_gaps(symbol: &str, calendar: &TradingCalendar, data: &[OHLCV]) {
+        let timestamps: Vec<_> = data.iter().map(|bar| bar.timestamp).collect();
+        let Some(bar_seconds) = median_spacing_secs(&timestamps) else {
+            return;
+        };
+        let gaps = calendar.find_gaps(&timestamps, bar_seconds);
+        if let Some(first) = gaps.first() {
+            warn!(
+                symbol = %symbol,
+                calendar = %calendar.name,
+                gaps = gaps.len(),
+                missing_bars = gaps.iter().map(|gap| gap.missing_bars).sum::<i64>(),
+                first_gap_after = %first.after,
+                "Collected OHLCV data has gaps in trading hours"
+            );
+        }
+    }
+
+    /// Fetch and store the splits and dividends between `start` and `end`.
+    /// Failures are logged rather than failing the collection, since the
+    /// quotes are already stored.
+    async fn collect_corporate_actions(
+        &self,
+        symbol: &str,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+    ) {
+        let actions = match self
+            .provider
+            .fetch_corporate_actions(symbol, start, end)
+            .await
+        {
+            Ok(actions) if actions.is_empty() => return,
+            Ok(actions) => actions,
+            Err(e) => {
+                warn!(symbol = %symbol, error = %e, "Corporate action fetch failed");
+                return;
+            }
+        };
+
+        let count = actions.len();
+        match self
+            .storage_ref
+            .ask(StoreCorporateActions {
+                symbol: symbol.to_string(),
+                actions,
+            })
+            .await
+        {
+            Ok(()) => info!(symbol = %symbol, count, "Stored corporate actions"),
+            Err(e) => warn!(symbol = %symbol, error = %e, "Failed to store corporate actions"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct CollectData {
     pub symbol: String,
+    pub asset_type: String,
 }
 
 impl Message<CollectData> for DataCollectorActor {
@@ -41,10 +104,21 @@ impl Message<CollectData> for DataCollectorActor {
         msg: CollectData,
         _ctx: &mut Context<Self, Self::Reply>,
     ) -> Self::Reply {
-        info!(symbol = %msg.symbol, "Collecting last 30 days of OHLCV data");
+        let asset_type = msg.asset_type.parse::<AssetType>().map_err(|e| {
+            error!(symbol = %msg.symbol, error = %e, "Unknown asset type");
+            ActorError::InvalidInput(e)
+        })?;
+        let calendar = Ticker::new(msg.symbol.clone(), None, asset_type).calendar();
+        info!(
+            symbol = %msg.symbol,
+            asset_type = %msg.asset_type,
+            calendar = %calendar.name,
+            "Collecting last {} sessions of OHLCV data",
+            RECENT_SESSIONS
+        );
 
         let end = chrono::Utc::now();
-        let start = end - chrono::Duration::days(30);
+        let start = calendar.sessions_back(end, RECENT_SESSIONS);
 
         let raw = self
             .provider
@@ -61,16 +135,20 @@ impl Message<CollectData> for DataCollectorActor {
         })?;
 
         info!(symbol = %msg.symbol, count = data.len(), "Fetched and normalized OHLCV records");
+        Self::report_gaps(&msg.symbol, &calendar, &data);
 
         self.storage_ref
             .ask(StoreOHLCV {
                 symbol: msg.symbol.clone(),
-                asset_type: "stock".to_string(),
+                asset_type: msg.asset_type.clone(),
                 data: data.clone(),
             })
             .await
             .map_err(|e| ActorError::Internal(e.to_string()))?;
 
+        self.collect_corporate_actions(&msg.symbol, start, end)
+            .await;
+
         info!(symbol = %msg.symbol, "Stored OHLCV data; forwarding to strategy executor");
 
         for point in data {
@@ -126,6 +204,13 @@ impl Message<CollectHistorical> for DataCollectorActor {
             count = data.len(),
             "Fetched and normalized historical OHLCV records"
         );
+        match msg.asset_type.parse::<AssetType>() {
+            Ok(asset_type) => {
+                let calendar = Ticker::new(msg.symbol.clone(), None, asset_type).calendar();
+                Self::report_gaps(&msg.symbol, &calendar, &data);
+            }
+            Err(e) => warn!(symbol = %msg.symbol, error = %e, "Skipping gap detection"),
+        }
 
         self.storage_ref
             .ask(StoreOHLCV {
@@ -136,6 +221,9 @@ impl Message<CollectHistorical> for DataCollectorActor {
             .await
             .map_err(|e| ActorError::Internal(e.to_string()))?;
 
+        self.collect_corporate_actions(&msg.symbol, msg.start, msg.end)
+            .await;
+
         info!(symbol = %msg.symbol, "Stored historical OHLCV data; forwarding to strategy executor");
 
         for point in data {
@@ -17,6 +17,8 @@ pub enum ActorError {
     ActorUnavailable(String),
     /// Operation timed out
     Timeout,
+    /// Operation was cancelled on request
+    Cancelled(String),
     /// Internal error
     Internal(String),
 }
@@ -29,6 +31,7 @@ impl std::fmt::Display for ActorError {
             ActorError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
             ActorError::ActorUnavailable(msg) => write!(f, "Actor unavailable: {}", msg),
             ActorError::Timeout => write!(f, "Operation timed out"),
+            ActorError::Cancelled(msg) => write!(f, "Cancelled: {}", msg),
             ActorError::Internal(msg) => write!(f, "Internal error: {}", msg),
         }
     }
@@ -122,6 +125,12 @@ pub struct RunBacktest {
     pub backtest_id: String,
 }
 
+/// Request to run a walk-forward analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunWalkForward {
+    pub walk_forward_id: String,
+}
+
 /// Request to collect historical OHLCV data for a specific date range
 #[derive(Debug, Clone)]
 pub struct CollectHistorical {
@@ -4,10 +4,12 @@ pub mod execution;
 pub mod messages;
 pub mod storage;
 pub mod strategy;
+pub mod walk_forward;
 
-pub use backtest::BacktestActor;
+pub use backtest::{BacktestActor, BacktestWorkers};
 pub use collector::DataCollectorActor;
 pub use execution::OrderExecutionActor;
 pub use messages::*;
 pub use storage::TimeSeriesStorageActor;
 pub use strategy::StrategyExecutorActor;
+pub use walk_forward::WalkForwardActor;
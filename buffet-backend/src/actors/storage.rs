@@ -1,5 +1,5 @@
 use crate::actors::messages::{ActorError, ActorResult, TimeSeriesRef};
-use crate::models::market_data::OHLCV;
+use crate::models::market_data::{CorporateAction, OHLCV, PriceAdjustment};
 use crate::tsdb::TimescaleDb;
 use kameo::Actor;
 use kameo::message::{Context, Message};
@@ -45,6 +45,8 @@ impl Message<StoreOHLCV> for TimeSeriesStorageActor {
 pub struct QueryOHLCV {
     pub symbol: String,
     pub ts_ref: TimeSeriesRef,
+    /// Corporate actions to back-adjust for, as of `ts_ref.end_time`.
+    pub adjustment: PriceAdjustment,
 }
 
 impl Message<QueryOHLCV> for TimeSeriesStorageActor {
@@ -56,7 +58,74 @@ impl Message<QueryOHLCV> for TimeSeriesStorageActor {
         _ctx: &mut Context<Self, Self::Reply>,
     ) -> Self::Reply {
         self.tsdb
-            .query_ohlcv(&msg.symbol, msg.ts_ref.start_time, msg.ts_ref.end_time)
+            .query_adjusted_ohlcv(
+                &msg.symbol,
+                msg.ts_ref.start_time,
+                msg.ts_ref.end_time,
+                msg.adjustment,
+            )
+            .await
+            .map_err(|e| ActorError::TsdbError(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoreCorporateActions {
+    pub symbol: String,
+    pub actions: Vec<CorporateAction>,
+}
+
+impl Message<StoreCorporateActions> for TimeSeriesStorageActor {
+    type Reply = ActorResult<()>;
+
+    async fn handle(
+        &mut self,
+        msg: StoreCorporateActions,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.tsdb
+            .insert_corporate_actions(&msg.symbol, &msg.actions)
+            .await
+            .map_err(|e| ActorError::TsdbError(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryCorporateActions {
+    pub symbol: String,
+    pub ts_ref: TimeSeriesRef,
+}
+
+impl Message<QueryCorporateActions> for TimeSeriesStorageActor {
+    type Reply = ActorResult<Vec<CorporateAction>>;
+
+    async fn handle(
+        &mut self,
+        msg: QueryCorporateActions,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.tsdb
+            .query_corporate_actions(&msg.symbol, msg.ts_ref.start_time, msg.ts_ref.end_time)
+            .await
+            .map_err(|e| ActorError::TsdbError(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryAssetType {
+    pub symbol: String,
+}
+
+impl Message<QueryAssetType> for TimeSeriesStorageActor {
+    type Reply = ActorResult<Option<String>>;
+
+    async fn handle(
+        &mut self,
+        msg: QueryAssetType,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        self.tsdb
+            .query_asset_type(&msg.symbol)
             .await
             .map_err(|e| ActorError::TsdbError(e.to_string()))
     }
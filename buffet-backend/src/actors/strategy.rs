@@ -3,91 +3,140 @@ use crate::actors::messages::{
     SignalType, UnregisterStrategy,
 };
 use crate::models::market_data::OHLCV;
+use crate::strategies::StrategyRegistry;
 use kameo::Actor;
 use kameo::message::{Context, Message};
 use std::collections::HashMap;
 
-// Trait for strategy logic
-pub trait StrategyLogic: Send + Sync {
-    fn update(&mut self, data: &OHLCV) -> Option<SignalType>;
+/// A signal emitted by a strategy for one of the symbols it trades.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategySignal {
+    pub symbol: String,
+    pub signal_type: SignalType,
+    /// Extra context persisted in the signal's `metadata` column (e.g. model coefficients).
+    pub metadata: Option<serde_json::Value>,
+    /// Set on the hedge leg of a spread, which opens in proportion to the
+    /// other leg instead of being sized on its own.
+    pub hedge: Option<Hedge>,
 }
 
-// Simple Moving Average Crossover Strategy
-pub struct MovingAverageCrossover {
-    fast_period: usize,
-    slow_period: usize,
-    prices: Vec<f64>,
+/// Open `ratio` units for every unit an entry in `symbol` is sized at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hedge {
+    pub symbol: String,
+    pub ratio: f64,
 }
 
-impl MovingAverageCrossover {
-    pub fn new(fast_period: usize, slow_period: usize) -> Self {
+impl StrategySignal {
+    pub fn new(symbol: &str, signal_type: SignalType) -> Self {
         Self {
-            fast_period,
-            slow_period,
-            prices: Vec::new(),
+            symbol: symbol.to_string(),
+            signal_type,
+            metadata: None,
+            hedge: None,
         }
     }
 
-    fn calculate_sma(&self, period: usize) -> Option<f64> {
-        if self.prices.len() < period {
-            return None;
-        }
-        let sum: f64 = self.prices.iter().rev().take(period).sum();
-        Some(sum / period as f64)
+    pub fn with_metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn hedging(mut self, symbol: &str, ratio: f64) -> Self {
+        self.hedge = Some(Hedge {
+            symbol: symbol.to_string(),
+            ratio,
+        });
+        self
     }
 }
 
-impl StrategyLogic for MovingAverageCrossover {
-    fn update(&mut self, data: &OHLCV) -> Option<SignalType> {
-        self.prices.push(data.close);
+/// How the executor should interpret a strategy's signals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalKind {
+    /// Desired state, re-asserted on every bar: `Buy` means "be long",
+    /// `Sell` means "be flat". Only a change of state results in an order.
+    Target,
+    /// Discrete event (e.g. a spread entry or exit) to act on exactly once.
+    Event,
+}
 
-        // Keep only enough history for the slow period
-        if self.prices.len() > self.slow_period + 1 {
-            self.prices.remove(0);
-        }
+// Trait for strategy logic
+pub trait StrategyLogic: Send + Sync {
+    /// Single-symbol hook: consume a candle and optionally emit a signal for it.
+    fn update(&mut self, _data: &OHLCV) -> Option<SignalType> {
+        None
+    }
 
-        let fast_ma = self.calculate_sma(self.fast_period)?;
-        let slow_ma = self.calculate_sma(self.slow_period)?;
+    /// Consume a candle for `symbol` and return any signals it produces.
+    ///
+    /// The default forwards to `update` and tags the result with `symbol`.
+    /// Multi-leg strategies override this to see bars from every leg and may
+    /// emit signals for symbols other than the one that just printed.
+    fn on_bar(&mut self, symbol: &str, data: &OHLCV) -> Vec<StrategySignal> {
+        self.update(data)
+            .map(|signal_type| StrategySignal::new(symbol, signal_type))
+            .into_iter()
+            .collect()
+    }
 
-        // Simple logic: if fast crosses above slow -> Buy
-        // if fast crosses below slow -> Sell
-        if fast_ma > slow_ma {
-            Some(SignalType::Buy)
-        } else {
-            Some(SignalType::Sell)
-        }
+    /// Symbols the strategy needs to be fed regardless of its subscriptions.
+    /// Empty for strategies that trade whatever symbol they are given.
+    fn symbols(&self) -> Vec<String> {
+        Vec::new()
     }
-}
 
-/// Build a boxed `StrategyLogic` from a type string and JSON parameters string.
-/// Returns `None` if the strategy type is unsupported or parameters are invalid.
-fn build_strategy(strategy_type: &str, parameters: &str) -> Option<Box<dyn StrategyLogic>> {
-    use crate::models::strategy::StrategyType;
-    use std::str::FromStr;
+    /// Whether signals describe a target state or one-off events.
+    fn signal_kind(&self) -> SignalKind {
+        SignalKind::Target
+    }
+}
 
-    let params: serde_json::Value = serde_json::from_str(parameters).unwrap_or_default();
+/// Order needed to act on a signal given the quantity already `held` for the
+/// strategy and symbol, or `None` when there is nothing to do.
+///
+/// Signals that close a position trade exactly what is held; signals that
+/// open one trade `size`, as decided by the strategy's `PositionSizer`.
+fn order_for_signal(
+    kind: SignalKind,
+    signal_type: SignalType,
+    held: f64,
+    size: f64,
+) -> Option<(OrderSide, f64)> {
+    let (side, quantity) = match (kind, signal_type) {
+        (_, SignalType::Hold) => return None,
+        // Already long: the target is met, no resizing on every bar
+        (SignalKind::Target, SignalType::Buy) if held > 0.0 => return None,
+        (SignalKind::Target, SignalType::Buy) => (OrderSide::Buy, size - held),
+        (SignalKind::Target, SignalType::Sell) if held > 0.0 => (OrderSide::Sell, held),
+        (SignalKind::Target, SignalType::Sell) => return None,
+        (SignalKind::Event, SignalType::Buy) if held < 0.0 => (OrderSide::Buy, -held),
+        (SignalKind::Event, SignalType::Buy) => (OrderSide::Buy, size),
+        (SignalKind::Event, SignalType::Sell) if held > 0.0 => (OrderSide::Sell, held),
+        (SignalKind::Event, SignalType::Sell) => (OrderSide::Sell, size),
+    };
+
+    (quantity > f64::EPSILON).then_some((side, quantity))
+}
 
-    match StrategyType::from_str(strategy_type) {
-        Ok(StrategyType::Classical) => {
-            let fast = params["fast_period"].as_u64().unwrap_or(10) as usize;
-            let slow = params["slow_period"].as_u64().unwrap_or(20) as usize;
-            Some(Box::new(MovingAverageCrossover::new(fast, slow)))
-        }
-        _ => {
-            tracing::warn!(
-                "Unsupported strategy type '{}', skipping",
-                strategy_type
-            );
-            None
-        }
-    }
+/// What a strategy holds in one symbol, as far as its own orders go.
+#[derive(Debug, Clone, Copy, Default)]
+struct Holding {
+    /// Signed net quantity of filled orders.
+    quantity: f64,
+    /// Price of the bar that opened the position, when known.
+    entry_price: Option<f64>,
 }
 
+pub use crate::strategies::MovingAverageCrossover;
+
+use crate::models::order::Order;
 use crate::models::signal::Signal as SignalModel;
+use crate::strategies::PositionSizer;
 use sqlx::{Pool, Sqlite};
 
 use crate::actors::OrderExecutionActor;
-use crate::models::order::OrderSide;
+use crate::models::order::{OrderSide, OrderStatus};
 use kameo::actor::ActorRef;
 
 #[derive(Actor)]
@@ -96,6 +145,14 @@ pub struct StrategyExecutorActor {
     active_strategies: HashMap<String, Box<dyn StrategyLogic>>,
     /// Maps strategy_id -> list of subscribed symbols (empty = all symbols)
     strategy_symbols: HashMap<String, Vec<String>>,
+    /// Maps strategy_id -> position sizing policy
+    sizers: HashMap<String, PositionSizer>,
+    /// Holdings per (strategy_id, symbol), updated from filled orders.
+    /// Seeded from the database the first time a pair is seen, and again
+    /// after an order that did not fill.
+    held: HashMap<(String, String), Holding>,
+    /// Last close seen per symbol, used to size orders for other legs.
+    last_prices: HashMap<String, f64>,
     pool: Pool<Sqlite>,
     execution_actor: ActorRef<OrderExecutionActor>,
 }
@@ -105,12 +162,26 @@ impl StrategyExecutorActor {
         Self {
             active_strategies: HashMap::new(),
             strategy_symbols: HashMap::new(),
+            sizers: HashMap::new(),
+            held: HashMap::new(),
+            last_prices: HashMap::new(),
             pool,
             execution_actor,
         }
     }
 
+    /// Register a strategy that trades one unit per entry.
     pub fn register_strategy(&mut self, id: String, strategy: Box<dyn StrategyLogic>) {
+        self.register_sized_strategy(id, strategy, PositionSizer::default());
+    }
+
+    pub fn register_sized_strategy(
+        &mut self,
+        id: String,
+        strategy: Box<dyn StrategyLogic>,
+        sizer: PositionSizer,
+    ) {
+        self.sizers.insert(id.clone(), sizer);
         self.active_strategies.insert(id, strategy);
     }
 }
@@ -125,28 +196,91 @@ impl Message<MarketDataUpdate> for StrategyExecutorActor {
         msg: MarketDataUpdate,
         _ctx: &mut Context<Self, Self::Reply>,
     ) -> Self::Reply {
+        self.last_prices.insert(msg.symbol.clone(), msg.data.close);
+
         // Process all strategies with the new data
         for (id, strategy) in &mut self.active_strategies {
-            // If the strategy has symbol subscriptions, only process matching symbols
+            // If the strategy has symbol subscriptions, only process matching symbols.
+            // Symbols the strategy itself trades (e.g. both legs of a pair) always pass.
             let subscribed = self
                 .strategy_symbols
                 .get(id)
                 .map(|v| v.as_slice())
                 .unwrap_or(&[]);
-            if !subscribed.is_empty() && !subscribed.contains(&msg.symbol) {
+            if !subscribed.is_empty()
+                && !subscribed.contains(&msg.symbol)
+                && !strategy.symbols().contains(&msg.symbol)
+            {
                 continue;
             }
 
-            if let Some(signal_type) = strategy.update(&msg.data) {
+            let Some(sizer) = self.sizers.get_mut(id) else {
+                continue;
+            };
+            sizer.on_bar(&msg.symbol, &msg.data);
+
+            let kind = strategy.signal_kind();
+            for signal in strategy.on_bar(&msg.symbol, &msg.data) {
+                let key = (id.clone(), signal.symbol.clone());
+                let holding = match self.held.get(&key) {
+                    Some(&holding) => holding,
+                    None => Holding {
+                        quantity: Order::net_filled_quantity(id, &signal.symbol, &self.pool)
+                            .await
+                            .unwrap_or_else(|e| {
+                                tracing::error!("Failed to load held quantity: {:?}", e);
+                                0.0
+                            }),
+                        entry_price: None,
+                    },
+                };
+                self.held.insert(key.clone(), holding);
+
+                let price = self
+                    .last_prices
+                    .get(&signal.symbol)
+                    .copied()
+                    .unwrap_or(msg.data.close);
+                // Without equity to size against nothing opens, but closing
+                // and reducing orders still go out
+                let equity = sizer.live_equity();
+                let size = equity.map_or(0.0, |equity| match &signal.hedge {
+                    // A hedge leg opens in proportion to its other leg
+                    Some(hedge) => self.last_prices.get(&hedge.symbol).map_or(0.0, |&anchor| {
+                        hedge.ratio * sizer.quantity(&hedge.symbol, equity, anchor)
+                    }),
+                    None => sizer.quantity(&signal.symbol, equity, price),
+                });
+
+                let order = order_for_signal(kind, signal.signal_type, holding.quantity, size);
+                if order.is_none() {
+                    // Sizing only held the signal back if any size would
+                    // have opened a position
+                    let opens =
+                        order_for_signal(kind, signal.signal_type, holding.quantity, 1.0).is_some();
+                    if opens && equity.is_none() {
+                        tracing::warn!(
+                            "Strategy '{}' sizes by equity but has no 'sizing.capital'; skipping signal",
+                            id
+                        );
+                        continue;
+                    }
+                    // A target the strategy already holds, or cannot size
+                    // yet, is not a new signal
+                    if kind == SignalKind::Target {
+                        continue;
+                    }
+                }
+
                 let timestamp = chrono::Utc::now();
 
                 // Persist signal to DB
                 let created_signal = match SignalModel::create(
                     id,
-                    &msg.symbol,
-                    signal_type,
+                    &signal.symbol,
+                    signal.signal_type,
                     timestamp,
-                    None,
+                    signal.metadata.as_ref().map(|m| m.to_string()),
                     &self.pool,
                 )
                 .await
@@ -159,25 +293,59 @@ impl Message<MarketDataUpdate> for StrategyExecutorActor {
                 };
 
                 // Forward order request if signal persisted and is actionable
-                if let Some(signal_record) = created_signal {
-                    let side = match signal_type {
-                        SignalType::Buy => Some(OrderSide::Buy),
-                        SignalType::Sell => Some(OrderSide::Sell),
-                        SignalType::Hold => None,
-                    };
-
-                    if let Some(order_side) = side {
-                        let _ = self
-                            .execution_actor
-                            .tell(OrderRequest {
-                                signal_id: signal_record.id,
-                                symbol: msg.symbol.clone(),
-                                side: order_side,
-                                quantity: 1.0,
-                                price: None,
-                            })
-                            .send()
-                            .await;
+                if let Some(signal_record) = created_signal
+                    && let Some((order_side, quantity)) = order
+                {
+                    let filled = self
+                        .execution_actor
+                        .ask(OrderRequest {
+                            signal_id: signal_record.id,
+                            symbol: signal.symbol.clone(),
+                            side: order_side.clone(),
+                            quantity,
+                            price: None,
+                        })
+                        .await;
+
+                    match filled {
+                        Ok(order) if order.status == OrderStatus::Filled.to_string() => {
+                            let change = match order_side {
+                                OrderSide::Buy => order.quantity,
+                                OrderSide::Sell => -order.quantity,
+                            };
+                            let after = holding.quantity + change;
+
+                            // Closing out feeds the trade's return back to the sizer
+                            if after.abs() < f64::EPSILON
+                                && let Some(entry) = holding.entry_price
+                            {
+                                sizer.record_trade(
+                                    (price - entry) / entry * holding.quantity.signum(),
+                                );
+                            }
+
+                            let entry_price = if after.abs() < f64::EPSILON {
+                                None
+                            } else if holding.quantity.abs() < f64::EPSILON {
+                                Some(price)
+                            } else {
+                                holding.entry_price
+                            };
+                            self.held.insert(
+                                key,
+                                Holding {
+                                    quantity: after,
+                                    entry_price,
+                                },
+                            );
+                        }
+                        outcome => {
+                            if let Err(e) = outcome {
+                                tracing::error!("Order request failed: {:?}", e);
+                            }
+                            // Unfilled or failed: re-read what actually filled next time
+                            self.held.remove(&key);
+                        }
                     }
                 }
             }
@@ -202,15 +370,20 @@ impl Message<LoadStrategies> for StrategyExecutorActor {
 
         let mut loaded = 0usize;
         for s in strategies {
-            if let Some(logic) = build_strategy(&s.strategy_type, &s.parameters) {
-                self.active_strategies.insert(s.id.clone(), logic);
+            match StrategyRegistry::global().build(&s.strategy_type, &s.parameters) {
+                Ok(built) => {
+                    self.register_sized_strategy(s.id.clone(), built.logic, built.sizer);
 
-                // Parse the symbols JSON array stored in the DB
-                let symbols: Vec<String> =
-                    serde_json::from_str(&s.symbols).unwrap_or_default();
-                self.strategy_symbols.insert(s.id, symbols);
+                    // Parse the symbols JSON array stored in the DB
+                    let symbols: Vec<String> =
+                        serde_json::from_str(&s.symbols).unwrap_or_default();
+                    self.strategy_symbols.insert(s.id, symbols);
 
-                loaded += 1;
+                    loaded += 1;
+                }
+                Err(e) => {
+                    tracing::warn!("Skipping strategy '{}': {}", s.id, e);
+                }
             }
         }
 
@@ -229,8 +402,8 @@ impl Message<RegisterStrategy> for StrategyExecutorActor {
         msg: RegisterStrategy,
         _ctx: &mut Context<Self, Self::Reply>,
     ) -> Self::Reply {
-        match build_strategy(&msg.strategy_type, &msg.parameters) {
-            Some(logic) => {
+        match StrategyRegistry::global().build(&msg.strategy_type, &msg.parameters) {
+            Ok(built) => {
                 // Parse symbols from the parameters JSON if present; default to empty (= all)
                 let params: serde_json::Value =
                     serde_json::from_str(&msg.parameters).unwrap_or_default();
@@ -243,16 +416,13 @@ impl Message<RegisterStrategy> for StrategyExecutorActor {
                     })
                     .unwrap_or_default();
 
-                self.active_strategies.insert(msg.strategy_id.clone(), logic);
+                self.register_sized_strategy(msg.strategy_id.clone(), built.logic, built.sizer);
                 self.strategy_symbols.insert(msg.strategy_id.clone(), symbols);
 
                 tracing::info!("Registered strategy '{}'", msg.strategy_id);
                 Ok(())
             }
-            None => Err(ActorError::InvalidInput(format!(
-                "Unsupported strategy type '{}'",
-                msg.strategy_type
-            ))),
+            Err(e) => Err(ActorError::InvalidInput(e)),
         }
     }
 }
@@ -269,6 +439,8 @@ impl Message<UnregisterStrategy> for StrategyExecutorActor {
     ) -> Self::Reply {
         let removed = self.active_strategies.remove(&msg.strategy_id).is_some();
         self.strategy_symbols.remove(&msg.strategy_id);
+        self.sizers.remove(&msg.strategy_id);
+        self.held.retain(|(id, _), _| id != &msg.strategy_id);
 
         if removed {
             tracing::info!("Unregistered strategy '{}'", msg.strategy_id);
@@ -282,3 +454,72 @@ impl Message<UnregisterStrategy> for StrategyExecutorActor {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_signal_only_orders_on_change() {
+        assert_eq!(
+            order_for_signal(SignalKind::Target, SignalType::Buy, 0.0, 5.0),
+            Some((OrderSide::Buy, 5.0))
+        );
+        assert_eq!(
+            order_for_signal(SignalKind::Target, SignalType::Buy, 2.0, 5.0),
+            None
+        );
+        assert_eq!(
+            order_for_signal(SignalKind::Target, SignalType::Sell, 2.0, 5.0),
+            Some((OrderSide::Sell, 2.0))
+        );
+        assert_eq!(
+            order_for_signal(SignalKind::Target, SignalType::Sell, 0.0, 5.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_event_signal_opens_sized_and_closes_held() {
+        assert_eq!(
+            order_for_signal(SignalKind::Event, SignalType::Sell, 0.0, 5.0),
+            Some((OrderSide::Sell, 5.0))
+        );
+        assert_eq!(
+            order_for_signal(SignalKind::Event, SignalType::Buy, -3.0, 5.0),
+            Some((OrderSide::Buy, 3.0))
+        );
+        assert_eq!(
+            order_for_signal(SignalKind::Event, SignalType::Hold, 0.0, 5.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_zero_size_does_not_open() {
+        assert_eq!(
+            order_for_signal(SignalKind::Target, SignalType::Buy, 0.0, 0.0),
+            None
+        );
+        assert_eq!(
+            order_for_signal(SignalKind::Event, SignalType::Sell, 0.0, 0.0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_zero_size_still_closes_held() {
+        assert_eq!(
+            order_for_signal(SignalKind::Target, SignalType::Sell, 2.0, 0.0),
+            Some((OrderSide::Sell, 2.0))
+        );
+        assert_eq!(
+            order_for_signal(SignalKind::Target, SignalType::Buy, -2.0, 0.0),
+            Some((OrderSide::Buy, 2.0))
+        );
+        assert_eq!(
+            order_for_signal(SignalKind::Event, SignalType::Buy, -3.0, 0.0),
+            Some((OrderSide::Buy, 3.0))
+        );
+    }
+}
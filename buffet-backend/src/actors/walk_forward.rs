@@ -0,0 +1,228 @@
+use crate::actors::backtest::BacktestWorkers;
+use crate::actors::messages::{ActorError, ActorResult, RunWalkForward};
+use crate::error::AppError;
+use crate::models::backtest::{
+    Backtest, BacktestStatus, BacktestTrade, CreateBacktestDto, EquityPoint,
+};
+use crate::models::sweep::{BacktestSweep, CreateSweepDto, ParameterValues, SweepObjective};
+use crate::models::walk_forward::{WalkForward, WalkForwardFold, WalkForwardResults};
+use crate::utils::metrics::{
+    calculate_max_drawdown, calculate_profit_factor, calculate_sharpe_ratio, calculate_win_rate,
+};
+use futures::future::join_all;
+use kameo::Actor;
+use kameo::message::{Context, Message};
+use sqlx::{Pool, Sqlite};
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+fn database_error(e: AppError) -> ActorError {
+    ActorError::DatabaseError(e.to_string())
+}
+
+/// Runs walk-forward analyses: for each fold, a parameter sweep on the train
+/// segment picks a winner, which is then backtested on the test segment.
+/// The backtests themselves run on the shared `BacktestWorkers`.
+#[derive(Actor)]
+#[actor(name = "WalkForwardActor")]
+pub struct WalkForwardActor {
+    pool: Pool<Sqlite>,
+    workers: BacktestWorkers,
+}
+
+impl WalkForwardActor {
+    pub fn new(pool: Pool<Sqlite>, workers: BacktestWorkers) -> Self {
+        Self { pool, workers }
+    }
+
+    async fn run(&self, walk_forward: &WalkForward) -> ActorResult<WalkForwardResults> {
+        let settings: CreateBacktestDto = serde_json::from_str(&walk_forward.settings)
+            .map_err(|e| ActorError::InvalidInput(format!("Invalid settings: {}", e)))?;
+        let grid: BTreeMap<String, ParameterValues> = serde_json::from_str(&walk_forward.grid)
+            .map_err(|e| ActorError::InvalidInput(format!("Invalid grid: {}", e)))?;
+        let objective: SweepObjective = walk_forward
+            .objective
+            .parse()
+            .map_err(ActorError::InvalidInput)?;
+        let folds = WalkForwardFold::find_by_walk_forward(&walk_forward.id, &self.pool)
+            .await
+            .map_err(database_error)?;
+
+        // Each test segment starts with the capital the previous one ended with
+        let mut capital = walk_forward.initial_balance;
+        let mut equity_curve: Vec<EquityPoint> = Vec::new();
+        let mut trades: Vec<BacktestTrade> = Vec::new();
+
+        for fold in &folds {
+            let parameters = self.optimise(fold, &settings, &grid, objective).await?;
+
+            let mut test = settings.clone();
+            test.start_time = fold.test_start;
+            test.end_time = fold.test_end;
+            test.warmup_start_time = Some(fold.train_start);
+            test.initial_balance = capital;
+            test.parameters = Some(parameters);
+            let backtest = Backtest::create(test, &self.pool)
+                .await
+                .map_err(database_error)?;
+            WalkForwardFold::record_test(&fold.id, &backtest.id, &self.pool)
+                .await
+                .map_err(database_error)?;
+
+            let curve = self.workers.run_and_wait(backtest.id.clone()).await?;
+            let backtest = Backtest::find_by_id(&backtest.id, &self.pool)
+                .await
+                .map_err(database_error)?;
+            capital = backtest.final_balance.unwrap_or(capital);
+
+            // A fold's first point repeats the previous fold's last
+            let skip = usize::from(!equity_curve.is_empty());
+            equity_curve.extend(curve.into_iter().skip(skip));
+            trades.extend(
+                BacktestTrade::find_by_backtest(&backtest.id, &self.pool)
+                    .await
+                    .map_err(database_error)?,
+            );
+
+            info!(
+                "Walk-forward {} fold {}: out-of-sample return {:.2}%",
+                walk_forward.id,
+                fold.fold_index,
+                backtest.total_return.unwrap_or(0.0) * 100.0
+            );
+        }
+
+        // Drawdowns of the stitched curve run across fold boundaries
+        EquityPoint::fill_drawdowns(&mut equity_curve);
+        Ok(stitched_results(
+            walk_forward.initial_balance,
+            capital,
+            equity_curve,
+            &trades,
+        ))
+    }
+
+    /// Sweep the grid over the fold's train segment and return the best
+    /// parameters under `objective`.
+    async fn optimise(
+        &self,
+        fold: &WalkForwardFold,
+        settings: &CreateBacktestDto,
+        grid: &BTreeMap<String, ParameterValues>,
+        objective: SweepObjective,
+    ) -> ActorResult<serde_json::Value> {
+        let mut train = settings.clone();
+        train.start_time = fold.train_start;
+        train.end_time = fold.train_end;
+        let (sweep, runs) = BacktestSweep::create(
+            CreateSweepDto {
+                backtest: train,
+                grid: grid.clone(),
+                objective: Some(objective),
+            },
+            &self.pool,
+        )
+        .await
+        .map_err(|e| ActorError::InvalidInput(e.to_string()))?;
+
+        // Individual runs may fail; the ranking below only counts completed ones
+        join_all(
+            runs.iter()
+                .map(|run| self.workers.run_and_wait(run.id.clone())),
+        )
+        .await;
+
+        let detail = BacktestSweep::detail(&sweep.id, &self.pool)
+            .await
+            .map_err(database_error)?;
+        let Some(best) = detail.runs.into_iter().find(|run| run.rank == Some(1)) else {
+            return Err(ActorError::InvalidInput(format!(
+                "Fold {}: no training run completed",
+                fold.fold_index
+            )));
+        };
+
+        WalkForwardFold::record_optimisation(
+            &fold.id,
+            &sweep.id,
+            &best.parameters,
+            best.score,
+            &self.pool,
+        )
+        .await
+        .map_err(database_error)?;
+        Ok(best.parameters)
+    }
+}
+
+/// Metrics over every out-of-sample trade and the stitched equity curve.
+fn stitched_results(
+    initial_balance: f64,
+    final_balance: f64,
+    equity_curve: Vec<EquityPoint>,
+    trades: &[BacktestTrade],
+) -> WalkForwardResults {
+    let returns: Vec<f64> = trades.iter().filter_map(|t| t.percentage_return).collect();
+    let trades_pnl: Vec<f64> = trades.iter().filter_map(|t| t.pnl).collect();
+    let equity: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+
+    let profit_factor = calculate_profit_factor(&trades_pnl);
+    WalkForwardResults {
+        final_balance,
+        total_return: (final_balance - initial_balance) / initial_balance,
+        sharpe_ratio: calculate_sharpe_ratio(&returns, 0.0),
+        max_drawdown: calculate_max_drawdown(&equity),
+        trade_count: trades_pnl.len() as i64,
+        win_rate: calculate_win_rate(&trades_pnl),
+        // Persist f64::INFINITY as a very large finite number (SQLite REAL limitation)
+        profit_factor: if profit_factor.is_infinite() {
+            f64::MAX
+        } else {
+            profit_factor
+        },
+        equity_curve,
+    }
+}
+
+impl Message<RunWalkForward> for WalkForwardActor {
+    type Reply = ActorResult<()>;
+
+    async fn handle(
+        &mut self,
+        msg: RunWalkForward,
+        _ctx: &mut Context<Self, Self::Reply>,
+    ) -> Self::Reply {
+        let walk_forward = WalkForward::find_by_id(&msg.walk_forward_id, &self.pool)
+            .await
+            .map_err(database_error)?;
+        let _ =
+            WalkForward::update_status(&walk_forward.id, BacktestStatus::Running, None, &self.pool)
+                .await;
+
+        match self.run(&walk_forward).await {
+            Ok(results) => {
+                info!(
+                    "Walk-forward {} completed. Trades: {}, Final Equity: {:.2}, Return: {:.2}%",
+                    walk_forward.id,
+                    results.trade_count,
+                    results.final_balance,
+                    results.total_return * 100.0
+                );
+                WalkForward::update_results(&walk_forward.id, &results, &self.pool)
+                    .await
+                    .map_err(database_error)
+            }
+            Err(e) => {
+                warn!("Walk-forward {} failed: {}", walk_forward.id, e);
+                let _ = WalkForward::update_status(
+                    &walk_forward.id,
+                    BacktestStatus::Failed,
+                    Some(e.to_string()),
+                    &self.pool,
+                )
+                .await;
+                Err(e)
+            }
+        }
+    }
+}
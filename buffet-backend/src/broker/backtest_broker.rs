@@ -1,47 +1,169 @@
-use crate::broker::{Broker, BrokerError, FillResult};
+use crate::broker::{
+    BarLiquidity, Broker, BrokerError, CommissionModel, FillResult, Liquidity, SlippageModel,
+};
+use crate::models::market_data::OHLCV;
 use crate::models::order::OrderSide;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Which price of a bar market orders fill at.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FillModel {
+    /// Fill at the open of the bar after the signal, so the signal never
+    /// trades on the close it was computed from.
+    #[default]
+    NextBarOpen,
+    /// Fill at the close of the signal bar (look-ahead: the signal saw that close).
+    SameBarClose,
+    /// Fill at the typical price `(high + low + close) / 3` of the signal bar.
+    Vwap,
+}
+
+impl FillModel {
+    /// Reference price for a market order on `bar`. For `NextBarOpen` the
+    /// caller passes the bar after the signal.
+    pub fn price(&self, bar: &OHLCV) -> f64 {
+        match self {
+            FillModel::NextBarOpen => bar.open,
+            FillModel::SameBarClose => bar.close,
+            FillModel::Vwap => (bar.high + bar.low + bar.close) / 3.0,
+        }
+    }
+}
+
+impl std::fmt::Display for FillModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FillModel::NextBarOpen => write!(f, "next_bar_open"),
+            FillModel::SameBarClose => write!(f, "same_bar_close"),
+            FillModel::Vwap => write!(f, "vwap"),
+        }
+    }
+}
+
+impl std::str::FromStr for FillModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "next_bar_open" => Ok(FillModel::NextBarOpen),
+            "same_bar_close" => Ok(FillModel::SameBarClose),
+            "vwap" => Ok(FillModel::Vwap),
+            other => Err(format!("Unknown fill model '{}'", other)),
+        }
+    }
+}
 
 /// A broker implementation for backtesting that applies configurable
-/// slippage and commission to every fill.
+/// slippage and commission to every fill, and fills no more of an order
+/// than its bar's volume allows.
 pub struct BacktestBroker {
-    /// Commission rate as a fraction of trade value (e.g. 0.001 = 0.1%)
-    pub commission_rate: f64,
+    /// How each fill is charged; a flat percentage unless overridden
+    pub commission: CommissionModel,
     /// Slippage in basis points (e.g. 10.0 = 0.1%)
     pub slippage_bps: f64,
-    /// The current market price — must be set via `set_price` before each fill
+    /// Size-dependent impact and participation cap on top of `slippage_bps`
+    pub slippage: SlippageModel,
+    /// How `set_bar` picks the fill price from a bar
+    pub fill_model: FillModel,
+    /// The current market price — must be set via `set_bar` or `set_price` before each fill
     pub current_price: f64,
+    /// `(low, high)` of the current bar, which limit orders must reach
+    range: (f64, f64),
+    /// Volume and volatility of the current bar; `None` for a bare price
+    liquidity: Option<BarLiquidity>,
 }
 
 impl BacktestBroker {
     pub fn new(commission_rate: f64, slippage_bps: f64) -> Self {
         Self {
-            commission_rate,
+            commission: CommissionModel::percentage(commission_rate),
             slippage_bps,
+            fill_model: FillModel::SameBarClose,
             current_price: 0.0,
+            range: (0.0, 0.0),
+            slippage: SlippageModel::default(),
+            liquidity: None,
         }
     }
 
-    /// Update the current market price before submitting an order.
+    pub fn with_slippage_model(mut self, slippage: SlippageModel) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    pub fn with_commission_model(mut self, commission: CommissionModel) -> Self {
+        self.commission = commission;
+        self
+    }
+
+    pub fn with_fill_model(mut self, fill_model: FillModel) -> Self {
+        self.fill_model = fill_model;
+        self
+    }
+
+    /// Price the next fills off `bar` according to the fill model.
+    pub fn set_bar(&mut self, bar: &OHLCV) {
+        self.current_price = self.fill_model.price(bar);
+        self.range = (bar.low, bar.high);
+        self.liquidity = Some(BarLiquidity::from_bar(bar));
+    }
+
+    /// Fill at exactly `price`, e.g. a triggered stop level. With no bar to
+    /// measure against, orders fill in full without market impact.
     pub fn set_price(&mut self, price: f64) {
         self.current_price = price;
+        self.range = (price, price);
+        self.liquidity = None;
+    }
+
+    /// Fill at exactly `price`, e.g. a triggered stop level, while the
+    /// current bar's volume still caps the fill and sets its impact.
+    pub fn set_exit_price(&mut self, price: f64) {
+        self.current_price = price;
+        self.range = (price, price);
     }
 
-    /// Apply slippage to a base price based on order side.
+    /// How much of `quantity` the current bar's volume lets fill now.
+    pub fn fillable(&self, quantity: f64) -> f64 {
+        self.slippage.fillable(quantity, self.liquidity.as_ref())
+    }
+
+    /// Apply slippage for filling `quantity` to a base price based on
+    /// order side.
     ///
     /// - Buys fill slightly *higher* (adverse for the buyer).
     /// - Sells fill slightly *lower* (adverse for the seller).
-    pub fn apply_slippage(&self, base: f64, side: &OrderSide) -> f64 {
-        let factor = self.slippage_bps / 10_000.0;
+    pub fn apply_slippage(&self, base: f64, side: &OrderSide, quantity: f64) -> f64 {
+        let factor =
+            self.slippage_bps / 10_000.0 + self.slippage.impact(quantity, self.liquidity.as_ref());
         match side {
             OrderSide::Buy => base * (1.0 + factor),
             OrderSide::Sell => base * (1.0 - factor),
         }
     }
 
-    /// Return the commission cost for a given trade value.
-    pub fn apply_commission(&self, value: f64) -> f64 {
-        value * self.commission_rate
+    /// Return the commission and fees for filling `quantity` at `price`.
+    pub fn apply_commission(
+        &self,
+        side: &OrderSide,
+        quantity: f64,
+        price: f64,
+        liquidity: Liquidity,
+    ) -> f64 {
+        self.commission.commission(side, quantity, price, liquidity)
+    }
+}
+
+/// An order that did not fill at all on the current bar.
+fn unfilled(reason: &str) -> FillResult {
+    FillResult {
+        fill_price: 0.0,
+        fill_quantity: 0.0,
+        filled: false,
+        rejection_reason: Some(reason.to_string()),
+        commission: None,
     }
 }
 
@@ -53,13 +175,17 @@ impl Broker for BacktestBroker {
         side: &OrderSide,
         quantity: f64,
     ) -> Result<FillResult, BrokerError> {
-        let fill_price = self.apply_slippage(self.current_price, side);
-        let fill_value = fill_price * quantity;
-        let commission = self.apply_commission(fill_value);
+        let fill_quantity = self.fillable(quantity);
+        if fill_quantity <= 0.0 {
+            return Ok(unfilled("No volume left in bar"));
+        }
+        let fill_price = self.apply_slippage(self.current_price, side, fill_quantity);
+        let commission = self.apply_commission(side, fill_quantity, fill_price, Liquidity::Taker);
 
         tracing::debug!(
-            "BacktestBroker: market {:?} {:.6} @ {:.4} (slippage applied), commission={:.4}",
+            "BacktestBroker: market {:?} {:.6}/{:.6} @ {:.4} (slippage applied), commission={:.4}",
             side,
+            fill_quantity,
             quantity,
             fill_price,
             commission
@@ -67,29 +193,55 @@ impl Broker for BacktestBroker {
 
         Ok(FillResult {
             fill_price,
-            fill_quantity: quantity,
-            filled: true,
+            fill_quantity,
+            filled: fill_quantity >= quantity,
             rejection_reason: None,
             commission: Some(commission),
         })
     }
 
-    /// Limit orders in backtesting are treated the same as market orders —
-    /// they fill immediately at the current price with slippage applied.
+    /// Limit orders fill only when the current bar's range reaches the
+    /// limit, at the market fill price or the limit, whichever is better.
     async fn submit_limit_order(
         &self,
         _symbol: &str,
         side: &OrderSide,
         quantity: f64,
-        _limit_price: f64,
+        limit_price: f64,
     ) -> Result<FillResult, BrokerError> {
-        let fill_price = self.apply_slippage(self.current_price, side);
-        let fill_value = fill_price * quantity;
-        let commission = self.apply_commission(fill_value);
+        let (low, high) = self.range;
+        let fill_quantity = self.fillable(quantity);
+        if fill_quantity <= 0.0 {
+            return Ok(unfilled("No volume left in bar"));
+        }
+        let market_price = self.apply_slippage(self.current_price, side, fill_quantity);
+        let fill_price = match side {
+            OrderSide::Buy if low <= limit_price => market_price.min(limit_price),
+            OrderSide::Sell if high >= limit_price => market_price.max(limit_price),
+            _ => {
+                tracing::debug!(
+                    "BacktestBroker: limit {:?} {:.6} @ {:.4} not reached (bar {:.4}-{:.4})",
+                    side,
+                    quantity,
+                    limit_price,
+                    low,
+                    high
+                );
+                return Ok(unfilled("Limit price not reached"));
+            }
+        };
+        // Filling at the limit means the order rested on the book
+        let liquidity = if fill_price == limit_price {
+            Liquidity::Maker
+        } else {
+            Liquidity::Taker
+        };
+        let commission = self.apply_commission(side, fill_quantity, fill_price, liquidity);
 
         tracing::debug!(
-            "BacktestBroker: limit {:?} {:.6} @ {:.4} (treated as market), commission={:.4}",
+            "BacktestBroker: limit {:?} {:.6}/{:.6} @ {:.4}, commission={:.4}",
             side,
+            fill_quantity,
             quantity,
             fill_price,
             commission
@@ -97,8 +249,8 @@ impl Broker for BacktestBroker {
 
         Ok(FillResult {
             fill_price,
-            fill_quantity: quantity,
-            filled: true,
+            fill_quantity,
+            filled: fill_quantity >= quantity,
             rejection_reason: None,
             commission: Some(commission),
         })
@@ -108,3 +260,100 @@ impl Broker for BacktestBroker {
         "BacktestBroker"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn bar() -> OHLCV {
+        OHLCV::new(Utc::now(), 10.0, 12.0, 8.0, 11.0, 100.0)
+    }
+
+    #[test]
+    fn test_fill_models_pick_bar_price() {
+        assert_eq!(FillModel::NextBarOpen.price(&bar()), 10.0);
+        assert_eq!(FillModel::SameBarClose.price(&bar()), 11.0);
+        assert!((FillModel::Vwap.price(&bar()) - 31.0 / 3.0).abs() < 1e-9);
+        assert_eq!("vwap".parse::<FillModel>().unwrap(), FillModel::Vwap);
+        assert_eq!(FillModel::NextBarOpen.to_string(), "next_bar_open");
+    }
+
+    #[tokio::test]
+    async fn test_limit_order_fills_only_inside_bar_range() {
+        let mut broker = BacktestBroker::new(0.0, 0.0).with_fill_model(FillModel::NextBarOpen);
+        broker.set_bar(&bar());
+
+        // Bar traded down to 8, so a buy at 9 fills at the limit
+        let fill = broker
+            .submit_limit_order("X", &OrderSide::Buy, 1.0, 9.0)
+            .await
+            .unwrap();
+        assert!(fill.filled);
+        assert_eq!(fill.fill_price, 9.0);
+
+        // Marketable limit fills at the better open price
+        let fill = broker
+            .submit_limit_order("X", &OrderSide::Buy, 1.0, 10.5)
+            .await
+            .unwrap();
+        assert_eq!(fill.fill_price, 10.0);
+
+        // Nothing traded below 8 or above 12
+        let fill = broker
+            .submit_limit_order("X", &OrderSide::Buy, 1.0, 7.5)
+            .await
+            .unwrap();
+        assert!(!fill.filled);
+        assert_eq!(fill.fill_quantity, 0.0);
+        let fill = broker
+            .submit_limit_order("X", &OrderSide::Sell, 1.0, 12.5)
+            .await
+            .unwrap();
+        assert!(!fill.filled);
+        let fill = broker
+            .submit_limit_order("X", &OrderSide::Sell, 1.0, 11.5)
+            .await
+            .unwrap();
+        assert_eq!(fill.fill_price, 11.5);
+    }
+
+    #[tokio::test]
+    async fn test_market_order_fills_partially_above_participation_cap() {
+        let mut broker = BacktestBroker::new(0.0, 0.0).with_slippage_model(
+            serde_json::from_value(serde_json::json!({
+                "type": "square_root",
+                "coefficient": 1.0,
+                "max_participation": 0.1
+            }))
+            .unwrap(),
+        );
+        broker.set_bar(&bar());
+
+        let fill = broker
+            .submit_market_order("X", &OrderSide::Buy, 4.0)
+            .await
+            .unwrap();
+        assert!(fill.filled);
+        let small_price = fill.fill_price;
+        assert!(small_price > 11.0);
+
+        // Only 10% of the bar's 100 units trade, at a worse price
+        let fill = broker
+            .submit_market_order("X", &OrderSide::Buy, 25.0)
+            .await
+            .unwrap();
+        assert!(!fill.filled);
+        assert_eq!(fill.fill_quantity, 10.0);
+        assert!(fill.fill_price > small_price);
+
+        // A bare price (e.g. a stop level) fills in full without impact
+        broker.set_price(9.0);
+        let fill = broker
+            .submit_market_order("X", &OrderSide::Sell, 25.0)
+            .await
+            .unwrap();
+        assert!(fill.filled);
+        assert_eq!(fill.fill_price, 9.0);
+    }
+}
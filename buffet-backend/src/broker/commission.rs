@@ -0,0 +1,284 @@
+use crate::models::order::OrderSide;
+use serde::{Deserialize, Serialize};
+
+/// Whether a fill took liquidity from the book or provided it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    /// Market orders and marketable limits.
+    Taker,
+    /// Limit orders that rested and were filled at their limit.
+    Maker,
+}
+
+/// One bracket of a tiered schedule: `rate` of the part of an order's value
+/// below `up_to` (and above the previous tier's bound).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommissionTier {
+    /// Upper bound of the bracket in trade value; `None` for the last one.
+    pub up_to: Option<f64>,
+    pub rate: f64,
+}
+
+/// How the broker charges for a fill, before any regulatory fees.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommissionSchedule {
+    /// A fraction of trade value (e.g. 0.001 = 0.1%).
+    Percentage { rate: f64 },
+    /// A fee per unit, clamped per order to `minimum` and `maximum`.
+    PerShare {
+        rate: f64,
+        #[serde(default)]
+        minimum: f64,
+        maximum: Option<f64>,
+    },
+    /// Marginal brackets of trade value, like a tax schedule.
+    Tiered { tiers: Vec<CommissionTier> },
+    /// Different fractions of trade value for providing and taking liquidity.
+    /// Maker rates may be negative (a rebate).
+    MakerTaker { maker_rate: f64, taker_rate: f64 },
+    /// A flat fee per order, whatever its size.
+    PerTicket { fee: f64 },
+}
+
+/// Exchange and regulatory fees charged on top of the commission.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegulatoryFees {
+    /// Fraction of the value of every sell (SEC Section 31-style).
+    pub sell_value_rate: f64,
+    /// Fee per unit sold (FINRA TAF-style), capped per order at
+    /// `sell_per_share_max`.
+    pub sell_per_share: f64,
+    pub sell_per_share_max: Option<f64>,
+    /// Exchange fee per unit traded on either side.
+    pub exchange_per_share: f64,
+}
+
+/// Commission schedule plus fees, selectable per backtest, e.g.
+/// `{"type": "per_share", "rate": 0.005, "minimum": 1.0, "sell_value_rate": 0.0000278}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommissionModel {
+    #[serde(flatten)]
+    pub schedule: CommissionSchedule,
+    #[serde(flatten)]
+    pub fees: RegulatoryFees,
+}
+
+impl CommissionModel {
+    /// The flat fraction-of-value model backtests used before models were
+    /// selectable.
+    pub fn percentage(rate: f64) -> Self {
+        Self {
+            schedule: CommissionSchedule::Percentage { rate },
+            fees: RegulatoryFees::default(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self.schedule {
+            CommissionSchedule::Percentage { .. } => "percentage",
+            CommissionSchedule::PerShare { .. } => "per_share",
+            CommissionSchedule::Tiered { .. } => "tiered",
+            CommissionSchedule::MakerTaker { .. } => "maker_taker",
+            CommissionSchedule::PerTicket { .. } => "per_ticket",
+        }
+    }
+
+    /// Reject negative fees, inverted bounds and unordered tiers.
+    pub fn validate(&self) -> Result<(), String> {
+        let negative = |value: f64, name: &str| {
+            if value.is_nan() || value < 0.0 {
+                Err(format!("Commission '{}' cannot be negative", name))
+            } else {
+                Ok(())
+            }
+        };
+
+        match &self.schedule {
+            CommissionSchedule::Percentage { rate } => negative(*rate, "rate")?,
+            CommissionSchedule::PerShare {
+                rate,
+                minimum,
+                maximum,
+            } => {
+                negative(*rate, "rate")?;
+                negative(*minimum, "minimum")?;
+                if let Some(maximum) = maximum
+                    && maximum < minimum
+                {
+                    return Err("Commission 'maximum' must not be below 'minimum'".to_string());
+                }
+            }
+            CommissionSchedule::Tiered { tiers } => {
+                if tiers.is_empty() {
+                    return Err("Tiered commission needs at least one tier".to_string());
+                }
+                let mut previous = 0.0;
+                for (i, tier) in tiers.iter().enumerate() {
+                    negative(tier.rate, "rate")?;
+                    match tier.up_to {
+                        Some(up_to) if up_to > previous => previous = up_to,
+                        Some(_) => {
+                            return Err(
+                                "Commission tiers must have increasing 'up_to' bounds".to_string()
+                            );
+                        }
+                        None if i + 1 == tiers.len() => {}
+                        None => {
+                            return Err(
+                                "Only the last commission tier may omit 'up_to'".to_string()
+                            );
+                        }
+                    }
+                }
+            }
+            // Maker rebates are allowed; a net rebate on taking is not
+            CommissionSchedule::MakerTaker { taker_rate, .. } => {
+                negative(*taker_rate, "taker_rate")?
+            }
+            CommissionSchedule::PerTicket { fee } => negative(*fee, "fee")?,
+        }
+
+        negative(self.fees.sell_value_rate, "sell_value_rate")?;
+        negative(self.fees.sell_per_share, "sell_per_share")?;
+        negative(self.fees.exchange_per_share, "exchange_per_share")?;
+        Ok(())
+    }
+
+    /// Total charge for filling `quantity` units at `price`.
+    pub fn commission(
+        &self,
+        side: &OrderSide,
+        quantity: f64,
+        price: f64,
+        liquidity: Liquidity,
+    ) -> f64 {
+        if quantity <= 0.0 {
+            return 0.0;
+        }
+        let value = quantity * price;
+
+        let commission = match &self.schedule {
+            CommissionSchedule::Percentage { rate } => value * rate,
+            CommissionSchedule::PerShare {
+                rate,
+                minimum,
+                maximum,
+            } => {
+                let fee = (quantity * rate).max(*minimum);
+                maximum.map_or(fee, |maximum| fee.min(maximum))
+            }
+            CommissionSchedule::Tiered { tiers } => {
+                let mut fee = 0.0;
+                let mut lower = 0.0;
+                for tier in tiers {
+                    let upper = tier.up_to.unwrap_or(f64::INFINITY).min(value);
+                    if upper > lower {
+                        fee += (upper - lower) * tier.rate;
+                    }
+                    lower = tier.up_to.unwrap_or(f64::INFINITY);
+                    if lower >= value {
+                        break;
+                    }
+                }
+                fee
+            }
+            CommissionSchedule::MakerTaker {
+                maker_rate,
+                taker_rate,
+            } => match liquidity {
+                Liquidity::Maker => value * maker_rate,
+                Liquidity::Taker => value * taker_rate,
+            },
+            CommissionSchedule::PerTicket { fee } => *fee,
+        };
+
+        let mut fees = quantity * self.fees.exchange_per_share;
+        if *side == OrderSide::Sell {
+            let per_share = quantity * self.fees.sell_per_share;
+            fees += value * self.fees.sell_value_rate
+                + self
+                    .fees
+                    .sell_per_share_max
+                    .map_or(per_share, |maximum| per_share.min(maximum));
+        }
+        commission + fees
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn model(value: serde_json::Value) -> CommissionModel {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_per_share_is_clamped_per_order() {
+        let m =
+            model(json!({ "type": "per_share", "rate": 0.005, "minimum": 1.0, "maximum": 5.0 }));
+        let fee = |quantity| m.commission(&OrderSide::Buy, quantity, 10.0, Liquidity::Taker);
+        assert_eq!(fee(100.0), 1.0);
+        assert!((fee(500.0) - 2.5).abs() < 1e-9);
+        assert_eq!(fee(10_000.0), 5.0);
+    }
+
+    #[test]
+    fn test_tiers_charge_marginal_brackets() {
+        let m = model(json!({
+            "type": "tiered",
+            "tiers": [
+                { "up_to": 1000.0, "rate": 0.002 },
+                { "up_to": 5000.0, "rate": 0.001 },
+                { "rate": 0.0005 }
+            ]
+        }));
+        assert!(m.validate().is_ok());
+        // 1000 @ 0.2% + 4000 @ 0.1% + 1000 @ 0.05%
+        let fee = m.commission(&OrderSide::Buy, 60.0, 100.0, Liquidity::Taker);
+        assert!((fee - 6.5).abs() < 1e-9);
+        let fee = m.commission(&OrderSide::Buy, 5.0, 100.0, Liquidity::Taker);
+        assert!((fee - 1.0).abs() < 1e-9);
+
+        let unordered = model(json!({
+            "type": "tiered",
+            "tiers": [{ "up_to": 500.0, "rate": 0.1 }, { "up_to": 100.0, "rate": 0.1 }]
+        }));
+        assert!(unordered.validate().is_err());
+    }
+
+    #[test]
+    fn test_maker_taker_and_ticket_fees() {
+        let m =
+            model(json!({ "type": "maker_taker", "maker_rate": -0.0001, "taker_rate": 0.0004 }));
+        assert!((m.commission(&OrderSide::Buy, 1.0, 1000.0, Liquidity::Taker) - 0.4).abs() < 1e-9);
+        assert!((m.commission(&OrderSide::Buy, 1.0, 1000.0, Liquidity::Maker) + 0.1).abs() < 1e-9);
+
+        let m = model(json!({ "type": "per_ticket", "fee": 4.95 }));
+        assert_eq!(
+            m.commission(&OrderSide::Sell, 3.0, 50.0, Liquidity::Taker),
+            4.95
+        );
+        assert_eq!(m.name(), "per_ticket");
+    }
+
+    #[test]
+    fn test_regulatory_fees_only_apply_to_sells() {
+        let m = model(json!({
+            "type": "per_ticket",
+            "fee": 1.0,
+            "sell_value_rate": 0.0001,
+            "sell_per_share": 0.01,
+            "sell_per_share_max": 0.5,
+            "exchange_per_share": 0.001
+        }));
+        let buy = m.commission(&OrderSide::Buy, 100.0, 100.0, Liquidity::Taker);
+        assert!((buy - 1.1).abs() < 1e-9);
+        // 1 + 0.1 exchange + 1.0 on value + TAF capped at 0.5
+        let sell = m.commission(&OrderSide::Sell, 100.0, 100.0, Liquidity::Taker);
+        assert!((sell - 2.6).abs() < 1e-9);
+    }
+}
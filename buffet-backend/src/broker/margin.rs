@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Leverage, margin calls and the interest and funding a margin account
+/// pays or earns, e.g.
+/// `{"initial_margin": 0.5, "maintenance_margin": 0.25, "debit_interest_rate": 0.08}`.
+///
+/// The default is a cash account: longs are paid in full, nothing is ever
+/// liquidated and no interest accrues.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarginModel {
+    /// Fraction of a long's value paid from equity when it is opened; the
+    /// rest is borrowed. 0.5 allows 2x leverage, and sizing is measured
+    /// against equity / `initial_margin`.
+    pub initial_margin: f64,
+    /// Equity, as a fraction of gross position value, below which every
+    /// position is liquidated. 0 disables margin calls.
+    pub maintenance_margin: f64,
+    /// Annual rate credited on idle cash.
+    pub cash_interest_rate: f64,
+    /// Annual rate charged on borrowed cash (a negative balance).
+    pub debit_interest_rate: f64,
+    /// Perpetual-swap funding per interval, as a fraction of position
+    /// value: longs pay it to shorts when positive, and receive it when
+    /// negative.
+    pub funding_rate: f64,
+    /// Hours between funding payments, aligned to midnight UTC.
+    pub funding_interval_hours: f64,
+}
+
+impl Default for MarginModel {
+    fn default() -> Self {
+        Self {
+            initial_margin: 1.0,
+            maintenance_margin: 0.0,
+            cash_interest_rate: 0.0,
+            debit_interest_rate: 0.0,
+            funding_rate: 0.0,
+            funding_interval_hours: 8.0,
+        }
+    }
+}
+
+impl MarginModel {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.initial_margin > 0.0 && self.initial_margin <= 1.0) {
+            return Err("'initial_margin' must be in (0, 1]".to_string());
+        }
+        if !(self.maintenance_margin >= 0.0 && self.maintenance_margin <= self.initial_margin) {
+            return Err("'maintenance_margin' must be between 0 and 'initial_margin'".to_string());
+        }
+        if !(self.cash_interest_rate >= 0.0 && self.debit_interest_rate >= 0.0) {
+            return Err("Interest rates cannot be negative".to_string());
+        }
+        if !self.funding_rate.is_finite() {
+            return Err("'funding_rate' must be a number".to_string());
+        }
+        if self.funding_interval_hours.is_nan() || self.funding_interval_hours <= 0.0 {
+            return Err("'funding_interval_hours' must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Interest on `cash` over `years`: credited when positive, charged when
+    /// negative.
+    pub fn interest(&self, cash: f64, years: f64) -> f64 {
+        let rate = if cash >= 0.0 {
+            self.cash_interest_rate
+        } else {
+            self.debit_interest_rate
+        };
+        cash * rate * years
+    }
+
+    /// Funding payments due between `from` (exclusive) and `to` (inclusive).
+    pub fn funding_periods(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> i64 {
+        if self.funding_rate == 0.0 {
+            return 0;
+        }
+        let interval = (self.funding_interval_hours * 3600.0).round().max(1.0) as i64;
+        to.timestamp().div_euclid(interval) - from.timestamp().div_euclid(interval)
+    }
+
+    /// Whether `equity` has fallen below the maintenance requirement on
+    /// `gross` position value.
+    pub fn is_margin_call(&self, equity: f64, gross: f64) -> bool {
+        self.maintenance_margin > 0.0 && gross > 0.0 && equity < self.maintenance_margin * gross
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use serde_json::json;
+
+    #[test]
+    fn test_interest_credits_cash_and_charges_debit() {
+        let model: MarginModel = serde_json::from_value(json!({
+            "initial_margin": 0.5,
+            "cash_interest_rate": 0.02,
+            "debit_interest_rate": 0.08
+        }))
+        .unwrap();
+        assert!(model.validate().is_ok());
+        assert!((model.interest(1000.0, 0.5) - 10.0).abs() < 1e-9);
+        assert!((model.interest(-1000.0, 0.5) + 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_funding_is_due_at_each_interval_boundary() {
+        let model = MarginModel {
+            funding_rate: 0.0001,
+            ..MarginModel::default()
+        };
+        let midnight = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let before = midnight - Duration::minutes(1);
+        assert_eq!(model.funding_periods(before, midnight), 1);
+        assert_eq!(
+            model.funding_periods(midnight, midnight + Duration::hours(7)),
+            0
+        );
+        assert_eq!(
+            model.funding_periods(before, midnight + Duration::hours(17)),
+            3
+        );
+        assert_eq!(MarginModel::default().funding_periods(before, midnight), 0);
+    }
+
+    #[test]
+    fn test_margin_call_below_maintenance() {
+        let model = MarginModel {
+            initial_margin: 0.5,
+            maintenance_margin: 0.25,
+            ..MarginModel::default()
+        };
+        assert!(!model.is_margin_call(300.0, 1000.0));
+        assert!(model.is_margin_call(200.0, 1000.0));
+        assert!(!MarginModel::default().is_margin_call(-10.0, 1000.0));
+
+        let inverted = MarginModel {
+            maintenance_margin: 0.6,
+            ..model
+        };
+        assert!(inverted.validate().is_err());
+    }
+}
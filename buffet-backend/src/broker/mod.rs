@@ -1,5 +1,11 @@
 pub mod backtest_broker;
-pub use backtest_broker::BacktestBroker;
+pub mod commission;
+pub mod margin;
+pub mod slippage;
+pub use backtest_broker::{BacktestBroker, FillModel};
+pub use commission::{CommissionModel, Liquidity};
+pub use margin::MarginModel;
+pub use slippage::{BarLiquidity, SlippageModel};
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
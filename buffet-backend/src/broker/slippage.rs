@@ -0,0 +1,121 @@
+use crate::models::market_data::OHLCV;
+use serde::{Deserialize, Serialize};
+
+/// How far an order moves the price against itself, on top of the fixed
+/// `slippage_bps` spread.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImpactModel {
+    /// No impact: every order pays `slippage_bps`, whatever its size.
+    #[default]
+    Fixed,
+    /// `coefficient · σ · √(quantity / volume)`, with σ the bar's
+    /// volatility, so impact grows with the square root of participation.
+    SquareRoot { coefficient: f64 },
+}
+
+/// Market impact plus a cap on how much of a bar's volume one order may
+/// take, e.g. `{"type": "square_root", "coefficient": 1.0, "max_participation": 0.1}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SlippageModel {
+    #[serde(flatten)]
+    pub impact: ImpactModel,
+    /// Largest fraction of a bar's volume one order may fill; the rest is
+    /// left for later bars.
+    #[serde(default)]
+    pub max_participation: Option<f64>,
+}
+
+/// Volume and volatility of the bar orders are filling against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BarLiquidity {
+    pub volume: f64,
+    /// Parkinson estimate from the bar's range, as a fraction of price.
+    pub volatility: f64,
+}
+
+impl BarLiquidity {
+    pub fn from_bar(bar: &OHLCV) -> Self {
+        let volatility = if bar.low > 0.0 && bar.high >= bar.low {
+            (bar.high / bar.low).ln() / (2.0 * std::f64::consts::LN_2.sqrt())
+        } else {
+            0.0
+        };
+        Self {
+            volume: bar.volume.max(0.0),
+            volatility,
+        }
+    }
+}
+
+impl SlippageModel {
+    pub fn validate(&self) -> Result<(), String> {
+        if let ImpactModel::SquareRoot { coefficient } = self.impact
+            && (coefficient.is_nan() || coefficient < 0.0)
+        {
+            return Err("Slippage 'coefficient' cannot be negative".to_string());
+        }
+        if let Some(participation) = self.max_participation
+            && (participation.is_nan() || participation <= 0.0 || participation > 1.0)
+        {
+            return Err("'max_participation' must be in (0, 1]".to_string());
+        }
+        Ok(())
+    }
+
+    /// Price impact of filling `quantity` against `liquidity`, as a fraction
+    /// of price.
+    pub fn impact(&self, quantity: f64, liquidity: Option<&BarLiquidity>) -> f64 {
+        match (&self.impact, liquidity) {
+            (ImpactModel::SquareRoot { coefficient }, Some(bar)) if bar.volume > 0.0 => {
+                coefficient * bar.volatility * (quantity.max(0.0) / bar.volume).sqrt()
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// How much of `quantity` the participation cap lets fill against
+    /// `liquidity`. Without a known bar (e.g. a stop level) it all fills.
+    pub fn fillable(&self, quantity: f64, liquidity: Option<&BarLiquidity>) -> f64 {
+        match (self.max_participation, liquidity) {
+            (Some(participation), Some(bar)) => quantity.min(participation * bar.volume),
+            _ => quantity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    #[test]
+    fn test_square_root_impact_grows_with_participation() {
+        let model: SlippageModel =
+            serde_json::from_value(json!({ "type": "square_root", "coefficient": 1.0 })).unwrap();
+        let bar = BarLiquidity {
+            volume: 10_000.0,
+            volatility: 0.02,
+        };
+        assert!((model.impact(100.0, Some(&bar)) - 0.002).abs() < 1e-12);
+        assert!((model.impact(400.0, Some(&bar)) - 0.004).abs() < 1e-12);
+        assert_eq!(model.impact(400.0, None), 0.0);
+        assert_eq!(SlippageModel::default().impact(400.0, Some(&bar)), 0.0);
+    }
+
+    #[test]
+    fn test_participation_caps_fills_at_bar_volume() {
+        let model: SlippageModel =
+            serde_json::from_value(json!({ "type": "fixed", "max_participation": 0.1 })).unwrap();
+        let bar = BarLiquidity::from_bar(&OHLCV::new(Utc::now(), 10.0, 10.0, 10.0, 10.0, 500.0));
+        assert_eq!(bar.volatility, 0.0);
+        assert_eq!(model.fillable(20.0, Some(&bar)), 20.0);
+        assert_eq!(model.fillable(80.0, Some(&bar)), 50.0);
+        assert_eq!(model.fillable(80.0, None), 80.0);
+
+        let too_much: SlippageModel =
+            serde_json::from_value(json!({ "type": "fixed", "max_participation": 1.5 })).unwrap();
+        assert!(too_much.validate().is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Date rules behind the built-in calendars: exchange holidays, early closes
+//! and daylight-saving transitions.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Weekday};
+
+/// The `n`th `weekday` of `month`, counting from 1.
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+        .expect("every month has at least four of each weekday")
+}
+
+/// The last `weekday` of `month`.
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    let next_month = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    }
+    .expect("valid month");
+    let mut date = next_month - Duration::days(1);
+    while date.weekday() != weekday {
+        date -= Duration::days(1);
+    }
+    date
+}
+
+/// Easter Sunday of the Gregorian calendar (anonymous Gregorian algorithm).
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("valid Easter date")
+}
+
+/// Saturday holidays are observed the Friday before, Sunday ones the Monday
+/// after.
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date - Duration::days(1),
+        Weekday::Sun => date + Duration::days(1),
+        _ => date,
+    }
+}
+
+/// Full-day NYSE (and NASDAQ) closures in `year`.
+pub fn nyse_holidays(year: i32) -> Vec<NaiveDate> {
+    let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).expect("valid date");
+    let mut holidays = Vec::with_capacity(10);
+
+    // A New Year's Day on Saturday is not observed on the Friday before
+    let new_year = date(1, 1);
+    if new_year.weekday() != Weekday::Sat {
+        holidays.push(observed(new_year));
+    }
+    if year >= 1998 {
+        holidays.push(nth_weekday(year, 1, Weekday::Mon, 3));
+    }
+    holidays.push(nth_weekday(year, 2, Weekday::Mon, 3));
+    holidays.push(easter(year) - Duration::days(2));
+    holidays.push(last_weekday(year, 5, Weekday::Mon));
+    if year >= 2022 {
+        holidays.push(observed(date(6, 19)));
+    }
+    holidays.push(observed(date(7, 4)));
+    holidays.push(nth_weekday(year, 9, Weekday::Mon, 1));
+    holidays.push(nth_weekday(year, 11, Weekday::Thu, 4));
+    holidays.push(observed(date(12, 25)));
+    holidays
+}
+
+/// Local close of the NYSE's half days.
+pub fn nyse_early_close_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(13, 0, 0).expect("valid time")
+}
+
+/// NYSE half days in `year`: the eve of Independence Day and of Christmas
+/// when they fall on a trading day, and the day after Thanksgiving.
+pub fn nyse_early_closes(year: i32) -> Vec<NaiveDate> {
+    let holidays = nyse_holidays(year);
+    let trading = |date: &NaiveDate| {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !holidays.contains(date)
+    };
+    let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).expect("valid date");
+
+    let mut early = vec![nth_weekday(year, 11, Weekday::Thu, 4) + Duration::days(1)];
+    early.extend([date(7, 3), date(12, 24)].into_iter().filter(trading));
+    early.sort();
+    early
+}
+
+/// First and last day of US daylight saving time in `year` (second Sunday
+/// of March to the day before the first Sunday of November).
+pub fn us_daylight_saving(year: i32) -> (NaiveDate, NaiveDate) {
+    (
+        nth_weekday(year, 3, Weekday::Sun, 2),
+        nth_weekday(year, 11, Weekday::Sun, 1) - Duration::days(1),
+    )
+}
+
+/// First and last day of EU summer time in `year` (last Sunday of March to
+/// the day before the last Sunday of October).
+pub fn eu_daylight_saving(year: i32) -> (NaiveDate, NaiveDate) {
+    (
+        last_weekday(year, 3, Weekday::Sun),
+        last_weekday(year, 10, Weekday::Sun) - Duration::days(1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_nyse_holidays_2024() {
+        assert_eq!(
+            nyse_holidays(2024),
+            vec![
+                d(2024, 1, 1),
+                d(2024, 1, 15),
+                d(2024, 2, 19),
+                d(2024, 3, 29),
+                d(2024, 5, 27),
+                d(2024, 6, 19),
+                d(2024, 7, 4),
+                d(2024, 9, 2),
+                d(2024, 11, 28),
+                d(2024, 12, 25),
+            ]
+        );
+        // Observed on the nearest weekday, except a Saturday New Year's Day
+        assert!(nyse_holidays(2021).contains(&d(2021, 7, 5)));
+        assert!(nyse_holidays(2021).contains(&d(2021, 12, 24)));
+        assert!(!nyse_holidays(2021).contains(&d(2021, 12, 31)));
+        assert!(
+            !nyse_holidays(2022)
+                .iter()
+                .any(|h| h.month() == 1 && h.day() < 3)
+        );
+    }
+
+    #[test]
+    fn test_nyse_early_closes_skip_holidays() {
+        assert_eq!(
+            nyse_early_closes(2024),
+            vec![d(2024, 7, 3), d(2024, 11, 29), d(2024, 12, 24)]
+        );
+        // Christmas Eve 2021 was the observed Christmas holiday
+        assert_eq!(nyse_early_closes(2021), vec![d(2021, 11, 26)]);
+    }
+
+    #[test]
+    fn test_daylight_saving_transitions() {
+        assert_eq!(us_daylight_saving(2024), (d(2024, 3, 10), d(2024, 11, 2)));
+        assert_eq!(eu_daylight_saving(2024), (d(2024, 3, 31), d(2024, 10, 26)));
+    }
+}
@@ -0,0 +1,579 @@
+//! Trading calendars: which days a market trades, its hours, and what that
+//! means for bar data — gaps in collected series, how many bars make a year,
+//! and when a session closes.
+//!
+//! NYSE, NASDAQ, 24/7 crypto and 24/5 forex calendars are built in. Custom
+//! calendars are loaded from JSON files (see [`load_file`]), e.g.
+//! `{"name": "xetra", "utc_offset_minutes": 60, "daylight_saving": "eu",
+//! "session": {"open": "09:00:00", "close": "17:30:00"}, "holidays": ["2024-12-24"]}`.
+
+pub mod holidays;
+
+use crate::utils::metrics::SECONDS_PER_YEAR;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+/// Which daylight-saving rule shifts a calendar's UTC offset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DaylightSaving {
+    #[default]
+    None,
+    /// Second Sunday of March to the first Sunday of November.
+    Us,
+    /// Last Sunday of March to the last Sunday of October.
+    Eu,
+}
+
+/// Holidays generated by rule rather than listed date by date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HolidayRules {
+    /// NYSE closures and half days, also followed by NASDAQ.
+    Nyse,
+}
+
+/// Regular trading hours, in the calendar's local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub open: NaiveTime,
+    pub close: NaiveTime,
+}
+
+/// A day the market closes early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EarlyClose {
+    pub date: NaiveDate,
+    pub close: NaiveTime,
+}
+
+/// A stretch of open market time with no bars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gap {
+    /// The last bar before the gap.
+    pub after: DateTime<Utc>,
+    /// The first bar after it.
+    pub before: DateTime<Utc>,
+    pub missing_bars: i64,
+}
+
+fn weekdays() -> Vec<Weekday> {
+    vec![
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ]
+}
+
+/// When a market trades.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradingCalendar {
+    pub name: String,
+    /// Local standard time as minutes east of UTC (e.g. -300 for New York).
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    #[serde(default)]
+    pub daylight_saving: DaylightSaving,
+    /// Regular hours; `None` trades around the clock on trading days.
+    #[serde(default)]
+    pub session: Option<Session>,
+    #[serde(default = "weekdays")]
+    pub trading_days: Vec<Weekday>,
+    #[serde(default)]
+    pub holiday_rules: Option<HolidayRules>,
+    /// Closures on top of `holiday_rules`.
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+    #[serde(default)]
+    pub early_closes: Vec<EarlyClose>,
+}
+
+/// Trading days are averaged over these ten years, so the factor does not
+/// depend on when a run happens.
+const REFERENCE_YEARS: std::ops::Range<i32> = 2015..2025;
+
+impl TradingCalendar {
+    /// New York Stock Exchange: 09:30–16:00 Eastern, NYSE holidays and
+    /// 13:00 half days.
+    pub fn nyse() -> Self {
+        Self {
+            name: "nyse".to_string(),
+            utc_offset_minutes: -300,
+            daylight_saving: DaylightSaving::Us,
+            session: Some(Session {
+                open: NaiveTime::from_hms_opt(9, 30, 0).expect("valid time"),
+                close: NaiveTime::from_hms_opt(16, 0, 0).expect("valid time"),
+            }),
+            trading_days: weekdays(),
+            holiday_rules: Some(HolidayRules::Nyse),
+            holidays: Vec::new(),
+            early_closes: Vec::new(),
+        }
+    }
+
+    /// NASDAQ keeps the NYSE's hours and holidays.
+    pub fn nasdaq() -> Self {
+        Self {
+            name: "nasdaq".to_string(),
+            ..Self::nyse()
+        }
+    }
+
+    /// Crypto markets never close.
+    pub fn crypto() -> Self {
+        Self {
+            name: "crypto".to_string(),
+            utc_offset_minutes: 0,
+            daylight_saving: DaylightSaving::None,
+            session: None,
+            trading_days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ],
+            holiday_rules: None,
+            holidays: Vec::new(),
+            early_closes: Vec::new(),
+        }
+    }
+
+    /// Spot FX trades around the clock on weekdays, taken here as whole UTC
+    /// days.
+    pub fn forex() -> Self {
+        Self {
+            name: "forex".to_string(),
+            trading_days: weekdays(),
+            ..Self::crypto()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Calendar 'name' cannot be empty".to_string());
+        }
+        if self.utc_offset_minutes.abs() > 18 * 60 {
+            return Err(format!(
+                "Calendar '{}': 'utc_offset_minutes' must be within 18 hours",
+                self.name
+            ));
+        }
+        if self.trading_days.is_empty() {
+            return Err(format!("Calendar '{}' has no trading days", self.name));
+        }
+        if let Some(session) = self.session
+            && session.open >= session.close
+        {
+            return Err(format!(
+                "Calendar '{}': session must open before it closes",
+                self.name
+            ));
+        }
+        if let Some(early) = self.early_closes.iter().find(|early| {
+            self.session
+                .is_none_or(|session| early.close <= session.open || early.close > session.close)
+        }) {
+            return Err(format!(
+                "Calendar '{}': early close on {} is outside the session",
+                self.name, early.date
+            ));
+        }
+        Ok(())
+    }
+
+    /// UTC offset of local time on `date`.
+    fn offset(&self, date: NaiveDate) -> FixedOffset {
+        let (start, end) = match self.daylight_saving {
+            DaylightSaving::None => (None, None),
+            DaylightSaving::Us => {
+                let (start, end) = holidays::us_daylight_saving(date.year());
+                (Some(start), Some(end))
+            }
+            DaylightSaving::Eu => {
+                let (start, end) = holidays::eu_daylight_saving(date.year());
+                (Some(start), Some(end))
+            }
+        };
+        let summer = start.zip(end).is_some_and(|(s, e)| s <= date && date <= e);
+        let minutes = self.utc_offset_minutes + if summer { 60 } else { 0 };
+        FixedOffset::east_opt(minutes * 60).expect("offset validated within 18 hours")
+    }
+
+    /// The local date at `timestamp`.
+    pub fn local_date(&self, timestamp: DateTime<Utc>) -> NaiveDate {
+        let standard = timestamp + Duration::minutes(self.utc_offset_minutes as i64);
+        timestamp
+            .with_timezone(&self.offset(standard.date_naive()))
+            .date_naive()
+    }
+
+    fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.holidays.contains(&date)
+            || match self.holiday_rules {
+                Some(HolidayRules::Nyse) => holidays::nyse_holidays(date.year()).contains(&date),
+                None => false,
+            }
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        self.trading_days.contains(&date.weekday()) && !self.is_holiday(date)
+    }
+
+    /// Local close on `date`, allowing for early closes.
+    fn close_time(&self, date: NaiveDate, session: Session) -> NaiveTime {
+        if let Some(early) = self.early_closes.iter().find(|early| early.date == date) {
+            return early.close;
+        }
+        match self.holiday_rules {
+            Some(HolidayRules::Nyse)
+                if holidays::nyse_early_closes(date.year()).contains(&date) =>
+            {
+                holidays::nyse_early_close_time().min(session.close)
+            }
+            _ => session.close,
+        }
+    }
+
+    /// Open and close of the session on local `date`, in UTC, or `None` when
+    /// the market does not trade that day.
+    pub fn session_bounds(&self, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        let offset = self.offset(date);
+        let at = |time: NaiveTime| {
+            date.and_time(time)
+                .and_local_timezone(offset)
+                .single()
+                .expect("fixed offsets are unambiguous")
+                .with_timezone(&Utc)
+        };
+        match self.session {
+            Some(session) => Some((at(session.open), at(self.close_time(date, session)))),
+            None => {
+                let open = at(NaiveTime::MIN);
+                Some((open, open + Duration::days(1)))
+            }
+        }
+    }
+
+    /// Whether the market is open at `timestamp`.
+    pub fn is_open(&self, timestamp: DateTime<Utc>) -> bool {
+        self.session_close(timestamp).is_some()
+    }
+
+    /// Close of the session open at `timestamp`, if one is.
+    pub fn session_close(&self, timestamp: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.session_bounds(self.local_date(timestamp))
+            .filter(|(open, close)| *open <= timestamp && timestamp < *close)
+            .map(|(_, close)| close)
+    }
+
+    /// Open of the `sessions`th trading day ending with the one at or before
+    /// `end`.
+    pub fn sessions_back(&self, end: DateTime<Utc>, sessions: usize) -> DateTime<Utc> {
+        let mut date = self.local_date(end);
+        let mut open = end;
+        let mut found = 0;
+        // A year without a single trading day means a broken calendar
+        for _ in 0..(sessions.max(1) * 7 + 366) {
+            if let Some((session_open, _)) = self.session_bounds(date)
+                && session_open <= end
+            {
+                open = session_open;
+                found += 1;
+                if found >= sessions {
+                    break;
+                }
+            }
+            date -= Duration::days(1);
+        }
+        open
+    }
+
+    /// Length of a regular session in seconds.
+    pub fn session_seconds(&self) -> f64 {
+        match self.session {
+            Some(session) => (session.close - session.open).num_seconds() as f64,
+            None => 86_400.0,
+        }
+    }
+
+    /// Average trading days in a year.
+    pub fn trading_days_per_year(&self) -> f64 {
+        let start = NaiveDate::from_ymd_opt(REFERENCE_YEARS.start, 1, 1).expect("valid date");
+        let end = NaiveDate::from_ymd_opt(REFERENCE_YEARS.end, 1, 1).expect("valid date");
+        let days = start
+            .iter_days()
+            .take_while(|date| *date < end)
+            .filter(|date| self.is_trading_day(*date))
+            .count();
+        days as f64 * 365.25 / (end - start).num_days() as f64
+    }
+
+    /// Bars of `bar_seconds` in a year of this calendar, to annualise
+    /// per-bar statistics: sessions per year for daily bars, times bars per
+    /// session for intraday ones. Bars of two days or more (weekly, monthly)
+    /// follow calendar time.
+    pub fn periods_per_year(&self, bar_seconds: f64) -> f64 {
+        if bar_seconds.is_nan() || bar_seconds <= 0.0 {
+            return 0.0;
+        }
+        let session = self.session_seconds();
+        if bar_seconds >= 2.0 * 86_400.0 {
+            SECONDS_PER_YEAR / bar_seconds
+        } else if bar_seconds >= session {
+            self.trading_days_per_year()
+        } else {
+            self.trading_days_per_year() * (session / bar_seconds).ceil()
+        }
+    }
+
+    /// Seconds the market is open between `from` and `to`.
+    fn open_seconds(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> i64 {
+        if to <= from {
+            return 0;
+        }
+        let mut date = self.local_date(from);
+        let last = self.local_date(to);
+        let mut seconds = 0;
+        while date <= last {
+            if let Some((open, close)) = self.session_bounds(date) {
+                let (start, end) = (open.max(from), close.min(to));
+                if end > start {
+                    seconds += (end - start).num_seconds();
+                }
+            }
+            date += Duration::days(1);
+        }
+        seconds
+    }
+
+    /// Bars of `bar_seconds` the market should have produced between bars
+    /// at `from` and `to`. Daily (or longer) bars count the trading days in
+    /// between; intraday bars count open time.
+    pub fn missing_bars(&self, from: DateTime<Utc>, to: DateTime<Utc>, bar_seconds: f64) -> i64 {
+        if bar_seconds.is_nan() || bar_seconds <= 0.0 || to <= from {
+            return 0;
+        }
+        if bar_seconds >= self.session_seconds() {
+            let (first, last) = (self.local_date(from), self.local_date(to));
+            return first
+                .iter_days()
+                .skip(1)
+                .take_while(|date| *date < last)
+                .filter(|date| self.is_trading_day(*date))
+                .count() as i64;
+        }
+        let bar = Duration::seconds(bar_seconds.round() as i64);
+        let open = self.open_seconds(from + bar, to) as f64;
+        (open / bar_seconds + 1e-9).floor() as i64
+    }
+
+    /// Stretches of open market time with no bars in `timestamps` (oldest
+    /// first), for bars of `bar_seconds`.
+    pub fn find_gaps(&self, timestamps: &[DateTime<Utc>], bar_seconds: f64) -> Vec<Gap> {
+        timestamps
+            .windows(2)
+            .filter_map(|w| {
+                let missing_bars = self.missing_bars(w[0], w[1], bar_seconds);
+                (missing_bars > 0).then_some(Gap {
+                    after: w[0],
+                    before: w[1],
+                    missing_bars,
+                })
+            })
+            .collect()
+    }
+}
+
+fn custom_calendars() -> &'static RwLock<HashMap<String, TradingCalendar>> {
+    static CUSTOM: OnceLock<RwLock<HashMap<String, TradingCalendar>>> = OnceLock::new();
+    CUSTOM.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// The calendar registered as `name` (case-insensitive). Custom calendars
+/// take precedence over the built-in ones they share a name with.
+pub fn by_name(name: &str) -> Option<TradingCalendar> {
+    let name = name.trim().to_lowercase();
+    if let Some(calendar) = custom_calendars()
+        .read()
+        .expect("calendar registry poisoned")
+        .get(&name)
+    {
+        return Some(calendar.clone());
+    }
+    match name.as_str() {
+        "nyse" => Some(TradingCalendar::nyse()),
+        "nasdaq" => Some(TradingCalendar::nasdaq()),
+        "crypto" => Some(TradingCalendar::crypto()),
+        "forex" => Some(TradingCalendar::forex()),
+        _ => None,
+    }
+}
+
+/// Make a custom calendar available by its (lowercased) name.
+pub fn register(mut calendar: TradingCalendar) -> Result<(), String> {
+    calendar.validate()?;
+    calendar.name = calendar.name.trim().to_lowercase();
+    custom_calendars()
+        .write()
+        .expect("calendar registry poisoned")
+        .insert(calendar.name.clone(), calendar);
+    Ok(())
+}
+
+/// Register the calendars in the JSON file at `path`, holding either one
+/// calendar or an array of them. Returns their names.
+pub fn load_file(path: &Path) -> Result<Vec<String>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read calendar file {}: {}", path.display(), e))?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid calendar file {}: {}", path.display(), e))?;
+    let calendars: Vec<TradingCalendar> = match value {
+        serde_json::Value::Array(_) => serde_json::from_value(value),
+        _ => serde_json::from_value(value).map(|calendar| vec![calendar]),
+    }
+    .map_err(|e| format!("Invalid calendar in {}: {}", path.display(), e))?;
+
+    let mut names = Vec::with_capacity(calendars.len());
+    for calendar in calendars {
+        names.push(calendar.name.trim().to_lowercase());
+        register(calendar)?;
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_nyse_sessions_follow_daylight_saving_and_half_days() {
+        let nyse = TradingCalendar::nyse();
+        // 09:30 Eastern is 14:30 UTC in winter and 13:30 UTC in summer
+        let winter = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        assert_eq!(
+            nyse.session_bounds(winter),
+            Some((utc(2024, 1, 2, 14, 30), utc(2024, 1, 2, 21, 0)))
+        );
+        let summer = NaiveDate::from_ymd_opt(2024, 7, 3).unwrap();
+        assert_eq!(
+            nyse.session_bounds(summer),
+            Some((utc(2024, 7, 3, 13, 30), utc(2024, 7, 3, 17, 0)))
+        );
+        assert!(nyse.is_open(utc(2024, 7, 3, 16, 59)));
+        assert!(!nyse.is_open(utc(2024, 7, 3, 17, 0)));
+        assert!(!nyse.is_open(utc(2024, 7, 4, 15, 0)));
+        assert!(!nyse.is_open(utc(2024, 7, 6, 15, 0)));
+        assert_eq!(
+            nyse.session_close(utc(2024, 1, 2, 15, 0)),
+            Some(utc(2024, 1, 2, 21, 0))
+        );
+    }
+
+    #[test]
+    fn test_annualisation_depends_on_calendar_and_bar_size() {
+        let nyse = TradingCalendar::nyse();
+        let days = nyse.trading_days_per_year();
+        assert!((days - 252.0).abs() < 1.0);
+        assert_eq!(nyse.periods_per_year(86_400.0), days);
+        assert_eq!(nyse.periods_per_year(60.0), days * 390.0);
+        assert!((nyse.periods_per_year(7.0 * 86_400.0) - 52.18).abs() < 0.01);
+
+        let crypto = TradingCalendar::crypto();
+        assert!((crypto.trading_days_per_year() - 365.25).abs() < 1e-9);
+        assert!((crypto.periods_per_year(60.0) - SECONDS_PER_YEAR / 60.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gaps_ignore_closed_market() {
+        let nyse = TradingCalendar::nyse();
+        // Friday close to Monday open, then a missing hour on Monday
+        let bars = [
+            utc(2024, 1, 5, 20, 0),
+            utc(2024, 1, 8, 14, 30),
+            utc(2024, 1, 8, 15, 30),
+            utc(2024, 1, 8, 17, 30),
+        ];
+        let gaps = nyse.find_gaps(&bars, 3600.0);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].after, bars[2]);
+        assert_eq!(gaps[0].missing_bars, 1);
+
+        // Daily bars across a holiday weekend miss nothing; a lost Tuesday is
+        // one missing bar
+        let daily = [
+            utc(2024, 1, 12, 21, 0),
+            utc(2024, 1, 16, 21, 0),
+            utc(2024, 1, 18, 21, 0),
+        ];
+        let gaps = nyse.find_gaps(&daily, 86_400.0);
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(gaps[0].missing_bars, 1);
+
+        let crypto = TradingCalendar::crypto();
+        let gaps = crypto.find_gaps(&[utc(2024, 1, 6, 0, 0), utc(2024, 1, 6, 0, 5)], 60.0);
+        assert_eq!(gaps[0].missing_bars, 4);
+    }
+
+    #[test]
+    fn test_custom_calendars_load_from_file() {
+        let path = std::env::temp_dir().join(format!("calendar-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"[{
+                "name": "Test_Exchange",
+                "utc_offset_minutes": 60,
+                "daylight_saving": "eu",
+                "session": { "open": "09:00:00", "close": "17:30:00" },
+                "holidays": ["2024-12-24"]
+            }]"#,
+        )
+        .unwrap();
+        assert_eq!(load_file(&path).unwrap(), vec!["test_exchange"]);
+        std::fs::remove_file(&path).unwrap();
+
+        let calendar = by_name("TEST_EXCHANGE").unwrap();
+        assert_eq!(calendar.trading_days.len(), 5);
+        assert!(!calendar.is_trading_day(NaiveDate::from_ymd_opt(2024, 12, 24).unwrap()));
+        // 09:00 CEST
+        assert!(calendar.is_open(utc(2024, 6, 3, 7, 0)));
+        assert!(!calendar.is_open(utc(2024, 6, 3, 6, 59)));
+
+        assert!(by_name("nasdaq").is_some());
+        assert!(by_name("unknown").is_none());
+        let inverted = TradingCalendar {
+            session: Some(Session {
+                open: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                close: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            }),
+            ..TradingCalendar::nyse()
+        };
+        assert!(register(inverted).is_err());
+    }
+
+    #[test]
+    fn test_sessions_back_skips_closed_days() {
+        let nyse = TradingCalendar::nyse();
+        // Tuesday after MLK day: Tue, Fri, Thu
+        assert_eq!(
+            nyse.sessions_back(utc(2024, 1, 16, 22, 0), 3),
+            utc(2024, 1, 11, 14, 30)
+        );
+    }
+}
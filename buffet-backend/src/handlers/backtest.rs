@@ -1,7 +1,7 @@
 use crate::{
-    actors::messages::RunBacktest,
     error::Result,
-    models::backtest::{Backtest, BacktestTrade, CreateBacktestDto},
+    models::backtest::{Backtest, BacktestTrade, CreateBacktestDto, EquityPoint},
+    models::monte_carlo::{MonteCarloDto, MonteCarloReport},
     state::AppState,
 };
 use axum::{
@@ -30,18 +30,20 @@ pub async fn run_backtest(
     // 1. Create backtest record
     let backtest = Backtest::create(dto, &state.db).await?;
 
-    // 2. Trigger backtest actor (fire-and-forget)
-    let _ = state
-        .backtest
-        .tell(RunBacktest {
-            backtest_id: backtest.id.clone(),
-        })
-        .send()
-        .await;
+    // 2. Trigger a backtest worker (fire-and-forget)
+    state.backtest.run(backtest.id.clone()).await;
 
     Ok((StatusCode::ACCEPTED, Json(backtest)))
 }
 
+pub async fn cancel_backtest(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Backtest>> {
+    let backtest = Backtest::cancel(&id, &state.db).await?;
+    Ok(Json(backtest))
+}
+
 pub async fn get_backtest_trades(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -49,3 +51,22 @@ pub async fn get_backtest_trades(
     let trades = BacktestTrade::find_by_backtest(&id, &state.db).await?;
     Ok(Json(trades))
 }
+
+pub async fn get_backtest_equity(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<EquityPoint>>> {
+    // 404 for an unknown backtest rather than an empty curve
+    Backtest::find_by_id(&id, &state.db).await?;
+    let curve = EquityPoint::find_by_backtest(&id, &state.db).await?;
+    Ok(Json(curve))
+}
+
+pub async fn run_monte_carlo(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(dto): Json<MonteCarloDto>,
+) -> Result<Json<MonteCarloReport>> {
+    let report = MonteCarloReport::run(&id, dto, &state.db).await?;
+    Ok(Json(report))
+}
@@ -26,13 +26,14 @@ pub async fn trigger_collection(
     Json(req): Json<CollectRequest>,
 ) -> Result<(StatusCode, Json<CollectResponse>)> {
     let symbol = req.symbol.clone();
+    let asset_type = req.asset_type.unwrap_or_else(|| "stock".to_string());
 
     if let (Some(start), Some(end)) = (req.start, req.end) {
         let _ = state
             .collector
             .tell(CollectHistorical {
                 symbol: symbol.clone(),
-                asset_type: req.asset_type.unwrap_or_else(|| "stock".to_string()),
+                asset_type,
                 start,
                 end,
             })
@@ -43,6 +44,7 @@ pub async fn trigger_collection(
             .collector
             .tell(CollectData {
                 symbol: symbol.clone(),
+                asset_type,
             })
             .send()
             .await;
@@ -5,3 +5,5 @@ pub mod order;
 pub mod position;
 pub mod signal;
 pub mod strategy;
+pub mod sweep;
+pub mod walk_forward;
@@ -5,6 +5,7 @@ use crate::{
         validate_parameters, CreateStrategyDto, Strategy, StrategyStatus, UpdateStrategyDto,
     },
     state::AppState,
+    strategies::{StrategyKindInfo, StrategyRegistry},
 };
 use axum::{
     Json,
@@ -24,6 +25,11 @@ pub async fn list_strategies(
     Ok(Json(strategies))
 }
 
+/// List every registered strategy kind with its parameter schema and defaults.
+pub async fn list_strategy_kinds() -> Json<Vec<StrategyKindInfo>> {
+    Json(StrategyRegistry::global().kinds())
+}
+
 pub async fn get_strategy(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -0,0 +1,39 @@
+use crate::{
+    error::Result,
+    models::sweep::{BacktestSweep, CreateSweepDto, SweepDetail},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+pub async fn list_sweeps(State(state): State<AppState>) -> Result<Json<Vec<BacktestSweep>>> {
+    let sweeps = BacktestSweep::find_all(&state.db).await?;
+    Ok(Json(sweeps))
+}
+
+pub async fn get_sweep(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<SweepDetail>> {
+    let detail = BacktestSweep::detail(&id, &state.db).await?;
+    Ok(Json(detail))
+}
+
+pub async fn run_sweep(
+    State(state): State<AppState>,
+    Json(dto): Json<CreateSweepDto>,
+) -> Result<(StatusCode, Json<SweepDetail>)> {
+    // 1. Create the sweep and one backtest per parameter combination
+    let (sweep, runs) = BacktestSweep::create(dto, &state.db).await?;
+
+    // 2. Fan the runs out across the backtest workers (fire-and-forget)
+    for run in &runs {
+        state.backtest.run(run.id.clone()).await;
+    }
+
+    let detail = BacktestSweep::detail(&sweep.id, &state.db).await?;
+    Ok((StatusCode::ACCEPTED, Json(detail)))
+}
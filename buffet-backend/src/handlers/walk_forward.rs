@@ -0,0 +1,43 @@
+use crate::{
+    actors::messages::RunWalkForward,
+    error::Result,
+    models::walk_forward::{CreateWalkForwardDto, WalkForward, WalkForwardDetail},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+pub async fn list_walk_forwards(State(state): State<AppState>) -> Result<Json<Vec<WalkForward>>> {
+    let walk_forwards = WalkForward::find_all(&state.db).await?;
+    Ok(Json(walk_forwards))
+}
+
+pub async fn get_walk_forward(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<WalkForwardDetail>> {
+    let detail = WalkForward::detail(&id, &state.db).await?;
+    Ok(Json(detail))
+}
+
+pub async fn run_walk_forward(
+    State(state): State<AppState>,
+    Json(dto): Json<CreateWalkForwardDto>,
+) -> Result<(StatusCode, Json<WalkForward>)> {
+    // 1. Create the analysis and its fold windows
+    let walk_forward = WalkForward::create(dto, &state.db).await?;
+
+    // 2. Trigger the walk-forward actor (fire-and-forget)
+    let _ = state
+        .walk_forward
+        .tell(RunWalkForward {
+            walk_forward_id: walk_forward.id.clone(),
+        })
+        .send()
+        .await;
+
+    Ok((StatusCode::ACCEPTED, Json(walk_forward)))
+}
@@ -1,6 +1,7 @@
 pub mod actors;
 pub mod providers;
 pub mod broker;
+pub mod calendar;
 pub mod config;
 pub mod db;
 pub mod error;
@@ -8,6 +9,7 @@ pub mod handlers;
 pub mod models;
 pub mod routes;
 pub mod state;
+pub mod strategies;
 pub mod telemetry;
 pub mod tsdb;
 pub mod utils;
@@ -1,6 +1,8 @@
 use buffet_backend::{
     actors::messages::LoadStrategies,
-    config, db, routes,
+    config, db,
+    models::walk_forward::WalkForward,
+    routes,
     telemetry::{get_subscriber, init_subscriber},
 };
 use kameo::actor::Spawn;
@@ -20,6 +22,19 @@ async fn main() -> anyhow::Result<()> {
     })?;
     let addr = config.server_addr;
 
+    // Register custom trading calendars before anything can reference them
+    for path in &config.calendar_files {
+        let names = buffet_backend::calendar::load_file(path).map_err(|e| {
+            error!("Configuration error: {}", e);
+            anyhow::anyhow!(e)
+        })?;
+        info!(
+            "Loaded trading calendars {:?} from {}",
+            names,
+            path.display()
+        );
+    }
+
     // Set up database connections
     let db_pool = db::setup_database(&config.database_url).await?;
     let tsdb_pool = db::setup_tsdb(&config.tsdb_url).await?;
@@ -53,8 +68,35 @@ async fn main() -> anyhow::Result<()> {
         ),
         mailbox::bounded(config.actor.mailbox_size),
     );
-    let backtest_actor = buffet_backend::actors::BacktestActor::spawn_with_mailbox(
-        buffet_backend::actors::BacktestActor::new(db_pool.clone(), storage_actor.clone()),
+    let backtest_workers = buffet_backend::actors::BacktestWorkers::spawn(
+        config.actor.backtest_workers,
+        db_pool.clone(),
+        storage_actor.clone(),
+        config.actor.mailbox_size,
+    );
+    // Re-run or fail backtests a previous process left unfinished, then keep
+    // watching the leases of running ones
+    let recovery = config.actor.backtest_recovery;
+    let lease = std::time::Duration::from_secs(config.actor.backtest_lease_secs);
+    match backtest_workers.recover_orphans(&db_pool, recovery).await {
+        Ok(count) => info!(
+            "Recovered {} unfinished backtest(s) ({:?})",
+            count, recovery
+        ),
+        Err(e) => error!("Failed to recover unfinished backtests: {}", e),
+    }
+    backtest_workers.spawn_lease_monitor(db_pool.clone(), lease, recovery);
+    // Walk-forwards are driven from memory, so none a previous process left
+    // running can pick up where it stopped
+    match WalkForward::fail_unfinished("Walk-forward was interrupted by a server restart", &db_pool)
+        .await
+    {
+        Ok(count) => info!("Failed {} unfinished walk-forward(s)", count),
+        Err(e) => error!("Failed to recover unfinished walk-forwards: {}", e),
+    }
+
+    let walk_forward_actor = buffet_backend::actors::WalkForwardActor::spawn_with_mailbox(
+        buffet_backend::actors::WalkForwardActor::new(db_pool.clone(), backtest_workers.clone()),
         mailbox::bounded(config.actor.mailbox_size),
     );
 
@@ -65,7 +107,8 @@ async fn main() -> anyhow::Result<()> {
         collector_actor.clone(),
         strategy_actor.clone(),
         execution_actor.clone(),
-        backtest_actor.clone(),
+        backtest_workers.clone(),
+        walk_forward_actor.clone(),
     );
 
     // Start server
@@ -1,7 +1,13 @@
+use crate::broker::{CommissionModel, FillModel, MarginModel, SlippageModel};
+use crate::calendar;
 use crate::error::{AppError, Result};
+use crate::models::strategy::{Strategy, StrategyType, validate_parameters};
+use crate::strategies::StrategyRegistry;
+use crate::utils::metrics::{BenchmarkComparison, Tearsheet};
+use crate::utils::vectorized::VectorizedStrategy;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, Pool, Sqlite};
+use sqlx::{FromRow, Pool, Sqlite, SqliteConnection};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -11,6 +17,8 @@ pub enum BacktestStatus {
     Running,
     Completed,
     Failed,
+    /// Stopped on request before it finished.
+    Cancelled,
 }
 
 impl std::fmt::Display for BacktestStatus {
@@ -20,6 +28,73 @@ impl std::fmt::Display for BacktestStatus {
             BacktestStatus::Running => write!(f, "running"),
             BacktestStatus::Completed => write!(f, "completed"),
             BacktestStatus::Failed => write!(f, "failed"),
+            BacktestStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// Why a backtest trade was closed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    /// The strategy signalled the exit.
+    Signal,
+    StopLoss,
+    TakeProfit,
+    TrailingStop,
+    /// Liquidated when the data ran out.
+    EndOfData,
+    /// Liquidated because equity fell below the maintenance margin.
+    MarginCall,
+    /// Flattened ahead of the session close.
+    SessionClose,
+}
+
+impl std::fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExitReason::Signal => write!(f, "signal"),
+            ExitReason::StopLoss => write!(f, "stop_loss"),
+            ExitReason::TakeProfit => write!(f, "take_profit"),
+            ExitReason::TrailingStop => write!(f, "trailing_stop"),
+            ExitReason::EndOfData => write!(f, "end_of_data"),
+            ExitReason::MarginCall => write!(f, "margin_call"),
+            ExitReason::SessionClose => write!(f, "session_close"),
+        }
+    }
+}
+
+/// How a backtest is simulated.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BacktestEngine {
+    /// Bar by bar through the broker, supporting every strategy and model.
+    #[default]
+    Event,
+    /// Column-wise over the whole series with Polars, for fast research
+    /// runs of single-symbol strategies. Requires `sizing.method =
+    /// fixed_quantity`, which is also what a strategy without a sizing
+    /// block trades (see [`crate::utils::vectorized`]).
+    Vectorized,
+}
+
+impl std::fmt::Display for BacktestEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BacktestEngine::Event => write!(f, "event"),
+            BacktestEngine::Vectorized => write!(f, "vectorized"),
+        }
+    }
+}
+
+impl std::str::FromStr for BacktestEngine {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "event" => Ok(BacktestEngine::Event),
+            "vectorized" => Ok(BacktestEngine::Vectorized),
+            other => Err(format!("Unknown backtest engine '{}'", other)),
         }
     }
 }
@@ -28,6 +103,7 @@ impl std::fmt::Display for BacktestStatus {
 pub struct Backtest {
     pub id: String,
     pub strategy_id: String,
+    /// First entry of `symbols`, kept for single-symbol clients.
     pub symbol: String,
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
@@ -42,10 +118,181 @@ pub struct Backtest {
     // T11 additions
     pub commission_rate: f64,
     pub slippage_bps: f64,
+    /// Strategy, parameters, sizing, exits and models the run used (JSON).
+    /// Its `sizing` is the resolved policy: a strategy without a `sizing`
+    /// block records `{"method": "fixed_quantity", "quantity": 1.0}`, as
+    /// backtests trade one unit per entry rather than all of their equity.
     pub run_config: Option<String>,
     pub trade_count: Option<i64>,
     pub win_rate: Option<f64>,
     pub profit_factor: Option<f64>,
+    pub symbols: String, // JSON array string e.g. '["AAPL","GOOG"]'
+    /// Whether a Sell with no position opens a short.
+    pub allow_short: bool,
+    /// Annualised fee on the market value of open shorts (e.g. 0.03 = 3%/yr).
+    pub borrow_fee_rate: f64,
+    /// Collateral required on top of the short proceeds, as a fraction of
+    /// the short's value (e.g. 0.5 = Reg T 150% total).
+    pub short_margin_rate: f64,
+    /// Which bar price market orders fill at (see [`FillModel`]).
+    pub fill_model: String,
+    /// Parameters this run used instead of its strategy's (JSON), if overridden.
+    pub parameters: Option<String>,
+    /// The sweep this run belongs to, if any.
+    pub sweep_id: Option<String>,
+    /// Bars from here until `start_time` warm up indicators without trading.
+    pub warmup_start_time: Option<DateTime<Utc>>,
+    // Tearsheet metrics (see `Tearsheet`)
+    pub cagr: Option<f64>,
+    pub annualized_volatility: Option<f64>,
+    pub annualized_sharpe_ratio: Option<f64>,
+    pub sortino_ratio: Option<f64>,
+    pub calmar_ratio: Option<f64>,
+    pub max_drawdown_duration_secs: Option<i64>,
+    /// Seconds from the deepest drawdown's trough back to its peak; null if
+    /// it never recovered.
+    pub recovery_time_secs: Option<i64>,
+    pub exposure_time: Option<f64>,
+    pub turnover: Option<f64>,
+    pub average_win: Option<f64>,
+    pub average_loss: Option<f64>,
+    pub expectancy: Option<f64>,
+    pub largest_win: Option<f64>,
+    pub largest_loss: Option<f64>,
+    pub max_consecutive_losses: Option<i64>,
+    /// Symbol whose buy-and-hold the run is compared against.
+    pub benchmark_symbol: Option<String>,
+    // Comparison with the benchmark (see `BenchmarkComparison`)
+    pub benchmark_return: Option<f64>,
+    pub alpha: Option<f64>,
+    pub beta: Option<f64>,
+    pub information_ratio: Option<f64>,
+    pub tracking_error: Option<f64>,
+    pub excess_return: Option<f64>,
+    /// Bars simulated so far, updated periodically while running.
+    pub bars_processed: i64,
+    /// Bars the run will simulate, once its data is loaded.
+    pub bars_total: Option<i64>,
+    /// Identifies the worker run holding the backtest; a run that loses it
+    /// stops at its next heartbeat.
+    #[serde(skip_serializing)]
+    pub lease_id: Option<String>,
+    /// Last sign of life from the worker running the backtest.
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    /// Commission schedule and fees (JSON, see [`CommissionModel`]); null
+    /// for a flat `commission_rate`.
+    pub commission_model: Option<String>,
+    /// Market impact and participation cap (JSON, see [`SlippageModel`]);
+    /// null for a fixed `slippage_bps`.
+    pub slippage_model: Option<String>,
+    /// Leverage, margin calls, interest and funding (JSON, see
+    /// [`MarginModel`]); null for a cash account.
+    pub margin_model: Option<String>,
+    /// Trading calendar every symbol follows; null for each symbol's asset
+    /// type's.
+    pub calendar: Option<String>,
+    /// Simulation engine (see [`BacktestEngine`]).
+    pub engine: String,
+}
+
+/// Portfolio value at one point of a backtest.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, FromRow)]
+pub struct EquityPoint {
+    pub timestamp: DateTime<Utc>,
+    pub equity: f64,
+    /// Fall from the highest equity so far, as a fraction of that peak.
+    #[serde(default)]
+    pub drawdown: f64,
+    /// Gross value of open positions, long plus short, as a fraction of equity.
+    #[serde(default)]
+    pub exposure: f64,
+    /// Value of the same initial balance held in the benchmark.
+    #[serde(default)]
+    pub benchmark_equity: Option<f64>,
+}
+
+/// Rows per INSERT when saving an equity curve, well under SQLite's bind limit.
+const EQUITY_INSERT_CHUNK: usize = 1000;
+
+impl EquityPoint {
+    pub fn new(timestamp: DateTime<Utc>, equity: f64, exposure: f64) -> Self {
+        Self {
+            timestamp,
+            equity,
+            drawdown: 0.0,
+            exposure,
+            benchmark_equity: None,
+        }
+    }
+
+    /// Set each point's drawdown from the running equity peak.
+    pub fn fill_drawdowns(curve: &mut [EquityPoint]) {
+        let mut peak = f64::MIN;
+        for point in curve {
+            peak = peak.max(point.equity);
+            point.drawdown = if peak > 0.0 {
+                (peak - point.equity) / peak
+            } else {
+                0.0
+            };
+        }
+    }
+
+    /// Replace the stored equity curve of a backtest, as part of the
+    /// transaction saving its results.
+    pub async fn save_curve(
+        backtest_id: &str,
+        curve: &[EquityPoint],
+        conn: &mut SqliteConnection,
+    ) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM backtest_equity WHERE backtest_id = ?",
+            backtest_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        for (chunk_index, chunk) in curve.chunks(EQUITY_INSERT_CHUNK).enumerate() {
+            let offset = chunk_index * EQUITY_INSERT_CHUNK;
+            let mut insert = sqlx::QueryBuilder::<Sqlite>::new(
+                "INSERT INTO backtest_equity \
+                 (backtest_id, seq, timestamp, equity, drawdown, exposure, benchmark_equity) ",
+            );
+            insert.push_values(chunk.iter().enumerate(), |mut row, (i, point)| {
+                row.push_bind(backtest_id)
+                    .push_bind((offset + i) as i64)
+                    .push_bind(point.timestamp)
+                    .push_bind(point.equity)
+                    .push_bind(point.drawdown)
+                    .push_bind(point.exposure)
+                    .push_bind(point.benchmark_equity);
+            });
+            insert
+                .build()
+                .execute(&mut *conn)
+                .await
+                .map_err(AppError::Database)?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn find_by_backtest(
+        backtest_id: &str,
+        pool: &Pool<Sqlite>,
+    ) -> Result<Vec<EquityPoint>> {
+        let curve = sqlx::query_as::<_, EquityPoint>(
+            "SELECT timestamp, equity, drawdown, exposure, benchmark_equity FROM backtest_equity \
+             WHERE backtest_id = ? ORDER BY seq ASC",
+        )
+        .bind(backtest_id)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(curve)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -61,49 +308,318 @@ pub struct BacktestTrade {
     pub exit_time: Option<DateTime<Utc>>,
     pub pnl: Option<f64>,
     pub percentage_return: Option<f64>,
+    pub exit_reason: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateBacktestDto {
     pub strategy_id: String,
-    pub symbol: String,
+    /// Single symbol to trade (ignored when `symbols` is given).
+    pub symbol: Option<String>,
+    /// Symbols traded together against one cash balance. When neither this
+    /// nor `symbol` is given, the strategy's own `symbols` are used.
+    pub symbols: Option<Vec<String>>,
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
     pub initial_balance: f64,
     // T11 additions
     pub commission_rate: Option<f64>,
+    /// Replaces the flat `commission_rate` with a schedule plus fees.
+    pub commission_model: Option<CommissionModel>,
     pub slippage_bps: Option<f64>,
+    /// Adds size-dependent impact and a cap on participation in bar volume;
+    /// orders above the cap fill over several bars.
+    pub slippage_model: Option<SlippageModel>,
+    pub allow_short: Option<bool>,
+    pub borrow_fee_rate: Option<f64>,
+    pub short_margin_rate: Option<f64>,
+    /// Borrowing for longs, margin calls, interest on cash and perpetual
+    /// funding. Omitted, the run uses a cash account.
+    pub margin_model: Option<MarginModel>,
+    /// Name of a built-in or loaded trading calendar, for session rules and
+    /// annualisation. Omitted, each symbol follows its asset type's.
+    pub calendar: Option<String>,
+    /// `vectorized` simulates column-wise for fast research runs, and
+    /// rejects strategies and models it cannot express: it needs
+    /// `fixed_quantity` sizing, the default when a strategy has none.
+    /// Defaults to `event`.
+    pub engine: Option<BacktestEngine>,
+    /// Defaults to `next_bar_open`, which avoids trading on the close the
+    /// signal was computed from.
+    pub fill_model: Option<FillModel>,
+    /// Parameters merged over the strategy's for this run only, leaving
+    /// the strategy itself untouched.
+    pub parameters: Option<serde_json::Value>,
+    /// Bars from here until `start_time` warm up indicators without trading.
+    pub warmup_start_time: Option<DateTime<Utc>>,
+    /// Symbol to compare against by buying and holding it. Defaults to the
+    /// (first) traded symbol.
+    pub benchmark_symbol: Option<String>,
+}
+
+impl CreateBacktestDto {
+    /// Symbols to backtest, de-duplicated in request order.
+    pub fn resolve_symbols(&self, strategy_symbols: &[String]) -> Result<Vec<String>> {
+        let requested = match (&self.symbols, &self.symbol) {
+            (Some(symbols), _) if !symbols.is_empty() => symbols.clone(),
+            (_, Some(symbol)) if !symbol.trim().is_empty() => vec![symbol.clone()],
+            _ => strategy_symbols.to_vec(),
+        };
+
+        let mut symbols: Vec<String> = Vec::new();
+        for symbol in requested {
+            let symbol = symbol.trim().to_string();
+            if symbol.is_empty() {
+                return Err(AppError::BadRequest("Symbols cannot be empty".to_string()));
+            }
+            if !symbols.contains(&symbol) {
+                symbols.push(symbol);
+            }
+        }
+
+        if symbols.is_empty() {
+            return Err(AppError::BadRequest(
+                "Backtest requires a symbol, and the strategy has none configured".to_string(),
+            ));
+        }
+        Ok(symbols)
+    }
+}
+
+/// `base` with each top-level key of `overrides` replaced.
+pub fn merge_parameters(base: &str, overrides: &serde_json::Value) -> Result<serde_json::Value> {
+    let mut merged: serde_json::Value = serde_json::from_str(base)
+        .map_err(|e| AppError::BadRequest(format!("Invalid strategy parameters: {}", e)))?;
+    let (Some(merged_map), Some(overrides)) = (merged.as_object_mut(), overrides.as_object())
+    else {
+        return Err(AppError::BadRequest(
+            "Parameter overrides must be a JSON object".to_string(),
+        ));
+    };
+    for (key, value) in overrides {
+        merged_map.insert(key.clone(), value.clone());
+    }
+    Ok(merged)
 }
 
 impl Backtest {
     pub async fn create(dto: CreateBacktestDto, pool: &Pool<Sqlite>) -> Result<Backtest> {
+        let strategy = Strategy::find_by_id(&dto.strategy_id, pool).await?;
+        let mut conn = pool.acquire().await.map_err(AppError::Database)?;
+        Self::create_in_sweep(dto, &strategy, None, &mut conn).await
+    }
+
+    /// Create a backtest of `strategy`, optionally as one run of a parameter
+    /// sweep. Runs on `conn` so a sweep can add every run in one transaction.
+    pub async fn create_in_sweep(
+        dto: CreateBacktestDto,
+        strategy: &Strategy,
+        sweep_id: Option<&str>,
+        conn: &mut SqliteConnection,
+    ) -> Result<Backtest> {
+        let strategy_symbols: Vec<String> =
+            serde_json::from_str(&strategy.symbols).unwrap_or_default();
+        let symbol_list = dto.resolve_symbols(&strategy_symbols)?;
+        let symbol = symbol_list[0].clone();
+        let symbols = serde_json::to_string(&symbol_list)
+            .map_err(|e| AppError::BadRequest(format!("Invalid symbols: {}", e)))?;
+
         let id = Uuid::new_v4().to_string();
         let now = Utc::now();
         let status = BacktestStatus::Pending.to_string();
         let commission_rate = dto.commission_rate.unwrap_or(0.001);
+        let commission_model = match &dto.commission_model {
+            Some(model) => {
+                model.validate().map_err(AppError::BadRequest)?;
+                Some(serde_json::to_string(model).map_err(|e| {
+                    AppError::BadRequest(format!("Invalid commission model: {}", e))
+                })?)
+            }
+            None => None,
+        };
         let slippage_bps = dto.slippage_bps.unwrap_or(10.0);
+        let slippage_model =
+            match &dto.slippage_model {
+                Some(model) => {
+                    model.validate().map_err(AppError::BadRequest)?;
+                    Some(serde_json::to_string(model).map_err(|e| {
+                        AppError::BadRequest(format!("Invalid slippage model: {}", e))
+                    })?)
+                }
+                None => None,
+            };
+        let margin_model =
+            match &dto.margin_model {
+                Some(model) => {
+                    model.validate().map_err(AppError::BadRequest)?;
+                    Some(serde_json::to_string(model).map_err(|e| {
+                        AppError::BadRequest(format!("Invalid margin model: {}", e))
+                    })?)
+                }
+                None => None,
+            };
+        let calendar = match dto.calendar.as_deref() {
+            Some(name) => Some(
+                calendar::by_name(name)
+                    .ok_or_else(|| {
+                        AppError::BadRequest(format!("Unknown trading calendar '{}'", name))
+                    })?
+                    .name,
+            ),
+            None => None,
+        };
+        let allow_short = dto.allow_short.unwrap_or(false);
+        let borrow_fee_rate = dto.borrow_fee_rate.unwrap_or(0.0);
+        let short_margin_rate = dto.short_margin_rate.unwrap_or(0.5);
+        let fill_model = dto.fill_model.unwrap_or_default().to_string();
+        let benchmark_symbol = match dto.benchmark_symbol.as_deref().map(str::trim) {
+            Some("") => {
+                return Err(AppError::BadRequest(
+                    "'benchmark_symbol' cannot be empty".to_string(),
+                ));
+            }
+            Some(benchmark) => benchmark.to_string(),
+            None => symbol.clone(),
+        };
+        if dto
+            .warmup_start_time
+            .is_some_and(|warmup| warmup > dto.start_time)
+        {
+            return Err(AppError::BadRequest(
+                "'warmup_start_time' must not be after 'start_time'".to_string(),
+            ));
+        }
+
+        let parameters = match &dto.parameters {
+            Some(overrides) => {
+                let merged = merge_parameters(&strategy.parameters, overrides)?;
+                let strategy_type = strategy
+                    .strategy_type
+                    .parse::<StrategyType>()
+                    .map_err(AppError::BadRequest)?;
+                validate_parameters(&strategy_type, &merged).map_err(AppError::BadRequest)?;
+                Some(merged.to_string())
+            }
+            None => None,
+        };
+        if borrow_fee_rate < 0.0 || short_margin_rate < 0.0 {
+            return Err(AppError::BadRequest(
+                "'borrow_fee_rate' and 'short_margin_rate' cannot be negative".to_string(),
+            ));
+        }
+
+        let built = StrategyRegistry::global()
+            .build(
+                &strategy.strategy_type,
+                parameters.as_deref().unwrap_or(&strategy.parameters),
+            )
+            .map_err(AppError::BadRequest)?;
+        if built.needs_short && !allow_short {
+            return Err(AppError::BadRequest(format!(
+                "'{}' strategies sell short; set 'allow_short' to backtest them",
+                built.kind
+            )));
+        }
+        let engine = dto.engine.unwrap_or_default();
+        if engine == BacktestEngine::Vectorized {
+            VectorizedStrategy::new(
+                &built,
+                symbol_list.len(),
+                &dto.slippage_model.clone().unwrap_or_default(),
+                &dto.margin_model.clone().unwrap_or_default(),
+            )
+            .map_err(AppError::BadRequest)?;
+        }
+        let engine = engine.to_string();
 
         sqlx::query!(
             r#"
-            INSERT INTO backtests (id, strategy_id, symbol, start_time, end_time, initial_balance, status, created_at, commission_rate, slippage_bps)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO backtests (id, strategy_id, symbol, symbols, start_time, end_time, initial_balance, status, created_at, commission_rate, slippage_bps, allow_short, borrow_fee_rate, short_margin_rate, fill_model, parameters, sweep_id, warmup_start_time, benchmark_symbol, commission_model, slippage_model, margin_model, calendar, engine)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             id,
             dto.strategy_id,
-            dto.symbol,
+            symbol,
+            symbols,
             dto.start_time,
             dto.end_time,
             dto.initial_balance,
             status,
             now,
             commission_rate,
-            slippage_bps
+            slippage_bps,
+            allow_short,
+            borrow_fee_rate,
+            short_margin_rate,
+            fill_model,
+            parameters,
+            sweep_id,
+            dto.warmup_start_time,
+            benchmark_symbol,
+            commission_model,
+            slippage_model,
+            margin_model,
+            calendar,
+            engine
         )
-        .execute(pool)
+        .execute(&mut *conn)
         .await
         .map_err(AppError::Database)?;
 
-        Self::find_by_id(&id, pool).await
+        let backtest = sqlx::query_as::<_, Backtest>("SELECT * FROM backtests WHERE id = ?")
+            .bind(&id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(AppError::Database)?;
+
+        Ok(backtest)
+    }
+
+    /// Parsed `symbols`, falling back to `symbol` for rows that predate it.
+    pub fn symbol_list(&self) -> Vec<String> {
+        let symbols: Vec<String> = serde_json::from_str(&self.symbols).unwrap_or_default();
+        if symbols.is_empty() {
+            vec![self.symbol.clone()]
+        } else {
+            symbols
+        }
+    }
+
+    /// Parsed `fill_model`; unrecognised values fall back to the close fill
+    /// every backtest used before the model was selectable.
+    pub fn fill_model(&self) -> FillModel {
+        self.fill_model.parse().unwrap_or(FillModel::SameBarClose)
+    }
+
+    /// Parsed `commission_model`, or the flat `commission_rate` when none was
+    /// chosen.
+    pub fn commission_model(&self) -> CommissionModel {
+        self.commission_model
+            .as_deref()
+            .and_then(|model| serde_json::from_str(model).ok())
+            .unwrap_or_else(|| CommissionModel::percentage(self.commission_rate))
+    }
+
+    /// Parsed `slippage_model`, or fixed slippage without a participation
+    /// cap when none was chosen.
+    pub fn slippage_model(&self) -> SlippageModel {
+        self.slippage_model
+            .as_deref()
+            .and_then(|model| serde_json::from_str(model).ok())
+            .unwrap_or_default()
+    }
+
+    /// Parsed `engine`; unrecognised values fall back to the event loop.
+    pub fn engine(&self) -> BacktestEngine {
+        self.engine.parse().unwrap_or_default()
+    }
+
+    /// Parsed `margin_model`, or a cash account when none was chosen.
+    pub fn margin_model(&self) -> MarginModel {
+        self.margin_model
+            .as_deref()
+            .and_then(|model| serde_json::from_str(model).ok())
+            .unwrap_or_default()
     }
 
     pub async fn find_by_id(id: &str, pool: &Pool<Sqlite>) -> Result<Backtest> {
@@ -127,6 +643,18 @@ impl Backtest {
         Ok(backtests)
     }
 
+    pub async fn find_by_sweep(sweep_id: &str, pool: &Pool<Sqlite>) -> Result<Vec<Backtest>> {
+        let backtests = sqlx::query_as::<_, Backtest>(
+            "SELECT * FROM backtests WHERE sweep_id = ? ORDER BY created_at ASC",
+        )
+        .bind(sweep_id)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(backtests)
+    }
+
     pub async fn update_status(
         id: &str,
         status: BacktestStatus,
@@ -134,8 +662,9 @@ impl Backtest {
         pool: &Pool<Sqlite>,
     ) -> Result<()> {
         let status_str = status.to_string();
+        // A cancelled run stays cancelled, whatever its worker reports
         sqlx::query!(
-            "UPDATE backtests SET status = ?, error_message = ? WHERE id = ?",
+            "UPDATE backtests SET status = ?, error_message = ? WHERE id = ? AND status != 'cancelled'",
             status_str,
             error_message,
             id
@@ -147,8 +676,250 @@ impl Backtest {
         Ok(())
     }
 
+    /// Mark the backtest running with no progress yet and take a new lease
+    /// on it. Returns `None` if it was cancelled before a worker picked it up.
+    pub async fn start(id: &str, pool: &Pool<Sqlite>) -> Result<Option<String>> {
+        let lease_id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE backtests
+            SET status = 'running', error_message = NULL, bars_processed = 0, bars_total = NULL,
+                lease_id = ?, heartbeat_at = ?
+            WHERE id = ? AND status != 'cancelled'
+            "#,
+            lease_id,
+            now,
+            id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok((result.rows_affected() > 0).then_some(lease_id))
+    }
+
+    /// Record how many of the run's bars have been simulated, renewing the
+    /// lease. Returns `false` once the backtest has been cancelled or handed
+    /// to another run, telling the worker to stop.
+    pub async fn record_progress(
+        id: &str,
+        lease_id: &str,
+        bars_processed: i64,
+        bars_total: i64,
+        pool: &Pool<Sqlite>,
+    ) -> Result<bool> {
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE backtests
+            SET bars_processed = ?, bars_total = ?, heartbeat_at = ?
+            WHERE id = ? AND lease_id = ? AND status = 'running'
+            "#,
+            bars_processed,
+            bars_total,
+            now,
+            id,
+            lease_id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Renew the lease of a running backtest without reporting progress.
+    /// Returns `false` once it has been cancelled or its lease revoked.
+    pub async fn heartbeat(id: &str, lease_id: &str, pool: &Pool<Sqlite>) -> Result<bool> {
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE backtests
+            SET heartbeat_at = ?
+            WHERE id = ? AND lease_id = ? AND status = 'running'
+            "#,
+            now,
+            id,
+            lease_id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Fail a running backtest with `error_message`, as long as it is still
+    /// held under `lease_id`. Returns `false` if it was cancelled or handed
+    /// to another run first, which is then left as it is.
+    pub async fn fail(
+        id: &str,
+        lease_id: &str,
+        error_message: &str,
+        pool: &Pool<Sqlite>,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE backtests
+            SET status = 'failed', error_message = ?
+            WHERE id = ? AND lease_id = ? AND status = 'running'
+            "#,
+            error_message,
+            id,
+            lease_id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Backtests that have not finished: queued, or running under some lease.
+    pub async fn find_unfinished(pool: &Pool<Sqlite>) -> Result<Vec<Backtest>> {
+        let backtests = sqlx::query_as::<_, Backtest>(
+            "SELECT * FROM backtests WHERE status IN ('pending', 'running') ORDER BY created_at ASC",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(backtests)
+    }
+
+    /// Running backtests whose last heartbeat is older than `cutoff`.
+    pub async fn find_stale(cutoff: DateTime<Utc>, pool: &Pool<Sqlite>) -> Result<Vec<Backtest>> {
+        let backtests = sqlx::query_as::<_, Backtest>(
+            r#"
+            SELECT * FROM backtests
+            WHERE status = 'running' AND (heartbeat_at IS NULL OR heartbeat_at < ?)
+            ORDER BY created_at ASC
+            "#,
+        )
+        .bind(cutoff)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(backtests)
+    }
+
+    /// Fail an unfinished backtest whose worker has not heartbeated since
+    /// `cutoff`. Returns `false` if it finished, was cancelled or heartbeated
+    /// in the meantime, which is then left as it is.
+    pub async fn fail_stale(
+        id: &str,
+        cutoff: DateTime<Utc>,
+        error_message: &str,
+        pool: &Pool<Sqlite>,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE backtests
+            SET status = 'failed', error_message = ?
+            WHERE id = ? AND status IN ('pending', 'running')
+                AND (heartbeat_at IS NULL OR heartbeat_at < ?)
+            "#,
+            error_message,
+            id,
+            cutoff
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Put an unfinished backtest back in the queue, revoking any lease on
+    /// it and clearing trades an earlier attempt left behind. Returns `false`
+    /// if it finished or was cancelled in the meantime.
+    pub async fn requeue(id: &str, pool: &Pool<Sqlite>) -> Result<bool> {
+        let mut tx = pool.begin().await.map_err(AppError::Database)?;
+        let result = sqlx::query!(
+            r#"
+            UPDATE backtests
+            SET status = 'pending', lease_id = NULL, heartbeat_at = NULL
+            WHERE id = ? AND status IN ('pending', 'running')
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query!("DELETE FROM backtest_trades WHERE backtest_id = ?", id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::Database)?;
+        tx.commit().await.map_err(AppError::Database)?;
+        Ok(true)
+    }
+
+    /// Cancel a pending or running backtest. Its worker stops at the next
+    /// progress update and leaves the results unrecorded.
+    pub async fn cancel(id: &str, pool: &Pool<Sqlite>) -> Result<Backtest> {
+        let backtest = Self::find_by_id(id, pool).await?;
+        let result = sqlx::query!(
+            "UPDATE backtests SET status = 'cancelled' WHERE id = ? AND status IN ('pending', 'running')",
+            id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::BadRequest(format!(
+                "Backtest {} is already {}",
+                id, backtest.status
+            )));
+        }
+        Self::find_by_id(id, pool).await
+    }
+
+    /// Record how the run compared with its benchmark.
+    pub async fn update_benchmark(
+        id: &str,
+        comparison: &BenchmarkComparison,
+        conn: &mut SqliteConnection,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE backtests
+            SET benchmark_return = ?,
+                alpha = ?,
+                beta = ?,
+                information_ratio = ?,
+                tracking_error = ?,
+                excess_return = ?
+            WHERE id = ?
+            "#,
+            comparison.benchmark_return,
+            comparison.alpha,
+            comparison.beta,
+            comparison.information_ratio,
+            comparison.tracking_error,
+            comparison.excess_return,
+            id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    /// Record a finished run's metrics and mark it completed. Returns
+    /// `false`, changing nothing, once the run has been cancelled or its
+    /// lease handed to another worker.
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_results(
         id: &str,
+        lease_id: &str,
         final_balance: f64,
         total_return: f64,
         sharpe_ratio: f64,
@@ -157,9 +928,10 @@ impl Backtest {
         trade_count: i64,
         win_rate: f64,
         profit_factor: f64,
-        pool: &Pool<Sqlite>,
-    ) -> Result<()> {
-        sqlx::query!(
+        tearsheet: &Tearsheet,
+        conn: &mut SqliteConnection,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
             r#"
             UPDATE backtests
             SET final_balance = ?,
@@ -170,8 +942,24 @@ impl Backtest {
                 trade_count = ?,
                 win_rate = ?,
                 profit_factor = ?,
+                cagr = ?,
+                annualized_volatility = ?,
+                annualized_sharpe_ratio = ?,
+                sortino_ratio = ?,
+                calmar_ratio = ?,
+                max_drawdown_duration_secs = ?,
+                recovery_time_secs = ?,
+                exposure_time = ?,
+                turnover = ?,
+                average_win = ?,
+                average_loss = ?,
+                expectancy = ?,
+                largest_win = ?,
+                largest_loss = ?,
+                max_consecutive_losses = ?,
+                bars_processed = COALESCE(bars_total, bars_processed),
                 status = 'completed'
-            WHERE id = ?
+            WHERE id = ? AND lease_id = ? AND status = 'running'
             "#,
             final_balance,
             total_return,
@@ -181,60 +969,127 @@ impl Backtest {
             trade_count,
             win_rate,
             profit_factor,
-            id
+            tearsheet.cagr,
+            tearsheet.annualized_volatility,
+            tearsheet.annualized_sharpe_ratio,
+            tearsheet.sortino_ratio,
+            tearsheet.calmar_ratio,
+            tearsheet.max_drawdown_duration_secs,
+            tearsheet.recovery_time_secs,
+            tearsheet.exposure_time,
+            tearsheet.turnover,
+            tearsheet.trades.average_win,
+            tearsheet.trades.average_loss,
+            tearsheet.trades.expectancy,
+            tearsheet.trades.largest_win,
+            tearsheet.trades.largest_loss,
+            tearsheet.trades.max_consecutive_losses,
+            id,
+            lease_id
         )
-        .execute(pool)
+        .execute(&mut *conn)
         .await
         .map_err(AppError::Database)?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 }
 
+/// Rows per INSERT when saving trades in bulk, well under SQLite's bind limit.
+const TRADE_INSERT_CHUNK: usize = 1000;
+
 impl BacktestTrade {
-    pub async fn create(
+    /// A trade entered at `entry_price`, held in memory until the run saves it.
+    pub fn open(
         backtest_id: &str,
         symbol: &str,
         side: &str,
         quantity: f64,
         entry_price: f64,
         entry_time: DateTime<Utc>,
-        pool: &Pool<Sqlite>,
-    ) -> Result<BacktestTrade> {
-        let id = Uuid::new_v4().to_string();
-
-        sqlx::query!(
-            r#"
-            INSERT INTO backtest_trades (id, backtest_id, symbol, side, quantity, entry_price, entry_time)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
-            "#,
-            id,
-            backtest_id,
-            symbol,
-            side,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            backtest_id: backtest_id.to_string(),
+            symbol: symbol.to_string(),
+            side: side.to_string(),
             quantity,
             entry_price,
-            entry_time
+            exit_price: None,
+            entry_time,
+            exit_time: None,
+            pnl: None,
+            percentage_return: None,
+            exit_reason: None,
+        }
+    }
+
+    /// Close the trade at `exit_price`, computing PnL for its side.
+    /// `costs` (e.g. commission and borrow fees) are charged against the PnL.
+    pub fn close(
+        &mut self,
+        exit_price: f64,
+        exit_time: DateTime<Utc>,
+        costs: f64,
+        exit_reason: ExitReason,
+    ) {
+        let (pnl, percentage_return) = trade_return(
+            &self.side,
+            self.entry_price,
+            exit_price,
+            self.quantity,
+            costs,
+        );
+        self.exit_price = Some(exit_price);
+        self.exit_time = Some(exit_time);
+        self.pnl = Some(pnl);
+        self.percentage_return = Some(percentage_return);
+        self.exit_reason = Some(exit_reason.to_string());
+    }
+
+    /// Replace the stored trades of a backtest, as part of the transaction
+    /// saving its results.
+    pub async fn save_trades(
+        backtest_id: &str,
+        trades: &[BacktestTrade],
+        conn: &mut SqliteConnection,
+    ) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM backtest_trades WHERE backtest_id = ?",
+            backtest_id
         )
-        .execute(pool)
+        .execute(&mut *conn)
         .await
         .map_err(AppError::Database)?;
 
-        Self::find_by_id(&id, pool).await
-    }
-
-    pub async fn find_by_id(id: &str, pool: &Pool<Sqlite>) -> Result<BacktestTrade> {
-        let trade =
-            sqlx::query_as::<_, BacktestTrade>("SELECT * FROM backtest_trades WHERE id = ?")
-                .bind(id)
-                .fetch_optional(pool)
+        for chunk in trades.chunks(TRADE_INSERT_CHUNK) {
+            let mut insert = sqlx::QueryBuilder::<Sqlite>::new(
+                "INSERT INTO backtest_trades \
+                 (id, backtest_id, symbol, side, quantity, entry_price, exit_price, entry_time, \
+                 exit_time, pnl, percentage_return, exit_reason) ",
+            );
+            insert.push_values(chunk, |mut row, trade| {
+                row.push_bind(&trade.id)
+                    .push_bind(&trade.backtest_id)
+                    .push_bind(&trade.symbol)
+                    .push_bind(&trade.side)
+                    .push_bind(trade.quantity)
+                    .push_bind(trade.entry_price)
+                    .push_bind(trade.exit_price)
+                    .push_bind(trade.entry_time)
+                    .push_bind(trade.exit_time)
+                    .push_bind(trade.pnl)
+                    .push_bind(trade.percentage_return)
+                    .push_bind(&trade.exit_reason);
+            });
+            insert
+                .build()
+                .execute(&mut *conn)
                 .await
-                .map_err(AppError::Database)?
-                .ok_or_else(|| {
-                    AppError::NotFound(format!("Backtest trade with ID {} not found", id))
-                })?;
+                .map_err(AppError::Database)?;
+        }
 
-        Ok(trade)
+        Ok(())
     }
 
     pub async fn find_by_backtest(
@@ -251,31 +1106,56 @@ impl BacktestTrade {
 
         Ok(trades)
     }
+}
 
-    pub async fn close_trade(
-        id: &str,
-        exit_price: f64,
-        exit_time: DateTime<Utc>,
-        pnl: f64,
-        percentage_return: f64,
-        pool: &Pool<Sqlite>,
-    ) -> Result<()> {
-        sqlx::query!(
-            r#"
-            UPDATE backtest_trades
-            SET exit_price = ?, exit_time = ?, pnl = ?, percentage_return = ?
-            WHERE id = ?
-            "#,
-            exit_price,
-            exit_time,
-            pnl,
-            percentage_return,
-            id
-        )
-        .execute(pool)
-        .await
-        .map_err(AppError::Database)?;
+/// `(pnl, percentage_return)` of a round trip. A `"sell"` trade is a short,
+/// which profits when the exit is below the entry. The return is relative to
+/// the entry notional.
+pub fn trade_return(
+    side: &str,
+    entry_price: f64,
+    exit_price: f64,
+    quantity: f64,
+    costs: f64,
+) -> (f64, f64) {
+    let direction = if side == "sell" { -1.0 } else { 1.0 };
+    let pnl = direction * (exit_price - entry_price) * quantity - costs;
+    let notional = entry_price * quantity;
+    let percentage_return = if notional > 0.0 { pnl / notional } else { 0.0 };
+    (pnl, percentage_return)
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_trade_return() {
+        let (pnl, pct) = trade_return("buy", 100.0, 110.0, 2.0, 0.0);
+        assert!((pnl - 20.0).abs() < 1e-9);
+        assert!((pct - 0.10).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_short_trade_return_includes_borrow_costs() {
+        let (pnl, pct) = trade_return("sell", 100.0, 90.0, 2.0, 1.0);
+        assert!((pnl - 19.0).abs() < 1e-9);
+        assert!((pct - 0.095).abs() < 1e-9);
+
+        let (pnl, _) = trade_return("sell", 100.0, 110.0, 1.0, 0.0);
+        assert!((pnl + 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_drawdowns_follow_the_running_peak() {
+        let t = Utc::now();
+        let mut curve: Vec<EquityPoint> = [100.0, 120.0, 90.0, 130.0]
+            .iter()
+            .map(|&equity| EquityPoint::new(t, equity, 0.0))
+            .collect();
+        EquityPoint::fill_drawdowns(&mut curve);
+
+        let drawdowns: Vec<f64> = curve.iter().map(|p| p.drawdown).collect();
+        assert_eq!(drawdowns, vec![0.0, 0.0, 0.25, 0.0]);
     }
 }
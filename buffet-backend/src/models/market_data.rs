@@ -1,3 +1,4 @@
+use crate::calendar::{self, TradingCalendar};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -32,6 +33,108 @@ impl OHLCV {
     }
 }
 
+// Let `ta` indicators that need the full bar (e.g. ATR) consume candles directly
+impl ta::Open for OHLCV {
+    fn open(&self) -> f64 {
+        self.open
+    }
+}
+
+impl ta::High for OHLCV {
+    fn high(&self) -> f64 {
+        self.high
+    }
+}
+
+impl ta::Low for OHLCV {
+    fn low(&self) -> f64 {
+        self.low
+    }
+}
+
+impl ta::Close for OHLCV {
+    fn close(&self) -> f64 {
+        self.close
+    }
+}
+
+impl ta::Volume for OHLCV {
+    fn volume(&self) -> f64 {
+        self.volume
+    }
+}
+
+/// What a corporate action does to a holding.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CorporateActionKind {
+    /// `value` new shares for every share held, e.g. 4.0 for a 4:1 split
+    /// or 0.1 for a 1:10 reverse split.
+    Split,
+    /// `value` in cash per share held at the ex-date.
+    Dividend,
+}
+
+impl std::fmt::Display for CorporateActionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CorporateActionKind::Split => write!(f, "split"),
+            CorporateActionKind::Dividend => write!(f, "dividend"),
+        }
+    }
+}
+
+impl std::str::FromStr for CorporateActionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "split" => Ok(CorporateActionKind::Split),
+            "dividend" => Ok(CorporateActionKind::Dividend),
+            other => Err(format!("Unknown corporate action '{}'", other)),
+        }
+    }
+}
+
+/// A split or dividend taking effect at its ex-date `timestamp`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CorporateAction {
+    pub timestamp: DateTime<Utc>,
+    pub kind: CorporateActionKind,
+    pub value: f64,
+}
+
+impl CorporateAction {
+    pub fn split(timestamp: DateTime<Utc>, ratio: f64) -> Self {
+        Self {
+            timestamp,
+            kind: CorporateActionKind::Split,
+            value: ratio,
+        }
+    }
+
+    pub fn dividend(timestamp: DateTime<Utc>, amount: f64) -> Self {
+        Self {
+            timestamp,
+            kind: CorporateActionKind::Dividend,
+            value: amount,
+        }
+    }
+}
+
+/// Which corporate actions a price series is adjusted for.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceAdjustment {
+    /// Prices as quoted at the time.
+    #[default]
+    Raw,
+    /// Earlier bars restated in today's shares, so splits leave no gaps.
+    Splits,
+    /// Splits plus dividends, as if every dividend were reinvested.
+    Total,
+}
+
 /// Ticker information
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Ticker {
@@ -64,6 +167,15 @@ impl Ticker {
             asset_type: AssetType::Crypto,
         }
     }
+
+    /// The calendar of the ticker's exchange when one is registered under
+    /// its name, else its asset type's.
+    pub fn calendar(&self) -> TradingCalendar {
+        self.exchange
+            .as_deref()
+            .and_then(calendar::by_name)
+            .unwrap_or_else(|| self.asset_type.calendar())
+    }
 }
 
 /// Asset type classification
@@ -87,3 +199,31 @@ impl std::fmt::Display for AssetType {
         }
     }
 }
+
+impl std::str::FromStr for AssetType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stock" => Ok(AssetType::Stock),
+            "crypto" => Ok(AssetType::Crypto),
+            "forex" => Ok(AssetType::Forex),
+            "commodity" => Ok(AssetType::Commodity),
+            "index" => Ok(AssetType::Index),
+            other => Err(format!("Unknown asset type '{}'", other)),
+        }
+    }
+}
+
+impl AssetType {
+    /// The calendar assets of this type trade on unless their exchange has
+    /// its own: NYSE hours for stocks and indices, around the clock for
+    /// crypto, and weekdays for forex and commodities.
+    pub fn calendar(&self) -> TradingCalendar {
+        match self {
+            AssetType::Stock | AssetType::Index => TradingCalendar::nyse(),
+            AssetType::Crypto => TradingCalendar::crypto(),
+            AssetType::Forex | AssetType::Commodity => TradingCalendar::forex(),
+        }
+    }
+}
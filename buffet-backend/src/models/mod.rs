@@ -1,13 +1,19 @@
 pub mod backtest;
 pub mod market_data;
+pub mod monte_carlo;
 pub mod order;
 pub mod position;
 pub mod signal;
 pub mod strategy;
+pub mod sweep;
+pub mod walk_forward;
 
 pub use backtest::*;
 pub use market_data::*;
+pub use monte_carlo::*;
 pub use order::*;
 pub use position::*;
 pub use signal::*;
 pub use strategy::*;
+pub use sweep::*;
+pub use walk_forward::*;
@@ -0,0 +1,144 @@
+use crate::error::{AppError, Result};
+use crate::models::backtest::{Backtest, BacktestStatus, BacktestTrade, EquityPoint};
+use crate::utils::metrics::{bar_returns, calculate_sharpe_ratio};
+use crate::utils::monte_carlo::{
+    MonteCarloMethod, MonteCarloSummary, TradeSample, simulate_bar_blocks, simulate_trades,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+
+/// Upper bound on the paths of one Monte Carlo run.
+pub const MAX_SIMULATIONS: usize = 100_000;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MonteCarloDto {
+    pub method: Option<MonteCarloMethod>,
+    /// Number of simulated paths (default 1000).
+    pub simulations: Option<usize>,
+    /// Bars per block for `block_bootstrap` (default √ of the bar count).
+    pub block_size: Option<usize>,
+    /// Seed for reproducible results; a random one is picked and reported
+    /// when omitted.
+    pub seed: Option<u64>,
+}
+
+/// The distributions next to the backtest's own, single-path figures.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonteCarloReport {
+    pub backtest_id: String,
+    pub method: MonteCarloMethod,
+    pub simulations: usize,
+    pub block_size: Option<usize>,
+    pub seed: u64,
+    pub observed_final_equity: Option<f64>,
+    pub observed_max_drawdown: Option<f64>,
+    /// On the same basis as the simulated `sharpe_ratio` bands: per trade
+    /// for the trade methods, per bar of the stored curve for the bootstrap.
+    pub observed_sharpe_ratio: Option<f64>,
+    #[serde(flatten)]
+    pub summary: MonteCarloSummary,
+}
+
+impl MonteCarloReport {
+    /// Simulate paths from a completed backtest's trades or equity curve.
+    pub async fn run(
+        backtest_id: &str,
+        dto: MonteCarloDto,
+        pool: &Pool<Sqlite>,
+    ) -> Result<MonteCarloReport> {
+        let backtest = Backtest::find_by_id(backtest_id, pool).await?;
+        if backtest.status != BacktestStatus::Completed.to_string() {
+            return Err(AppError::BadRequest(format!(
+                "Backtest {} is {}, not completed",
+                backtest_id, backtest.status
+            )));
+        }
+
+        let method = dto.method.unwrap_or_default();
+        let simulations = dto.simulations.unwrap_or(1000);
+        if simulations == 0 || simulations > MAX_SIMULATIONS {
+            return Err(AppError::BadRequest(format!(
+                "'simulations' must be between 1 and {}",
+                MAX_SIMULATIONS
+            )));
+        }
+        let seed = dto.seed.unwrap_or_else(rand::random);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let initial_balance = backtest.initial_balance;
+
+        let (summary, block_size, observed_sharpe_ratio) = match method {
+            MonteCarloMethod::TradeResample | MonteCarloMethod::TradeShuffle => {
+                let trades: Vec<TradeSample> = BacktestTrade::find_by_backtest(backtest_id, pool)
+                    .await?
+                    .into_iter()
+                    .filter_map(|t| {
+                        Some(TradeSample {
+                            pnl: t.pnl?,
+                            percentage_return: t.percentage_return?,
+                        })
+                    })
+                    .collect();
+                if trades.len() < 2 {
+                    return Err(AppError::BadRequest(
+                        "Monte Carlo over trades needs at least 2 closed trades".to_string(),
+                    ));
+                }
+
+                let shuffle = method == MonteCarloMethod::TradeShuffle;
+                let summary = tokio::task::spawn_blocking(move || {
+                    simulate_trades(&trades, initial_balance, simulations, shuffle, &mut rng)
+                })
+                .await
+                .map_err(|e| AppError::InternalServerError(format!("Simulation failed: {}", e)))?;
+                (summary, None, backtest.sharpe_ratio)
+            }
+            MonteCarloMethod::BlockBootstrap => {
+                let curve: Vec<f64> = EquityPoint::find_by_backtest(backtest_id, pool)
+                    .await?
+                    .iter()
+                    .map(|p| p.equity)
+                    .collect();
+                let returns = bar_returns(&curve);
+                if returns.len() < 2 {
+                    return Err(AppError::BadRequest(
+                        "Block bootstrap needs a stored equity curve of at least 3 bars \
+                         (backtests run before curves were stored have none)"
+                            .to_string(),
+                    ));
+                }
+
+                let observed_sharpe_ratio = calculate_sharpe_ratio(&returns, 0.0);
+                let block_size = dto
+                    .block_size
+                    .unwrap_or_else(|| (returns.len() as f64).sqrt().round() as usize)
+                    .clamp(1, returns.len());
+                let summary = tokio::task::spawn_blocking(move || {
+                    simulate_bar_blocks(
+                        &returns,
+                        initial_balance,
+                        simulations,
+                        block_size,
+                        &mut rng,
+                    )
+                })
+                .await
+                .map_err(|e| AppError::InternalServerError(format!("Simulation failed: {}", e)))?;
+                (summary, Some(block_size), Some(observed_sharpe_ratio))
+            }
+        };
+
+        Ok(MonteCarloReport {
+            backtest_id: backtest.id,
+            method,
+            simulations,
+            block_size,
+            seed,
+            observed_final_equity: backtest.final_balance,
+            observed_max_drawdown: backtest.max_drawdown,
+            observed_sharpe_ratio,
+            summary,
+        })
+    }
+}
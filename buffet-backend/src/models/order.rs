@@ -124,6 +124,30 @@ impl Order {
         Ok(orders)
     }
 
+    /// Net filled quantity (buys minus sells) of `symbol` across orders raised
+    /// by `strategy_id`'s signals.
+    pub async fn net_filled_quantity(
+        strategy_id: &str,
+        symbol: &str,
+        pool: &Pool<Sqlite>,
+    ) -> Result<f64> {
+        let net: Option<f64> = sqlx::query_scalar(
+            r#"
+            SELECT SUM(CASE WHEN o.side = 'buy' THEN o.quantity ELSE -o.quantity END)
+            FROM orders o
+            JOIN signals s ON s.id = o.signal_id
+            WHERE s.strategy_id = ? AND o.symbol = ? AND o.status = 'filled'
+            "#,
+        )
+        .bind(strategy_id)
+        .bind(symbol)
+        .fetch_one(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(net.unwrap_or(0.0))
+    }
+
     pub async fn find_by_id(id: &str, pool: &Pool<Sqlite>) -> Result<Order> {
         let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = ?")
             .bind(id)
@@ -4,6 +4,7 @@ use sqlx::{FromRow, Pool, Sqlite};
 use uuid::Uuid;
 
 use crate::error::{AppError, Result};
+use crate::strategies::StrategyRegistry;
 
 /// Strategy metadata stored in SQLite
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -100,44 +101,15 @@ pub struct UpdateStrategyDto {
 
 /// Validate that the provided parameters are valid for the given strategy type.
 ///
-/// - `Classical`: requires `fast_period` and `slow_period` as positive integers,
-///   with `fast_period < slow_period`.
-/// - `Statistical` / `MLBased`: pass-through (no validation for now).
+/// The concrete strategy is resolved through `StrategyRegistry` from the
+/// `kind` parameter (or the type's default kind), and its own validation runs.
+/// Parameters without a `kind` for a type with no registered strategies are
+/// accepted as-is.
 pub fn validate_parameters(
     strategy_type: &StrategyType,
     params: &serde_json::Value,
 ) -> std::result::Result<(), String> {
-    match strategy_type {
-        StrategyType::Classical => {
-            let fast = params.get("fast_period").ok_or_else(|| {
-                "Classical strategy requires 'fast_period' parameter".to_string()
-            })?;
-            let slow = params.get("slow_period").ok_or_else(|| {
-                "Classical strategy requires 'slow_period' parameter".to_string()
-            })?;
-
-            let fast_val = fast
-                .as_u64()
-                .filter(|&v| v > 0)
-                .ok_or_else(|| "'fast_period' must be a positive integer".to_string())?;
-
-            let slow_val = slow
-                .as_u64()
-                .filter(|&v| v > 0)
-                .ok_or_else(|| "'slow_period' must be a positive integer".to_string())?;
-
-            if fast_val >= slow_val {
-                return Err(format!(
-                    "'fast_period' ({}) must be less than 'slow_period' ({})",
-                    fast_val, slow_val
-                ));
-            }
-
-            Ok(())
-        }
-        // Pass-through for Statistical and MLBased
-        StrategyType::Statistical | StrategyType::MLBased => Ok(()),
-    }
+    StrategyRegistry::global().validate(strategy_type, params)
 }
 
 impl Strategy {
@@ -443,9 +415,60 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_validate_parameters_statistical_passthrough() {
-        let params = serde_json::json!({"anything": "goes"});
+    async fn test_validate_parameters_unknown_kind() {
+        let params = serde_json::json!({"kind": "ichimoku"});
+        let err = validate_parameters(&StrategyType::Classical, &params).unwrap_err();
+        assert!(err.contains("ichimoku"));
+
+        let params = serde_json::json!({"kind": 7});
+        assert!(validate_parameters(&StrategyType::Classical, &params).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_parameters_rsi() {
+        let params = serde_json::json!({"kind": "rsi", "period": 14});
+        assert!(validate_parameters(&StrategyType::Classical, &params).is_ok());
+
+        let params = serde_json::json!({"kind": "rsi", "oversold": 80, "overbought": 20});
+        let err = validate_parameters(&StrategyType::Classical, &params).unwrap_err();
+        assert!(err.contains("less than"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_parameters_bollinger() {
+        let params = serde_json::json!({"kind": "bollinger", "period": 20, "mode": "breakout"});
+        assert!(validate_parameters(&StrategyType::Classical, &params).is_ok());
+
+        let params = serde_json::json!({"kind": "bollinger", "std_dev": -1.0});
+        let err = validate_parameters(&StrategyType::Classical, &params).unwrap_err();
+        assert!(err.contains("std_dev"));
+
+        let params = serde_json::json!({"kind": "bollinger", "mode": "sideways"});
+        assert!(validate_parameters(&StrategyType::Classical, &params).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_parameters_macd() {
+        let params = serde_json::json!({"kind": "macd"});
+        assert!(validate_parameters(&StrategyType::Classical, &params).is_ok());
+
+        let params = serde_json::json!({"kind": "macd", "fast_period": 30, "slow_period": 26});
+        let err = validate_parameters(&StrategyType::Classical, &params).unwrap_err();
+        assert!(err.contains("less than"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_parameters_statistical_pairs() {
+        let params = serde_json::json!({"symbol_a": "KO", "symbol_b": "PEP"});
         assert!(validate_parameters(&StrategyType::Statistical, &params).is_ok());
+
+        let params = serde_json::json!({"symbol_a": "KO"});
+        let err = validate_parameters(&StrategyType::Statistical, &params).unwrap_err();
+        assert!(err.contains("symbol_b"));
+
+        let params =
+            serde_json::json!({"symbol_a": "KO", "symbol_b": "PEP", "entry_z": 0.5, "exit_z": 1.0});
+        assert!(validate_parameters(&StrategyType::Statistical, &params).is_err());
     }
 
     #[tokio::test]
@@ -0,0 +1,428 @@
+use crate::error::{AppError, Result};
+use crate::models::backtest::{Backtest, BacktestStatus, CreateBacktestDto, merge_parameters};
+use crate::models::strategy::Strategy;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, Pool, Sqlite};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Upper bound on the runs one sweep may fan out to.
+pub const MAX_SWEEP_RUNS: usize = 500;
+
+/// Drawdown floor for `ReturnOverDrawdown`, so runs that never drew down
+/// don't score infinitely.
+const MIN_DRAWDOWN: f64 = 0.01;
+
+/// What a sweep ranks its runs by, best first.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepObjective {
+    #[default]
+    Sharpe,
+    TotalReturn,
+    /// Total return divided by max drawdown.
+    ReturnOverDrawdown,
+}
+
+impl std::fmt::Display for SweepObjective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SweepObjective::Sharpe => write!(f, "sharpe"),
+            SweepObjective::TotalReturn => write!(f, "total_return"),
+            SweepObjective::ReturnOverDrawdown => write!(f, "return_over_drawdown"),
+        }
+    }
+}
+
+impl std::str::FromStr for SweepObjective {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "sharpe" => Ok(SweepObjective::Sharpe),
+            "total_return" => Ok(SweepObjective::TotalReturn),
+            "return_over_drawdown" => Ok(SweepObjective::ReturnOverDrawdown),
+            other => Err(format!("Unknown sweep objective '{}'", other)),
+        }
+    }
+}
+
+impl SweepObjective {
+    /// Score of a completed run; `None` while it is pending, running or failed.
+    pub fn score(&self, backtest: &Backtest) -> Option<f64> {
+        if backtest.status != BacktestStatus::Completed.to_string() {
+            return None;
+        }
+        let total_return = backtest.total_return?;
+        match self {
+            SweepObjective::Sharpe => backtest.sharpe_ratio,
+            SweepObjective::TotalReturn => Some(total_return),
+            SweepObjective::ReturnOverDrawdown => {
+                let drawdown = backtest.max_drawdown.unwrap_or(0.0).max(MIN_DRAWDOWN);
+                Some(total_return / drawdown)
+            }
+        }
+    }
+}
+
+/// Values one parameter takes in a sweep: an explicit list, or an
+/// inclusive `start..=end` range walked in `step`s.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParameterValues {
+    List(Vec<Value>),
+    Range { start: f64, end: f64, step: f64 },
+}
+
+impl ParameterValues {
+    /// The concrete values. Ranges whose `start` and `step` are whole
+    /// numbers yield integers, so they suit integer parameters like periods.
+    pub fn values(&self) -> std::result::Result<Vec<Value>, String> {
+        match self {
+            ParameterValues::List(values) => Ok(values.clone()),
+            &ParameterValues::Range { start, end, step } => {
+                let finite = start.is_finite() && end.is_finite() && step.is_finite();
+                if !finite || step <= 0.0 || end < start {
+                    return Err(format!(
+                        "Range {}..={} step {} needs finite bounds, a positive step and end >= start",
+                        start, end, step
+                    ));
+                }
+                let count = ((end - start) / step + 1e-9).floor() as usize + 1;
+                if count > MAX_SWEEP_RUNS {
+                    return Err(format!(
+                        "Range {}..={} step {} has more than {} values",
+                        start, end, step, MAX_SWEEP_RUNS
+                    ));
+                }
+                let integral = start.fract() == 0.0 && step.fract() == 0.0;
+                Ok((0..count)
+                    .map(|i| {
+                        let value = start + step * i as f64;
+                        if integral {
+                            Value::from(value as i64)
+                        } else {
+                            Value::from(value)
+                        }
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Every combination of the grid's values, as parameter override objects.
+pub fn expand_grid(
+    grid: &BTreeMap<String, ParameterValues>,
+) -> std::result::Result<Vec<Value>, String> {
+    if grid.is_empty() {
+        return Err("Sweep grid must name at least one parameter".to_string());
+    }
+
+    let mut combinations = vec![serde_json::Map::new()];
+    for (name, values) in grid {
+        let values = values.values()?;
+        if values.is_empty() {
+            return Err(format!("Sweep parameter '{}' has no values", name));
+        }
+        if combinations.len() * values.len() > MAX_SWEEP_RUNS {
+            return Err(format!(
+                "Sweep grid expands to more than {} runs",
+                MAX_SWEEP_RUNS
+            ));
+        }
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.insert(name.clone(), value.clone());
+                    combination
+                })
+            })
+            .collect();
+    }
+
+    Ok(combinations.into_iter().map(Value::Object).collect())
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct BacktestSweep {
+    pub id: String,
+    pub strategy_id: String,
+    pub grid: String, // JSON object of parameter name → values
+    pub objective: String,
+    pub run_count: i64,
+    /// Grid combinations the strategy rejected as invalid.
+    pub skipped_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSweepDto {
+    /// Shared settings of every run; `parameters`, if given, are the base
+    /// the grid values are layered over.
+    #[serde(flatten)]
+    pub backtest: CreateBacktestDto,
+    /// Parameter name → values to try. Names are top-level strategy parameters.
+    pub grid: BTreeMap<String, ParameterValues>,
+    pub objective: Option<SweepObjective>,
+}
+
+/// One run of a sweep with its score under the sweep's objective.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SweepRun {
+    /// 1-based position among scored runs; `None` until the run completes.
+    pub rank: Option<usize>,
+    pub score: Option<f64>,
+    pub parameters: Value,
+    pub backtest: Backtest,
+}
+
+/// A sweep with its runs ranked best first.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SweepDetail {
+    #[serde(flatten)]
+    pub sweep: BacktestSweep,
+    /// `running` until every run finished, then `completed` (or `failed`
+    /// when no run completed).
+    pub status: String,
+    pub completed_count: usize,
+    pub failed_count: usize,
+    pub cancelled_count: usize,
+    pub runs: Vec<SweepRun>,
+}
+
+impl BacktestSweep {
+    /// Create the sweep and one pending backtest per valid grid combination,
+    /// in one transaction.
+    pub async fn create(
+        dto: CreateSweepDto,
+        pool: &Pool<Sqlite>,
+    ) -> Result<(BacktestSweep, Vec<Backtest>)> {
+        let strategy = Strategy::find_by_id(&dto.backtest.strategy_id, pool).await?;
+        let combinations = expand_grid(&dto.grid).map_err(AppError::BadRequest)?;
+        let base = match &dto.backtest.parameters {
+            Some(overrides) => merge_parameters(&strategy.parameters, overrides)?,
+            None => serde_json::from_str(&strategy.parameters)
+                .map_err(|e| AppError::BadRequest(format!("Invalid strategy parameters: {}", e)))?,
+        };
+
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let grid = serde_json::to_string(&dto.grid)
+            .map_err(|e| AppError::BadRequest(format!("Invalid sweep grid: {}", e)))?;
+        let objective = dto.objective.unwrap_or_default().to_string();
+
+        // All or none of the grid: workers must never rank a partial sweep
+        let mut tx = pool.begin().await.map_err(AppError::Database)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO backtest_sweeps (id, strategy_id, grid, objective, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            id,
+            dto.backtest.strategy_id,
+            grid,
+            objective,
+            now
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+
+        let mut runs = Vec::with_capacity(combinations.len());
+        let mut skipped_count = 0i64;
+        let mut first_rejection = None;
+        for combination in combinations {
+            let mut run_dto = dto.backtest.clone();
+            run_dto.parameters = Some(merge_parameters(&base.to_string(), &combination)?);
+
+            match Backtest::create_in_sweep(run_dto, &strategy, Some(&id), &mut tx).await {
+                Ok(backtest) => runs.push(backtest),
+                // Grids routinely include invalid pairs (e.g. fast >= slow)
+                Err(AppError::BadRequest(reason)) => {
+                    tracing::debug!("Sweep {} skips {}: {}", id, combination, reason);
+                    skipped_count += 1;
+                    first_rejection.get_or_insert(reason);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let run_count = runs.len() as i64;
+
+        if runs.is_empty() {
+            return Err(AppError::BadRequest(format!(
+                "No valid parameter combination in the sweep grid: {}",
+                first_rejection.unwrap_or_default()
+            )));
+        }
+
+        sqlx::query!(
+            "UPDATE backtest_sweeps SET run_count = ?, skipped_count = ? WHERE id = ?",
+            run_count,
+            skipped_count,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::Database)?;
+        tx.commit().await.map_err(AppError::Database)?;
+
+        Ok((Self::find_by_id(&id, pool).await?, runs))
+    }
+
+    pub async fn find_by_id(id: &str, pool: &Pool<Sqlite>) -> Result<BacktestSweep> {
+        let sweep =
+            sqlx::query_as::<_, BacktestSweep>("SELECT * FROM backtest_sweeps WHERE id = ?")
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+                .map_err(AppError::Database)?
+                .ok_or_else(|| AppError::NotFound(format!("Sweep with ID {} not found", id)))?;
+
+        Ok(sweep)
+    }
+
+    pub async fn find_all(pool: &Pool<Sqlite>) -> Result<Vec<BacktestSweep>> {
+        let sweeps = sqlx::query_as::<_, BacktestSweep>(
+            "SELECT * FROM backtest_sweeps ORDER BY created_at DESC",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(sweeps)
+    }
+
+    /// The sweep with its runs ranked by its objective.
+    pub async fn detail(id: &str, pool: &Pool<Sqlite>) -> Result<SweepDetail> {
+        let sweep = Self::find_by_id(id, pool).await?;
+        let runs = Backtest::find_by_sweep(id, pool).await?;
+        Ok(rank_runs(sweep, runs))
+    }
+}
+
+/// Order runs best first: scored runs by descending score, then the rest.
+fn rank_runs(sweep: BacktestSweep, backtests: Vec<Backtest>) -> SweepDetail {
+    let objective: SweepObjective = sweep.objective.parse().unwrap_or_default();
+    let status_count = |status: BacktestStatus| {
+        backtests
+            .iter()
+            .filter(|b| b.status == status.to_string())
+            .count()
+    };
+    let completed_count = status_count(BacktestStatus::Completed);
+    let failed_count = status_count(BacktestStatus::Failed);
+    let cancelled_count = status_count(BacktestStatus::Cancelled);
+    let status = if completed_count + failed_count + cancelled_count < backtests.len() {
+        BacktestStatus::Running
+    } else if completed_count == 0 {
+        BacktestStatus::Failed
+    } else {
+        BacktestStatus::Completed
+    };
+
+    let mut runs: Vec<SweepRun> = backtests
+        .into_iter()
+        .map(|backtest| SweepRun {
+            rank: None,
+            score: objective.score(&backtest),
+            parameters: backtest
+                .parameters
+                .as_deref()
+                .and_then(|p| serde_json::from_str(p).ok())
+                .unwrap_or(Value::Null),
+            backtest,
+        })
+        .collect();
+    runs.sort_by(|a, b| match (a.score, b.score) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    for (i, run) in runs.iter_mut().enumerate() {
+        if run.score.is_some() {
+            run.rank = Some(i + 1);
+        }
+    }
+
+    SweepDetail {
+        sweep,
+        status: status.to_string(),
+        completed_count,
+        failed_count,
+        cancelled_count,
+        runs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_expand_grid_takes_cartesian_product() {
+        let grid: BTreeMap<String, ParameterValues> = serde_json::from_value(json!({
+            "fast_period": [5, 10],
+            "slow_period": { "start": 20, "end": 40, "step": 10 }
+        }))
+        .unwrap();
+
+        let combinations = expand_grid(&grid).unwrap();
+        assert_eq!(combinations.len(), 6);
+        assert_eq!(
+            combinations[0],
+            json!({ "fast_period": 5, "slow_period": 20 })
+        );
+        assert_eq!(
+            combinations[5],
+            json!({ "fast_period": 10, "slow_period": 40 })
+        );
+    }
+
+    #[test]
+    fn test_fractional_range_and_invalid_grids() {
+        let range = ParameterValues::Range {
+            start: 0.5,
+            end: 1.0,
+            step: 0.25,
+        };
+        assert_eq!(
+            range.values().unwrap(),
+            vec![json!(0.5), json!(0.75), json!(1.0)]
+        );
+
+        let backwards = ParameterValues::Range {
+            start: 5.0,
+            end: 1.0,
+            step: 1.0,
+        };
+        assert!(backwards.values().is_err());
+        for (start, end, step) in [
+            (f64::NAN, 5.0, 1.0),
+            (1.0, f64::INFINITY, 1.0),
+            (1.0, 5.0, f64::INFINITY),
+        ] {
+            let range = ParameterValues::Range { start, end, step };
+            assert!(range.values().unwrap_err().contains("finite"));
+        }
+        assert!(expand_grid(&BTreeMap::new()).is_err());
+
+        let mut huge = BTreeMap::new();
+        huge.insert(
+            "a".to_string(),
+            ParameterValues::Range {
+                start: 1.0,
+                end: 100.0,
+                step: 1.0,
+            },
+        );
+        huge.insert("b".to_string(), ParameterValues::List(vec![json!(1); 10]));
+        assert!(expand_grid(&huge).unwrap_err().contains("more than"));
+    }
+}
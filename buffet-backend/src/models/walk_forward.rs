@@ -0,0 +1,494 @@
+use crate::error::{AppError, Result};
+use crate::models::backtest::{Backtest, BacktestStatus, CreateBacktestDto, EquityPoint};
+use crate::models::strategy::Strategy;
+use crate::models::sweep::{ParameterValues, SweepObjective, expand_grid};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Sqlite};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Upper bound on the folds of one walk-forward analysis.
+pub const MAX_FOLDS: usize = 50;
+
+/// How train segments advance from one fold to the next.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WalkForwardMode {
+    /// A fixed-length train window that slides forward with the test window.
+    #[default]
+    Rolling,
+    /// Every train window starts at the beginning of the period and grows.
+    Anchored,
+}
+
+impl std::fmt::Display for WalkForwardMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalkForwardMode::Rolling => write!(f, "rolling"),
+            WalkForwardMode::Anchored => write!(f, "anchored"),
+        }
+    }
+}
+
+impl std::str::FromStr for WalkForwardMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "rolling" => Ok(WalkForwardMode::Rolling),
+            "anchored" => Ok(WalkForwardMode::Anchored),
+            other => Err(format!("Unknown walk-forward mode '{}'", other)),
+        }
+    }
+}
+
+/// Train and test segments of one fold. Bounds are inclusive, and each test
+/// segment ends just before the next one starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldWindow {
+    pub train_start: DateTime<Utc>,
+    pub train_end: DateTime<Utc>,
+    pub test_start: DateTime<Utc>,
+    pub test_end: DateTime<Utc>,
+}
+
+/// Split `start..=end` into `folds` consecutive test segments of equal
+/// length, each preceded by a train segment `train_ratio` times as long
+/// (anchored train segments also keep everything before that).
+pub fn fold_windows(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    folds: usize,
+    train_ratio: f64,
+    mode: WalkForwardMode,
+) -> std::result::Result<Vec<FoldWindow>, String> {
+    if folds == 0 || folds > MAX_FOLDS {
+        return Err(format!("'folds' must be between 1 and {}", MAX_FOLDS));
+    }
+    if train_ratio.is_nan() || train_ratio <= 0.0 {
+        return Err("'train_ratio' must be positive".to_string());
+    }
+
+    let span = (end - start).num_microseconds().unwrap_or(0) as f64;
+    let test_len = (span / (train_ratio + folds as f64)) as i64;
+    let train_len = (test_len as f64 * train_ratio) as i64;
+    if test_len < 2 {
+        return Err("Period is too short to split into folds".to_string());
+    }
+    let test_len = Duration::microseconds(test_len);
+    let train_len = Duration::microseconds(train_len);
+    let tick = Duration::microseconds(1);
+
+    Ok((0..folds)
+        .map(|k| {
+            let test_start = start + train_len + test_len * k as i32;
+            let test_end = if k + 1 == folds {
+                end
+            } else {
+                test_start + test_len - tick
+            };
+            let train_start = match mode {
+                WalkForwardMode::Rolling => test_start - train_len,
+                WalkForwardMode::Anchored => start,
+            };
+            FoldWindow {
+                train_start,
+                train_end: test_start - tick,
+                test_start,
+                test_end,
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WalkForward {
+    pub id: String,
+    pub strategy_id: String,
+    pub mode: String,
+    pub fold_count: i64,
+    /// Train segment length in multiples of the test segment length.
+    pub train_ratio: f64,
+    pub grid: String, // JSON object of parameter name → values
+    pub objective: String,
+    /// Settings shared by every train and test run (JSON `CreateBacktestDto`).
+    pub settings: String,
+    pub initial_balance: f64,
+    pub status: String,
+    pub error_message: Option<String>,
+    // Metrics of the stitched out-of-sample result
+    pub final_balance: Option<f64>,
+    pub total_return: Option<f64>,
+    pub sharpe_ratio: Option<f64>,
+    pub max_drawdown: Option<f64>,
+    pub trade_count: Option<i64>,
+    pub win_rate: Option<f64>,
+    pub profit_factor: Option<f64>,
+    /// JSON array of `EquityPoint`s across every test segment, each fold
+    /// starting with the capital the previous one ended with.
+    pub equity_curve: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WalkForwardFold {
+    pub id: String,
+    pub walk_forward_id: String,
+    pub fold_index: i64,
+    pub train_start: DateTime<Utc>,
+    pub train_end: DateTime<Utc>,
+    pub test_start: DateTime<Utc>,
+    pub test_end: DateTime<Utc>,
+    /// The sweep that optimised parameters on the train segment.
+    pub sweep_id: Option<String>,
+    /// The out-of-sample backtest of the winning parameters.
+    pub backtest_id: Option<String>,
+    pub parameters: Option<String>, // JSON of the winning parameters
+    /// The winner's in-sample score under the objective.
+    pub train_score: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWalkForwardDto {
+    /// Settings shared by every run; `start_time..end_time` is the whole
+    /// period split into folds.
+    #[serde(flatten)]
+    pub backtest: CreateBacktestDto,
+    /// Parameter name → values optimised on each train segment.
+    pub grid: BTreeMap<String, ParameterValues>,
+    pub objective: Option<SweepObjective>,
+    pub mode: Option<WalkForwardMode>,
+    /// Number of out-of-sample test segments (default 4).
+    pub folds: Option<usize>,
+    /// Train segment length in multiples of the test segment length (default 3).
+    pub train_ratio: Option<f64>,
+}
+
+/// A fold with its out-of-sample backtest, once it has run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalkForwardFoldDetail {
+    #[serde(flatten)]
+    pub fold: WalkForwardFold,
+    pub test_backtest: Option<Backtest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalkForwardDetail {
+    #[serde(flatten)]
+    pub walk_forward: WalkForward,
+    pub folds: Vec<WalkForwardFoldDetail>,
+}
+
+/// Stitched out-of-sample metrics of a finished walk-forward analysis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkForwardResults {
+    pub final_balance: f64,
+    pub total_return: f64,
+    pub sharpe_ratio: f64,
+    pub max_drawdown: f64,
+    pub trade_count: i64,
+    pub win_rate: f64,
+    pub profit_factor: f64,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+impl WalkForward {
+    /// Create a pending walk-forward analysis and its fold windows.
+    pub async fn create(dto: CreateWalkForwardDto, pool: &Pool<Sqlite>) -> Result<WalkForward> {
+        Strategy::find_by_id(&dto.backtest.strategy_id, pool).await?;
+        expand_grid(&dto.grid).map_err(AppError::BadRequest)?;
+        if dto.backtest.warmup_start_time.is_some() {
+            return Err(AppError::BadRequest(
+                "Walk-forward folds warm up on their train segment; \
+                 'warmup_start_time' is not supported"
+                    .to_string(),
+            ));
+        }
+
+        let mode = dto.mode.unwrap_or_default();
+        let folds = dto.folds.unwrap_or(4);
+        let train_ratio = dto.train_ratio.unwrap_or(3.0);
+        let windows = fold_windows(
+            dto.backtest.start_time,
+            dto.backtest.end_time,
+            folds,
+            train_ratio,
+            mode,
+        )
+        .map_err(AppError::BadRequest)?;
+
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let mode_str = mode.to_string();
+        let fold_count = folds as i64;
+        let grid = serde_json::to_string(&dto.grid)
+            .map_err(|e| AppError::BadRequest(format!("Invalid grid: {}", e)))?;
+        let objective = dto.objective.unwrap_or_default().to_string();
+        let settings = serde_json::to_string(&dto.backtest)
+            .map_err(|e| AppError::BadRequest(format!("Invalid settings: {}", e)))?;
+        let status = BacktestStatus::Pending.to_string();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO walk_forwards (id, strategy_id, mode, fold_count, train_ratio, grid, objective, settings, initial_balance, status, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            id,
+            dto.backtest.strategy_id,
+            mode_str,
+            fold_count,
+            train_ratio,
+            grid,
+            objective,
+            settings,
+            dto.backtest.initial_balance,
+            status,
+            now
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        for (index, window) in windows.iter().enumerate() {
+            let fold_id = Uuid::new_v4().to_string();
+            let fold_index = index as i64;
+            sqlx::query!(
+                r#"
+                INSERT INTO walk_forward_folds (id, walk_forward_id, fold_index, train_start, train_end, test_start, test_end)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#,
+                fold_id,
+                id,
+                fold_index,
+                window.train_start,
+                window.train_end,
+                window.test_start,
+                window.test_end
+            )
+            .execute(pool)
+            .await
+            .map_err(AppError::Database)?;
+        }
+
+        Self::find_by_id(&id, pool).await
+    }
+
+    pub async fn find_by_id(id: &str, pool: &Pool<Sqlite>) -> Result<WalkForward> {
+        let walk_forward =
+            sqlx::query_as::<_, WalkForward>("SELECT * FROM walk_forwards WHERE id = ?")
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+                .map_err(AppError::Database)?
+                .ok_or_else(|| {
+                    AppError::NotFound(format!("Walk-forward analysis with ID {} not found", id))
+                })?;
+
+        Ok(walk_forward)
+    }
+
+    pub async fn find_all(pool: &Pool<Sqlite>) -> Result<Vec<WalkForward>> {
+        let walk_forwards = sqlx::query_as::<_, WalkForward>(
+            "SELECT * FROM walk_forwards ORDER BY created_at DESC",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(walk_forwards)
+    }
+
+    /// The analysis with every fold and its out-of-sample backtest.
+    pub async fn detail(id: &str, pool: &Pool<Sqlite>) -> Result<WalkForwardDetail> {
+        let walk_forward = Self::find_by_id(id, pool).await?;
+        let mut folds = Vec::new();
+        for fold in WalkForwardFold::find_by_walk_forward(id, pool).await? {
+            let test_backtest = match &fold.backtest_id {
+                Some(backtest_id) => Some(Backtest::find_by_id(backtest_id, pool).await?),
+                None => None,
+            };
+            folds.push(WalkForwardFoldDetail {
+                fold,
+                test_backtest,
+            });
+        }
+
+        Ok(WalkForwardDetail {
+            walk_forward,
+            folds,
+        })
+    }
+
+    pub async fn update_status(
+        id: &str,
+        status: BacktestStatus,
+        error_message: Option<String>,
+        pool: &Pool<Sqlite>,
+    ) -> Result<()> {
+        let status_str = status.to_string();
+        sqlx::query!(
+            "UPDATE walk_forwards SET status = ?, error_message = ? WHERE id = ?",
+            status_str,
+            error_message,
+            id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    /// Fail every walk-forward still pending or running, returning how many
+    /// there were. Their folds are driven by the process that started them,
+    /// so none survives a restart.
+    pub async fn fail_unfinished(reason: &str, pool: &Pool<Sqlite>) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE walk_forwards
+            SET status = 'failed', error_message = ?
+            WHERE status IN ('pending', 'running')
+            "#,
+            reason
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn update_results(
+        id: &str,
+        results: &WalkForwardResults,
+        pool: &Pool<Sqlite>,
+    ) -> Result<()> {
+        let equity_curve = serde_json::to_string(&results.equity_curve)
+            .map_err(|e| AppError::BadRequest(format!("Invalid equity curve: {}", e)))?;
+
+        sqlx::query!(
+            r#"
+            UPDATE walk_forwards
+            SET final_balance = ?,
+                total_return = ?,
+                sharpe_ratio = ?,
+                max_drawdown = ?,
+                trade_count = ?,
+                win_rate = ?,
+                profit_factor = ?,
+                equity_curve = ?,
+                status = 'completed'
+            WHERE id = ?
+            "#,
+            results.final_balance,
+            results.total_return,
+            results.sharpe_ratio,
+            results.max_drawdown,
+            results.trade_count,
+            results.win_rate,
+            results.profit_factor,
+            equity_curve,
+            id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+}
+
+impl WalkForwardFold {
+    pub async fn find_by_walk_forward(
+        walk_forward_id: &str,
+        pool: &Pool<Sqlite>,
+    ) -> Result<Vec<WalkForwardFold>> {
+        let folds = sqlx::query_as::<_, WalkForwardFold>(
+            "SELECT * FROM walk_forward_folds WHERE walk_forward_id = ? ORDER BY fold_index ASC",
+        )
+        .bind(walk_forward_id)
+        .fetch_all(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(folds)
+    }
+
+    /// Record the fold's optimisation sweep and the winner it picked.
+    pub async fn record_optimisation(
+        id: &str,
+        sweep_id: &str,
+        parameters: &serde_json::Value,
+        train_score: Option<f64>,
+        pool: &Pool<Sqlite>,
+    ) -> Result<()> {
+        let parameters = parameters.to_string();
+        sqlx::query!(
+            "UPDATE walk_forward_folds SET sweep_id = ?, parameters = ?, train_score = ? WHERE id = ?",
+            sweep_id,
+            parameters,
+            train_score,
+            id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+
+    /// Record the out-of-sample backtest of the fold's winner.
+    pub async fn record_test(id: &str, backtest_id: &str, pool: &Pool<Sqlite>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE walk_forward_folds SET backtest_id = ? WHERE id = ?",
+            backtest_id,
+            id
+        )
+        .execute(pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn day(d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_rolling_folds_slide_a_fixed_train_window() {
+        // 10 days, 2 folds, train 3x test → test segments of 2 days
+        let windows = fold_windows(day(1), day(11), 2, 3.0, WalkForwardMode::Rolling).unwrap();
+        assert_eq!(windows.len(), 2);
+
+        assert_eq!(windows[0].train_start, day(1));
+        assert_eq!(windows[0].test_start, day(7));
+        assert_eq!(windows[0].test_end, day(9) - Duration::microseconds(1));
+        assert_eq!(windows[0].train_end, day(7) - Duration::microseconds(1));
+
+        assert_eq!(windows[1].train_start, day(3));
+        assert_eq!(windows[1].test_start, day(9));
+        assert_eq!(windows[1].test_end, day(11));
+    }
+
+    #[test]
+    fn test_anchored_folds_keep_the_start() {
+        let windows = fold_windows(day(1), day(11), 2, 3.0, WalkForwardMode::Anchored).unwrap();
+        assert!(windows.iter().all(|w| w.train_start == day(1)));
+        assert_eq!(windows[1].train_end, day(9) - Duration::microseconds(1));
+    }
+
+    #[test]
+    fn test_fold_windows_rejects_bad_settings() {
+        assert!(fold_windows(day(1), day(11), 0, 3.0, WalkForwardMode::Rolling).is_err());
+        assert!(fold_windows(day(1), day(11), 2, 0.0, WalkForwardMode::Rolling).is_err());
+        assert!(fold_windows(day(1), day(1), 2, 3.0, WalkForwardMode::Rolling).is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use crate::models::market_data::OHLCV;
+use crate::models::market_data::{CorporateAction, OHLCV};
 use chrono::{DateTime, Utc};
 
 #[derive(Debug, thiserror::Error)]
@@ -23,6 +23,17 @@ pub trait MarketDataProvider: Send + Sync {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<Vec<OHLCV>, ProviderError>;
+
+    /// Splits and dividends that went ex between `start` and `end`. Providers
+    /// without corporate-action data report none.
+    async fn fetch_corporate_actions(
+        &self,
+        _symbol: &str,
+        _start: DateTime<Utc>,
+        _end: DateTime<Utc>,
+    ) -> Result<Vec<CorporateAction>, ProviderError> {
+        Ok(Vec::new())
+    }
 }
 
 pub mod normalize;
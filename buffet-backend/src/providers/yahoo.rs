@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use time::OffsetDateTime;
 
-use crate::models::market_data::OHLCV;
+use crate::models::market_data::{CorporateAction, OHLCV};
 use crate::providers::{MarketDataProvider, ProviderError};
 
 pub struct YahooProvider;
@@ -25,6 +25,31 @@ fn chrono_to_offset(dt: DateTime<Utc>) -> OffsetDateTime {
         .unwrap_or(OffsetDateTime::UNIX_EPOCH)
 }
 
+/// Daily quotes for `symbol`, with split and dividend events.
+async fn quote_history(
+    symbol: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<yahoo_finance_api::YResponse, ProviderError> {
+    let connector = yahoo_finance_api::YahooConnector::new()
+        .map_err(|e| ProviderError::Http(e.to_string()))?;
+
+    let start_odt = chrono_to_offset(start);
+    let end_odt = chrono_to_offset(end);
+
+    connector
+        .get_quote_history(symbol, start_odt, end_odt)
+        .await
+        .map_err(|e| {
+            let msg = e.to_string();
+            if msg.to_lowercase().contains("too many") || msg.contains("429") {
+                ProviderError::RateLimited
+            } else {
+                ProviderError::Http(msg)
+            }
+        })
+}
+
 #[async_trait]
 impl MarketDataProvider for YahooProvider {
     fn name(&self) -> &str {
@@ -37,23 +62,7 @@ impl MarketDataProvider for YahooProvider {
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<Vec<OHLCV>, ProviderError> {
-        let connector = yahoo_finance_api::YahooConnector::new()
-            .map_err(|e| ProviderError::Http(e.to_string()))?;
-
-        let start_odt = chrono_to_offset(start);
-        let end_odt = chrono_to_offset(end);
-
-        let response = connector
-            .get_quote_history(symbol, start_odt, end_odt)
-            .await
-            .map_err(|e| {
-                let msg = e.to_string();
-                if msg.to_lowercase().contains("too many") || msg.contains("429") {
-                    ProviderError::RateLimited
-                } else {
-                    ProviderError::Http(msg)
-                }
-            })?;
+        let response = quote_history(symbol, start, end).await?;
 
         let quotes = response
             .quotes()
@@ -80,4 +89,35 @@ impl MarketDataProvider for YahooProvider {
 
         Ok(ohlcv)
     }
+
+    async fn fetch_corporate_actions(
+        &self,
+        symbol: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CorporateAction>, ProviderError> {
+        let response = quote_history(symbol, start, end).await?;
+        let ex_date = |date: u64| DateTime::from_timestamp(date as i64, 0).unwrap_or_default();
+
+        let splits = response
+            .splits()
+            .map_err(|e| ProviderError::Parse(e.to_string()))?;
+        let dividends = response
+            .dividends()
+            .map_err(|e| ProviderError::Parse(e.to_string()))?;
+
+        // A 4:1 split arrives as numerator 4, denominator 1
+        let mut actions: Vec<CorporateAction> = splits
+            .into_iter()
+            .filter(|s| s.numerator > 0.0 && s.denominator > 0.0)
+            .map(|s| CorporateAction::split(ex_date(s.date), s.numerator / s.denominator))
+            .chain(
+                dividends
+                    .into_iter()
+                    .map(|d| CorporateAction::dividend(ex_date(d.date), d.amount)),
+            )
+            .collect();
+        actions.sort_by_key(|action| action.timestamp);
+        Ok(actions)
+    }
 }
@@ -9,8 +9,20 @@ pub fn create_routes() -> Router<AppState> {
         .route("/api/backtests", get(backtest::list_backtests))
         .route("/api/backtests", post(backtest::run_backtest))
         .route("/api/backtests/{id}", get(backtest::get_backtest))
+        .route(
+            "/api/backtests/{id}/cancel",
+            post(backtest::cancel_backtest),
+        )
         .route(
             "/api/backtests/{id}/trades",
             get(backtest::get_backtest_trades),
         )
+        .route(
+            "/api/backtests/{id}/equity",
+            get(backtest::get_backtest_equity),
+        )
+        .route(
+            "/api/backtests/{id}/monte-carlo",
+            post(backtest::run_monte_carlo),
+        )
 }
@@ -4,7 +4,8 @@ use sqlx::{Pool, Postgres, Sqlite};
 use tower_http::cors::{Any, CorsLayer};
 
 use crate::actors::{
-    BacktestActor, DataCollectorActor, OrderExecutionActor, StrategyExecutorActor,
+    BacktestWorkers, DataCollectorActor, OrderExecutionActor, StrategyExecutorActor,
+    WalkForwardActor,
 };
 use crate::state::AppState;
 use kameo::actor::ActorRef;
@@ -16,6 +17,8 @@ mod order;
 mod position;
 mod signal;
 mod strategy;
+mod sweep;
+mod walk_forward;
 
 pub fn create_router(
     db_pool: Pool<Sqlite>,
@@ -23,7 +26,8 @@ pub fn create_router(
     collector_actor: ActorRef<DataCollectorActor>,
     executor_actor: ActorRef<StrategyExecutorActor>,
     execution_actor: ActorRef<OrderExecutionActor>,
-    backtest_actor: ActorRef<BacktestActor>,
+    backtest_workers: BacktestWorkers,
+    walk_forward_actor: ActorRef<WalkForwardActor>,
 ) -> Router {
     // Create application state
     let state = AppState::new(
@@ -32,7 +36,8 @@ pub fn create_router(
         collector_actor,
         executor_actor,
         execution_actor,
-        backtest_actor,
+        backtest_workers,
+        walk_forward_actor,
     );
 
     // Configure CORS
@@ -51,6 +56,8 @@ fn create_router_with_state(state: AppState) -> Router {
         .merge(order::create_routes())
         .merge(position::create_routes())
         .merge(backtest::create_routes())
+        .merge(sweep::create_routes())
+        .merge(walk_forward::create_routes())
         .merge(signal::create_routes())
         .merge(collect::create_routes())
         .merge(health::create_routes())
@@ -8,6 +8,7 @@ pub fn create_routes() -> Router<AppState> {
     Router::new()
         .route("/api/strategies", get(strategy::list_strategies))
         .route("/api/strategies", post(strategy::create_strategy))
+        .route("/api/strategies/kinds", get(strategy::list_strategy_kinds))
         .route("/api/strategies/{id}", get(strategy::get_strategy))
         .route("/api/strategies/{id}", put(strategy::update_strategy))
         .route("/api/strategies/{id}", delete(strategy::delete_strategy))
@@ -0,0 +1,12 @@
+use crate::{handlers::sweep, state::AppState};
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+pub fn create_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/sweeps", get(sweep::list_sweeps))
+        .route("/api/sweeps", post(sweep::run_sweep))
+        .route("/api/sweeps/{id}", get(sweep::get_sweep))
+}
@@ -0,0 +1,15 @@
+use crate::{handlers::walk_forward, state::AppState};
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+pub fn create_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/walk-forwards", get(walk_forward::list_walk_forwards))
+        .route("/api/walk-forwards", post(walk_forward::run_walk_forward))
+        .route(
+            "/api/walk-forwards/{id}",
+            get(walk_forward::get_walk_forward),
+        )
+}
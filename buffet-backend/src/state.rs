@@ -2,7 +2,8 @@ use kameo::actor::ActorRef;
 use sqlx::{Pool, Postgres, Sqlite};
 
 use crate::actors::{
-    BacktestActor, DataCollectorActor, OrderExecutionActor, StrategyExecutorActor,
+    BacktestWorkers, DataCollectorActor, OrderExecutionActor, StrategyExecutorActor,
+    WalkForwardActor,
 };
 
 #[derive(Clone)]
@@ -12,7 +13,8 @@ pub struct AppState {
     pub collector: ActorRef<DataCollectorActor>,
     pub executor: ActorRef<StrategyExecutorActor>,
     pub execution: ActorRef<OrderExecutionActor>,
-    pub backtest: ActorRef<BacktestActor>,
+    pub backtest: BacktestWorkers,
+    pub walk_forward: ActorRef<WalkForwardActor>,
 }
 
 impl AppState {
@@ -22,7 +24,8 @@ impl AppState {
         collector: ActorRef<DataCollectorActor>,
         executor: ActorRef<StrategyExecutorActor>,
         execution: ActorRef<OrderExecutionActor>,
-        backtest: ActorRef<BacktestActor>,
+        backtest: BacktestWorkers,
+        walk_forward: ActorRef<WalkForwardActor>,
     ) -> Self {
         Self {
             db,
@@ -31,6 +34,7 @@ impl AppState {
             executor,
             execution,
             backtest,
+            walk_forward,
         }
     }
 }
@@ -0,0 +1,234 @@
+use crate::actors::messages::SignalType;
+use crate::actors::strategy::StrategyLogic;
+use crate::models::market_data::OHLCV;
+use crate::models::strategy::StrategyType;
+use crate::strategies::{
+    StrategyDefinition, optional_number, optional_positive_int, param_f64, param_usize,
+};
+use polars::prelude::{Duration, Expr, NULL, RollingOptions, RollingVarParams, col, lit, when};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use ta::Next;
+use ta::indicators::BollingerBands;
+
+pub fn definition() -> StrategyDefinition {
+    StrategyDefinition {
+        kind: "bollinger",
+        strategy_type: StrategyType::Classical,
+        is_default: false,
+        description: "Trade closes outside the Bollinger Bands, as breakout or reversion",
+        parameter_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "period": { "type": "integer", "minimum": 2 },
+                    "std_dev": { "type": "number", "exclusiveMinimum": 0 },
+                    "mode": { "type": "string", "enum": ["reversion", "breakout"] }
+                }
+            })
+        },
+        default_parameters: || json!({ "period": 20, "std_dev": 2.0, "mode": "reversion" }),
+        validate,
+        warmup: |params| params["period"].as_u64().unwrap_or(0) as usize,
+        build: |params| Ok(Box::new(BollingerBandStrategy::from_params(params)?)),
+        vectorized: Some(vectorized),
+        needs_short: false,
+    }
+}
+
+/// Optional `period` (> 1), positive `std_dev`, and `mode` of `reversion` or `breakout`.
+fn validate(params: &Value) -> Result<(), String> {
+    if optional_positive_int(params, "period")? == Some(1) {
+        return Err("'period' must be at least 2".to_string());
+    }
+    if optional_number(params, "std_dev")?.is_some_and(|v| v <= 0.0) {
+        return Err("'std_dev' must be a positive number".to_string());
+    }
+    if let Some(mode) = params.get("mode") {
+        mode.as_str()
+            .ok_or_else(|| "'mode' must be a string".to_string())?
+            .parse::<BollingerMode>()?;
+    }
+
+    Ok(())
+}
+
+/// How a Bollinger Band strategy reacts to the close leaving the bands.
+/// Buy (1.0) or sell (-1.0) closes outside the bands, null inside them.
+/// The bands use the population standard deviation, as `ta`'s do.
+fn vectorized(params: &Value) -> Result<Expr, String> {
+    let period = param_usize(params, "period")?;
+    let std_dev = param_f64(params, "std_dev")?;
+    let mode = params["mode"]
+        .as_str()
+        .ok_or_else(|| "'mode' must be a string".to_string())?
+        .parse::<BollingerMode>()?;
+
+    let options = RollingOptions {
+        window_size: Duration::new(period as i64),
+        min_periods: period,
+        ..Default::default()
+    };
+    let mean = col("close").rolling_mean(options.clone());
+    let sd = col("close").rolling_std(RollingOptions {
+        fn_params: Some(Arc::new(RollingVarParams { ddof: 0 })),
+        ..options
+    });
+    let above = col("close").gt(mean.clone() + sd.clone() * lit(std_dev));
+    let below = col("close").lt(mean - sd * lit(std_dev));
+    let (buy, sell) = match mode {
+        BollingerMode::Reversion => (below, above),
+        BollingerMode::Breakout => (above, below),
+    };
+
+    Ok(when(buy)
+        .then(lit(1.0))
+        .when(sell)
+        .then(lit(-1.0))
+        .otherwise(lit(NULL)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BollingerMode {
+    /// Fade the move: buy below the lower band, sell above the upper band.
+    Reversion,
+    /// Follow the move: buy above the upper band, sell below the lower band.
+    Breakout,
+}
+
+impl std::fmt::Display for BollingerMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BollingerMode::Reversion => write!(f, "reversion"),
+            BollingerMode::Breakout => write!(f, "breakout"),
+        }
+    }
+}
+
+impl std::str::FromStr for BollingerMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reversion" => Ok(BollingerMode::Reversion),
+            "breakout" => Ok(BollingerMode::Breakout),
+            _ => Err(format!("Unknown Bollinger mode: {}", s)),
+        }
+    }
+}
+
+/// Bollinger Band strategy supporting both breakout and mean-reversion modes.
+pub struct BollingerBandStrategy {
+    period: usize,
+    mode: BollingerMode,
+    bands: BollingerBands,
+    bars_seen: usize,
+}
+
+impl BollingerBandStrategy {
+    pub fn new(period: usize, std_dev: f64, mode: BollingerMode) -> Result<Self, String> {
+        let bands = BollingerBands::new(period, std_dev).map_err(|e| {
+            format!(
+                "Invalid Bollinger parameters (period {}, std_dev {}): {}",
+                period, std_dev, e
+            )
+        })?;
+        Ok(Self {
+            period,
+            mode,
+            bands,
+            bars_seen: 0,
+        })
+    }
+
+    /// Build from JSON parameters (`period`, `std_dev`, `mode`).
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        let mode = params["mode"]
+            .as_str()
+            .ok_or_else(|| "'mode' must be a string".to_string())?
+            .parse::<BollingerMode>()?;
+        Self::new(
+            param_usize(params, "period")?,
+            param_f64(params, "std_dev")?,
+            mode,
+        )
+    }
+}
+
+impl StrategyLogic for BollingerBandStrategy {
+    fn update(&mut self, data: &OHLCV) -> Option<SignalType> {
+        let bands = self.bands.next(data.close);
+        self.bars_seen += 1;
+
+        if self.bars_seen < self.period {
+            return None;
+        }
+
+        let above = data.close > bands.upper;
+        let below = data.close < bands.lower;
+
+        match (self.mode, above, below) {
+            (BollingerMode::Reversion, _, true) | (BollingerMode::Breakout, true, _) => {
+                Some(SignalType::Buy)
+            }
+            (BollingerMode::Reversion, true, _) | (BollingerMode::Breakout, _, true) => {
+                Some(SignalType::Sell)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn candle(i: i64, close: f64) -> OHLCV {
+        OHLCV::new(
+            Utc.timestamp_opt(i * 60, 0).unwrap(),
+            close,
+            close,
+            close,
+            close,
+            1.0,
+        )
+    }
+
+    fn run(mode: BollingerMode, closes: &[f64]) -> Option<SignalType> {
+        let mut strategy = BollingerBandStrategy::new(10, 2.0, mode).unwrap();
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| strategy.update(&candle(i as i64, c)))
+            .last()
+            .flatten()
+    }
+
+    #[test]
+    fn test_reversion_buys_below_lower_band() {
+        let closes = [
+            100.0, 101.0, 99.0, 100.0, 101.0, 99.0, 100.0, 101.0, 99.0, 80.0,
+        ];
+        assert_eq!(
+            run(BollingerMode::Reversion, &closes),
+            Some(SignalType::Buy)
+        );
+    }
+
+    #[test]
+    fn test_breakout_buys_above_upper_band() {
+        let closes = [
+            100.0, 101.0, 99.0, 100.0, 101.0, 99.0, 100.0, 101.0, 99.0, 120.0,
+        ];
+        assert_eq!(run(BollingerMode::Breakout, &closes), Some(SignalType::Buy));
+    }
+
+    #[test]
+    fn test_inside_bands_emits_nothing() {
+        let closes = [
+            100.0, 101.0, 99.0, 100.0, 101.0, 99.0, 100.0, 101.0, 99.0, 100.0,
+        ];
+        assert_eq!(run(BollingerMode::Reversion, &closes), None);
+    }
+}
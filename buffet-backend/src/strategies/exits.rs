@@ -0,0 +1,416 @@
+//! Risk exits applied by the backtester on top of a strategy's own signals.
+//!
+//! Configured under the `exits` key of a strategy's `parameters`, e.g.
+//! `{"exits": {"stop_loss_pct": 0.05, "take_profit_pct": 0.1}}`. Stops are
+//! checked intrabar against each candle's high and low.
+//!
+//! `flat_by_close_minutes` is a session rule: positions are closed on the
+//! last bar ending within that many minutes of the trading calendar's
+//! close, and nothing new is opened on it.
+
+use crate::calendar::TradingCalendar;
+use crate::models::backtest::ExitReason;
+use crate::models::market_data::OHLCV;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use ta::Next;
+use ta::indicators::AverageTrueRange;
+
+/// Exit rules for a strategy, plus the ATR state needed to place ATR stops.
+#[derive(Default)]
+pub struct ExitRules {
+    /// Stop this fraction below (longs) or above (shorts) the entry price.
+    pub stop_loss_pct: Option<f64>,
+    /// Take profit this fraction beyond the entry price.
+    pub take_profit_pct: Option<f64>,
+    /// Trail the best price since entry by this fraction.
+    pub trailing_stop_pct: Option<f64>,
+    pub atr_period: usize,
+    /// Stop this many ATRs from the entry price.
+    pub atr_stop_multiple: Option<f64>,
+    /// Trail the best price since entry by this many ATRs (measured at entry).
+    pub atr_trailing_multiple: Option<f64>,
+    /// Be flat by the session close, this many minutes ahead of it.
+    pub flat_by_close_minutes: Option<i64>,
+    atr: HashMap<String, (AverageTrueRange, f64)>,
+}
+
+fn optional_fraction(config: &Value, key: &str) -> Result<Option<f64>, String> {
+    match config.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_f64()
+            .filter(|v| *v > 0.0 && *v < 1.0)
+            .map(Some)
+            .ok_or_else(|| format!("'exits.{}' must be between 0 and 1 (exclusive)", key)),
+    }
+}
+
+fn optional_positive(config: &Value, key: &str) -> Result<Option<f64>, String> {
+    match config.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_f64()
+            .filter(|v| *v > 0.0)
+            .map(Some)
+            .ok_or_else(|| format!("'exits.{}' must be a positive number", key)),
+    }
+}
+
+impl ExitRules {
+    /// Parse `params["exits"]`; a missing block means no risk exits.
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        let config = match params.get("exits") {
+            None | Some(Value::Null) => return Ok(Self::default()),
+            Some(config) if config.is_object() => config,
+            Some(_) => return Err("'exits' must be an object".to_string()),
+        };
+
+        let atr_period = match config.get("atr_period") {
+            None => 14,
+            Some(v) => v
+                .as_u64()
+                .filter(|v| *v > 0)
+                .ok_or_else(|| "'exits.atr_period' must be a positive integer".to_string())?
+                as usize,
+        };
+
+        let flat_by_close_minutes = match config.get("flat_by_close_minutes") {
+            None => None,
+            Some(v) => Some(v.as_u64().ok_or_else(|| {
+                "'exits.flat_by_close_minutes' must be a non-negative integer".to_string()
+            })? as i64),
+        };
+
+        Ok(Self {
+            stop_loss_pct: optional_fraction(config, "stop_loss_pct")?,
+            take_profit_pct: optional_positive(config, "take_profit_pct")?,
+            trailing_stop_pct: optional_fraction(config, "trailing_stop_pct")?,
+            atr_period,
+            atr_stop_multiple: optional_positive(config, "atr_stop_multiple")?,
+            atr_trailing_multiple: optional_positive(config, "atr_trailing_multiple")?,
+            flat_by_close_minutes,
+            atr: HashMap::new(),
+        })
+    }
+
+    /// Validate an exits block without keeping the rules.
+    pub fn validate(params: &Value) -> Result<(), String> {
+        Self::from_params(params).map(|_| ())
+    }
+
+    /// Whether no exit rule is configured.
+    pub fn is_empty(&self) -> bool {
+        self.stop_loss_pct.is_none()
+            && self.take_profit_pct.is_none()
+            && self.trailing_stop_pct.is_none()
+            && !self.uses_atr()
+            && self.flat_by_close_minutes.is_none()
+    }
+
+    fn uses_atr(&self) -> bool {
+        self.atr_stop_multiple.is_some() || self.atr_trailing_multiple.is_some()
+    }
+
+    /// Feed a candle for `symbol` so ATR-based stops stay current.
+    pub fn on_bar(&mut self, symbol: &str, data: &OHLCV) {
+        if !self.uses_atr() {
+            return;
+        }
+        let period = self.atr_period;
+        let (atr, last) = self.atr.entry(symbol.to_string()).or_insert_with(|| {
+            (
+                AverageTrueRange::new(period).expect("atr_period validated positive"),
+                0.0,
+            )
+        });
+        *last = atr.next(data);
+    }
+
+    /// Whether a bar of `bar_seconds` opening at `timestamp` is the last one
+    /// to trade before `calendar`'s session closes, under the flat-by-close
+    /// rule. Bars outside a session never are.
+    pub fn is_closing_bar(
+        &self,
+        timestamp: DateTime<Utc>,
+        bar_seconds: f64,
+        calendar: &TradingCalendar,
+    ) -> bool {
+        let Some(minutes) = self.flat_by_close_minutes else {
+            return false;
+        };
+        let Some(close) = calendar.session_close(timestamp) else {
+            return false;
+        };
+        let bar_end = timestamp + Duration::seconds(bar_seconds.round() as i64);
+        bar_end + Duration::minutes(minutes) >= close
+    }
+
+    /// Stops for a position just opened in `symbol`, or `None` without rules.
+    /// `direction` is `1.0` for longs and `-1.0` for shorts.
+    pub fn track(&self, symbol: &str, direction: f64, entry_price: f64) -> Option<StopTracker> {
+        let atr = self
+            .atr
+            .get(symbol)
+            .map(|(_, atr)| *atr)
+            .filter(|atr| *atr > 0.0);
+
+        // Of a percent and an ATR stop, the one nearer the entry wins
+        let stop_distance = [
+            self.stop_loss_pct.map(|pct| entry_price * pct),
+            self.atr_stop_multiple.zip(atr).map(|(m, atr)| m * atr),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(f64::min);
+
+        let tracker = StopTracker {
+            direction,
+            stop_loss: stop_distance.map(|d| entry_price - direction * d),
+            take_profit: self
+                .take_profit_pct
+                .map(|pct| entry_price * (1.0 + direction * pct)),
+            trailing_pct: self.trailing_stop_pct,
+            trailing_distance: self.atr_trailing_multiple.zip(atr).map(|(m, atr)| m * atr),
+            extreme: entry_price,
+        };
+
+        tracker.is_active().then_some(tracker)
+    }
+
+    /// Snapshot of the rules for `run_config`.
+    pub fn describe(&self) -> Value {
+        json!({
+            "stop_loss_pct": self.stop_loss_pct,
+            "take_profit_pct": self.take_profit_pct,
+            "trailing_stop_pct": self.trailing_stop_pct,
+            "atr_period": self.atr_period,
+            "atr_stop_multiple": self.atr_stop_multiple,
+            "atr_trailing_multiple": self.atr_trailing_multiple,
+            "flat_by_close_minutes": self.flat_by_close_minutes,
+        })
+    }
+}
+
+/// Exit levels for one open position.
+#[derive(Debug, Clone)]
+pub struct StopTracker {
+    /// `1.0` for longs, `-1.0` for shorts.
+    direction: f64,
+    stop_loss: Option<f64>,
+    take_profit: Option<f64>,
+    trailing_pct: Option<f64>,
+    trailing_distance: Option<f64>,
+    /// Best price reached since entry (highest high / lowest low).
+    extreme: f64,
+}
+
+impl StopTracker {
+    fn is_active(&self) -> bool {
+        self.stop_loss.is_some()
+            || self.take_profit.is_some()
+            || self.trailing_pct.is_some()
+            || self.trailing_distance.is_some()
+    }
+
+    /// The tighter trailing level given the best price so far.
+    fn trailing_stop(&self) -> Option<f64> {
+        [
+            self.trailing_pct
+                .map(|pct| self.extreme * (1.0 - self.direction * pct)),
+            self.trailing_distance
+                .map(|d| self.extreme - self.direction * d),
+        ]
+        .into_iter()
+        .flatten()
+        .reduce(|a, b| {
+            if self.direction > 0.0 {
+                a.max(b)
+            } else {
+                a.min(b)
+            }
+        })
+    }
+
+    /// The protective stop nearest the market, with the reason it would fire.
+    fn active_stop(&self) -> Option<(f64, ExitReason)> {
+        let fixed = self.stop_loss.map(|level| (level, ExitReason::StopLoss));
+        let trailing = self
+            .trailing_stop()
+            .map(|level| (level, ExitReason::TrailingStop));
+        match (fixed, trailing) {
+            (Some(f), Some(t)) => {
+                // A trailing stop only takes over once it has moved past the fixed one
+                if self.direction * (t.0 - f.0) > 0.0 {
+                    Some(t)
+                } else {
+                    Some(f)
+                }
+            }
+            (f, t) => f.or(t),
+        }
+    }
+
+    /// Check `candle` against the exit levels set before it opened.
+    ///
+    /// A gap through a level fills at the open. When a stop and the take
+    /// profit both fall inside one bar the stop is assumed to hit first.
+    /// Returns the exit reason and price, or updates the trailing extreme.
+    pub fn check(&mut self, candle: &OHLCV) -> Option<(ExitReason, f64)> {
+        let d = self.direction;
+        // Signed distance "past" a level in the adverse/favourable direction
+        let adverse = |price: f64, level: f64| d * (level - price) >= 0.0;
+        let favourable = |price: f64, level: f64| d * (price - level) >= 0.0;
+        let (worst, best) = if d > 0.0 {
+            (candle.low, candle.high)
+        } else {
+            (candle.high, candle.low)
+        };
+
+        let stop = self.active_stop();
+        if let Some((level, reason)) = stop
+            && adverse(candle.open, level)
+        {
+            return Some((reason, candle.open));
+        }
+        if let Some(level) = self.take_profit
+            && favourable(candle.open, level)
+        {
+            return Some((ExitReason::TakeProfit, candle.open));
+        }
+        if let Some((level, reason)) = stop
+            && adverse(worst, level)
+        {
+            return Some((reason, level));
+        }
+        if let Some(level) = self.take_profit
+            && favourable(best, level)
+        {
+            return Some((ExitReason::TakeProfit, level));
+        }
+
+        // Trail only after the bar, so a stop never reacts to its own bar's extreme
+        if d * (best - self.extreme) > 0.0 {
+            self.extreme = best;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn bar(open: f64, high: f64, low: f64, close: f64) -> OHLCV {
+        OHLCV::new(
+            Utc.timestamp_opt(0, 0).unwrap(),
+            open,
+            high,
+            low,
+            close,
+            1.0,
+        )
+    }
+
+    fn rules(exits: Value) -> ExitRules {
+        ExitRules::from_params(&json!({ "exits": exits })).unwrap()
+    }
+
+    #[test]
+    fn test_no_rules_means_no_tracker() {
+        assert!(ExitRules::default().track("A", 1.0, 100.0).is_none());
+    }
+
+    #[test]
+    fn test_long_stop_loss_intrabar_and_gap() {
+        let rules = rules(json!({"stop_loss_pct": 0.05}));
+
+        let mut tracker = rules.track("A", 1.0, 100.0).unwrap();
+        assert_eq!(tracker.check(&bar(99.0, 101.0, 96.0, 98.0)), None);
+        assert_eq!(
+            tracker.check(&bar(98.0, 99.0, 94.0, 95.0)),
+            Some((ExitReason::StopLoss, 95.0))
+        );
+
+        // Gapping below the stop fills at the open, not the stop level
+        let mut tracker = rules.track("A", 1.0, 100.0).unwrap();
+        assert_eq!(
+            tracker.check(&bar(90.0, 92.0, 89.0, 91.0)),
+            Some((ExitReason::StopLoss, 90.0))
+        );
+    }
+
+    #[test]
+    fn test_short_take_profit() {
+        let rules = rules(json!({"take_profit_pct": 0.1}));
+        let mut tracker = rules.track("A", -1.0, 100.0).unwrap();
+        assert_eq!(tracker.check(&bar(98.0, 99.0, 91.0, 92.0)), None);
+        assert_eq!(
+            tracker.check(&bar(92.0, 93.0, 88.0, 89.0)),
+            Some((ExitReason::TakeProfit, 90.0))
+        );
+    }
+
+    #[test]
+    fn test_stop_wins_when_both_levels_inside_bar() {
+        let rules = rules(json!({"stop_loss_pct": 0.05, "take_profit_pct": 0.05}));
+        let mut tracker = rules.track("A", 1.0, 100.0).unwrap();
+        assert_eq!(
+            tracker.check(&bar(100.0, 106.0, 94.0, 100.0)),
+            Some((ExitReason::StopLoss, 95.0))
+        );
+    }
+
+    #[test]
+    fn test_trailing_stop_follows_highs() {
+        let rules = rules(json!({"stop_loss_pct": 0.1, "trailing_stop_pct": 0.05}));
+        let mut tracker = rules.track("A", 1.0, 100.0).unwrap();
+
+        assert_eq!(tracker.check(&bar(100.0, 120.0, 99.0, 118.0)), None);
+        // Trailing level is now 114, above the fixed stop at 90
+        assert_eq!(
+            tracker.check(&bar(117.0, 118.0, 110.0, 112.0)),
+            Some((ExitReason::TrailingStop, 114.0))
+        );
+    }
+
+    #[test]
+    fn test_atr_stop_uses_atr_at_entry() {
+        let mut rules = rules(json!({"atr_period": 3, "atr_stop_multiple": 2.0}));
+        for _ in 0..10 {
+            rules.on_bar("A", &bar(100.0, 101.0, 99.0, 100.0));
+        }
+        let mut tracker = rules.track("A", 1.0, 100.0).unwrap();
+        assert_eq!(
+            tracker.check(&bar(99.0, 99.5, 95.0, 96.0)),
+            Some((ExitReason::StopLoss, 96.0))
+        );
+    }
+
+    #[test]
+    fn test_flat_by_close_picks_the_last_bar_of_the_session() {
+        let rules = rules(json!({ "flat_by_close_minutes": 5 }));
+        let nyse = TradingCalendar::nyse();
+        // The NYSE closes at 21:00 UTC in January
+        let at = |hour, minute| Utc.with_ymd_and_hms(2024, 1, 2, hour, minute, 0).unwrap();
+        assert!(!rules.is_closing_bar(at(20, 53), 60.0, &nyse));
+        assert!(rules.is_closing_bar(at(20, 54), 60.0, &nyse));
+        assert!(!rules.is_closing_bar(at(21, 30), 60.0, &nyse));
+        assert!(!ExitRules::default().is_closing_bar(at(20, 59), 60.0, &nyse));
+    }
+
+    #[test]
+    fn test_invalid_exits_rejected() {
+        for exits in [
+            json!(0.05),
+            json!({"stop_loss_pct": 1.5}),
+            json!({"take_profit_pct": -0.1}),
+            json!({"atr_period": 0, "atr_stop_multiple": 2.0}),
+            json!({"flat_by_close_minutes": -5}),
+        ] {
+            assert!(ExitRules::validate(&json!({ "exits": exits })).is_err());
+        }
+    }
+}
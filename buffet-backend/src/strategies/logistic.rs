@@ -0,0 +1,412 @@
+use crate::actors::messages::SignalType;
+use crate::actors::strategy::{StrategyLogic, StrategySignal};
+use crate::models::market_data::OHLCV;
+use crate::models::strategy::StrategyType;
+use crate::strategies::{
+    StrategyDefinition, optional_number, optional_positive_int, param_f64, param_usize,
+};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use ta::Next;
+use ta::indicators::RelativeStrengthIndex;
+
+pub fn definition() -> StrategyDefinition {
+    StrategyDefinition {
+        kind: "logistic_regression",
+        strategy_type: StrategyType::MLBased,
+        is_default: true,
+        description: "Online logistic regression predicting next-bar direction",
+        parameter_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "lags": { "type": "integer", "minimum": 1 },
+                    "rsi_period": { "type": "integer", "minimum": 1 },
+                    "volatility_window": { "type": "integer", "minimum": 2 },
+                    "train_window": { "type": "integer", "minimum": 2 },
+                    "min_train_samples": { "type": "integer", "minimum": 2 },
+                    "learning_rate": { "type": "number", "exclusiveMinimum": 0 },
+                    "epochs": { "type": "integer", "minimum": 1 },
+                    "l2": { "type": "number", "minimum": 0 },
+                    "threshold": { "type": "number", "exclusiveMinimum": 0.5, "exclusiveMaximum": 1 }
+                }
+            })
+        },
+        default_parameters: || {
+            json!({
+                "lags": 3,
+                "rsi_period": 14,
+                "volatility_window": 20,
+                "train_window": 250,
+                "min_train_samples": 50,
+                "learning_rate": 0.1,
+                "epochs": 20,
+                "l2": 0.01,
+                "threshold": 0.55
+            })
+        },
+        validate,
+        warmup: |params| {
+            let usize_of = |key: &str| params[key].as_u64().unwrap_or(0) as usize;
+            feature_warmup(
+                usize_of("lags"),
+                usize_of("rsi_period"),
+                usize_of("volatility_window"),
+            ) + usize_of("min_train_samples")
+        },
+        build: |params| Ok(Box::new(LogisticRegressionStrategy::from_params(params)?)),
+        vectorized: None,
+        needs_short: false,
+    }
+}
+
+/// Positive window sizes, `min_train_samples <= train_window`, and a
+/// probability `threshold` strictly between 0.5 and 1.
+fn validate(params: &Value) -> Result<(), String> {
+    for key in ["lags", "rsi_period", "epochs"] {
+        optional_positive_int(params, key)?;
+    }
+    for key in ["volatility_window", "train_window", "min_train_samples"] {
+        if optional_positive_int(params, key)?.is_some_and(|v| v < 2) {
+            return Err(format!("'{}' must be at least 2", key));
+        }
+    }
+
+    let train_window = optional_positive_int(params, "train_window")?.unwrap_or(250);
+    let min_train = optional_positive_int(params, "min_train_samples")?.unwrap_or(50);
+    if min_train > train_window {
+        return Err(format!(
+            "'min_train_samples' ({}) cannot exceed 'train_window' ({})",
+            min_train, train_window
+        ));
+    }
+
+    if optional_number(params, "learning_rate")?.is_some_and(|v| v <= 0.0) {
+        return Err("'learning_rate' must be a positive number".to_string());
+    }
+    if optional_number(params, "l2")?.is_some_and(|v| v < 0.0) {
+        return Err("'l2' must be non-negative".to_string());
+    }
+    if optional_number(params, "threshold")?.is_some_and(|v| v <= 0.5 || v >= 1.0) {
+        return Err("'threshold' must be between 0.5 and 1 (exclusive)".to_string());
+    }
+
+    Ok(())
+}
+
+/// Candles needed before the first feature vector can be computed.
+fn feature_warmup(lags: usize, rsi_period: usize, volatility_window: usize) -> usize {
+    lags.max(volatility_window).max(rsi_period) + 1
+}
+
+fn sigmoid(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+/// ML strategy: logistic regression over engineered features, refit on a
+/// rolling window of past bars.
+///
+/// Features at bar `t` are the last `lags` close-to-close returns, a centred
+/// RSI and the rolling return volatility. The label for those features is the
+/// direction of the *next* bar's return, so a sample only joins the training
+/// window once that bar has closed — the model never trains on the future.
+/// Each bar the model is warm-started from its previous coefficients and
+/// refit with a few epochs of L2-regularised gradient descent.
+pub struct LogisticRegressionStrategy {
+    lags: usize,
+    volatility_window: usize,
+    train_window: usize,
+    min_train_samples: usize,
+    learning_rate: f64,
+    epochs: usize,
+    l2: f64,
+    threshold: f64,
+    rsi: RelativeStrengthIndex,
+    rsi_period: usize,
+    bars_seen: usize,
+    last_close: Option<f64>,
+    returns: VecDeque<f64>,
+    /// Features from the previous bar, waiting for this bar's return as label.
+    pending: Option<Vec<f64>>,
+    samples: VecDeque<(Vec<f64>, f64)>,
+    weights: Vec<f64>,
+    bias: f64,
+}
+
+impl LogisticRegressionStrategy {
+    /// Build from JSON parameters (see `definition()` for the full list).
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        let lags = param_usize(params, "lags")?;
+        let rsi_period = param_usize(params, "rsi_period")?;
+        let rsi = RelativeStrengthIndex::new(rsi_period)
+            .map_err(|e| format!("Invalid RSI period {}: {}", rsi_period, e))?;
+
+        Ok(Self {
+            lags,
+            volatility_window: param_usize(params, "volatility_window")?,
+            train_window: param_usize(params, "train_window")?,
+            min_train_samples: param_usize(params, "min_train_samples")?,
+            learning_rate: param_f64(params, "learning_rate")?,
+            epochs: param_usize(params, "epochs")?,
+            l2: param_f64(params, "l2")?,
+            threshold: param_f64(params, "threshold")?,
+            rsi,
+            rsi_period,
+            bars_seen: 0,
+            last_close: None,
+            returns: VecDeque::new(),
+            pending: None,
+            samples: VecDeque::new(),
+            // lagged returns + RSI + volatility
+            weights: vec![0.0; lags + 2],
+            bias: 0.0,
+        })
+    }
+
+    fn feature_names(&self) -> Vec<String> {
+        (1..=self.lags)
+            .map(|lag| format!("return_lag_{}", lag))
+            .chain(["rsi".to_string(), "volatility".to_string()])
+            .collect()
+    }
+
+    /// Feature vector for the most recent bar, once enough history exists.
+    fn features(&self, rsi: f64) -> Option<Vec<f64>> {
+        if self.bars_seen < feature_warmup(self.lags, self.rsi_period, self.volatility_window) {
+            return None;
+        }
+
+        let mut features: Vec<f64> = self.returns.iter().rev().take(self.lags).copied().collect();
+        features.push((rsi - 50.0) / 50.0);
+
+        let window: Vec<f64> = self
+            .returns
+            .iter()
+            .rev()
+            .take(self.volatility_window)
+            .copied()
+            .collect();
+        let n = window.len() as f64;
+        let mean = window.iter().sum::<f64>() / n;
+        let variance = window.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        features.push(variance.sqrt());
+
+        Some(features)
+    }
+
+    /// Per-feature mean and standard deviation over the training window.
+    fn scaling(&self) -> (Vec<f64>, Vec<f64>) {
+        let dims = self.weights.len();
+        let n = self.samples.len() as f64;
+        let mut means = vec![0.0; dims];
+        for (x, _) in &self.samples {
+            for (m, v) in means.iter_mut().zip(x) {
+                *m += v / n;
+            }
+        }
+        let mut stds = vec![0.0; dims];
+        for (x, _) in &self.samples {
+            for ((s, v), m) in stds.iter_mut().zip(x).zip(&means) {
+                *s += (v - m).powi(2) / n;
+            }
+        }
+        let stds = stds
+            .into_iter()
+            .map(|s| if s > 0.0 { s.sqrt() } else { 1.0 })
+            .collect();
+        (means, stds)
+    }
+
+    fn standardize(x: &[f64], means: &[f64], stds: &[f64]) -> Vec<f64> {
+        x.iter()
+            .zip(means)
+            .zip(stds)
+            .map(|((v, m), s)| (v - m) / s)
+            .collect()
+    }
+
+    fn probability(&self, z: &[f64]) -> f64 {
+        let logit: f64 = self.bias + self.weights.iter().zip(z).map(|(w, v)| w * v).sum::<f64>();
+        sigmoid(logit)
+    }
+
+    /// Refit on the rolling window, warm-starting from the current coefficients.
+    fn fit(&mut self, means: &[f64], stds: &[f64]) {
+        let data: Vec<(Vec<f64>, f64)> = self
+            .samples
+            .iter()
+            .map(|(x, y)| (Self::standardize(x, means, stds), *y))
+            .collect();
+        let n = data.len() as f64;
+
+        for _ in 0..self.epochs {
+            let mut grad_w = vec![0.0; self.weights.len()];
+            let mut grad_b = 0.0;
+            for (z, y) in &data {
+                let error = self.probability(z) - y;
+                for (g, v) in grad_w.iter_mut().zip(z) {
+                    *g += error * v / n;
+                }
+                grad_b += error / n;
+            }
+            for (w, g) in self.weights.iter_mut().zip(&grad_w) {
+                *w -= self.learning_rate * (g + self.l2 * *w);
+            }
+            self.bias -= self.learning_rate * grad_b;
+        }
+    }
+
+    /// Advance one bar; returns a signal plus the model state that produced it.
+    fn step(&mut self, data: &OHLCV) -> Option<(SignalType, Value)> {
+        let rsi = self.rsi.next(data.close);
+        self.bars_seen += 1;
+
+        let prev_close = self.last_close.replace(data.close)?;
+        if prev_close <= 0.0 {
+            return None;
+        }
+        let ret = data.close / prev_close - 1.0;
+        self.returns.push_back(ret);
+        if self.returns.len() > self.lags.max(self.volatility_window) {
+            self.returns.pop_front();
+        }
+
+        // The previous bar's features are now labelled by this bar's return
+        if let Some(features) = self.pending.take() {
+            self.samples
+                .push_back((features, if ret > 0.0 { 1.0 } else { 0.0 }));
+            if self.samples.len() > self.train_window {
+                self.samples.pop_front();
+            }
+        }
+
+        let features = self.features(rsi)?;
+        self.pending = Some(features.clone());
+
+        if self.samples.len() < self.min_train_samples {
+            return None;
+        }
+
+        let (means, stds) = self.scaling();
+        self.fit(&means, &stds);
+        let probability_up = self.probability(&Self::standardize(&features, &means, &stds));
+
+        let signal_type = if probability_up >= self.threshold {
+            SignalType::Buy
+        } else if probability_up <= 1.0 - self.threshold {
+            SignalType::Sell
+        } else {
+            return None;
+        };
+
+        let metadata = json!({
+            "model": "logistic_regression",
+            "probability_up": probability_up,
+            "intercept": self.bias,
+            "coefficients": self.weights,
+            "feature_names": self.feature_names(),
+            "features": features,
+            "feature_means": means,
+            "feature_stds": stds,
+            "training_samples": self.samples.len(),
+        });
+
+        Some((signal_type, metadata))
+    }
+}
+
+impl StrategyLogic for LogisticRegressionStrategy {
+    fn update(&mut self, data: &OHLCV) -> Option<SignalType> {
+        self.step(data).map(|(signal_type, _)| signal_type)
+    }
+
+    fn on_bar(&mut self, symbol: &str, data: &OHLCV) -> Vec<StrategySignal> {
+        self.step(data)
+            .map(|(signal_type, metadata)| {
+                StrategySignal::new(symbol, signal_type).with_metadata(metadata)
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn candle(i: i64, close: f64) -> OHLCV {
+        OHLCV::new(
+            Utc.timestamp_opt(i * 60, 0).unwrap(),
+            close,
+            close,
+            close,
+            close,
+            1.0,
+        )
+    }
+
+    fn strategy() -> LogisticRegressionStrategy {
+        let params = json!({
+            "lags": 1,
+            "rsi_period": 3,
+            "volatility_window": 3,
+            "train_window": 40,
+            "min_train_samples": 20,
+            "learning_rate": 0.5,
+            "epochs": 50,
+            "l2": 0.0,
+            "threshold": 0.6
+        });
+        LogisticRegressionStrategy::from_params(&params).unwrap()
+    }
+
+    /// Zig-zag prices: an up bar is always followed by a down bar and vice versa.
+    fn zigzag(i: i64) -> f64 {
+        if i % 2 == 0 { 100.0 } else { 102.0 }
+    }
+
+    #[test]
+    fn test_learns_mean_reverting_direction() {
+        let mut strategy = strategy();
+        let mut last = Vec::new();
+        for i in 0..80 {
+            last = strategy.on_bar("ZZ", &candle(i, zigzag(i)));
+        }
+
+        // Bar 79 closed up (100 → 102), so the model should expect a down bar
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].signal_type, SignalType::Sell);
+
+        let metadata = last[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata["coefficients"].as_array().unwrap().len(), 3);
+        assert!(metadata["coefficients"][0].as_f64().unwrap() < 0.0);
+        assert!(metadata["probability_up"].as_f64().unwrap() < 0.4);
+    }
+
+    #[test]
+    fn test_samples_only_include_closed_labels() {
+        let mut strategy = strategy();
+        for i in 0..10 {
+            strategy.on_bar("ZZ", &candle(i, zigzag(i)));
+        }
+        // Features exist from bar 3 onward; bar 9's features still await a label
+        assert_eq!(strategy.samples.len(), 6);
+        assert!(strategy.pending.is_some());
+    }
+
+    #[test]
+    fn test_silent_until_min_train_samples() {
+        let mut strategy = strategy();
+        for i in 0..23 {
+            assert!(strategy.on_bar("ZZ", &candle(i, zigzag(i))).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_validate_threshold_bounds() {
+        assert!(validate(&json!({})).is_ok());
+        assert!(validate(&json!({"threshold": 0.5})).is_err());
+        assert!(validate(&json!({"min_train_samples": 300, "train_window": 100})).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::actors::messages::SignalType;
+use crate::actors::strategy::StrategyLogic;
+use crate::models::market_data::OHLCV;
+use crate::models::strategy::StrategyType;
+use crate::strategies::{
+    StrategyDefinition, ensure_fast_below_slow, optional_positive_int, param_usize,
+};
+use serde_json::{Value, json};
+use ta::Next;
+use ta::indicators::MovingAverageConvergenceDivergence;
+
+pub fn definition() -> StrategyDefinition {
+    StrategyDefinition {
+        kind: "macd",
+        strategy_type: StrategyType::Classical,
+        is_default: false,
+        description: "Long while the MACD line is above its signal line",
+        parameter_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "fast_period": { "type": "integer", "minimum": 1 },
+                    "slow_period": { "type": "integer", "minimum": 2 },
+                    "signal_period": { "type": "integer", "minimum": 1 }
+                }
+            })
+        },
+        default_parameters: || json!({ "fast_period": 12, "slow_period": 26, "signal_period": 9 }),
+        validate,
+        warmup: |params| {
+            let slow = params["slow_period"].as_u64().unwrap_or(0);
+            let signal = params["signal_period"].as_u64().unwrap_or(0);
+            (slow + signal) as usize
+        },
+        build: |params| Ok(Box::new(MacdCrossover::from_params(params)?)),
+        vectorized: None,
+        needs_short: false,
+    }
+}
+
+/// Optional `fast_period < slow_period` and a positive `signal_period`.
+fn validate(params: &Value) -> Result<(), String> {
+    let fast = optional_positive_int(params, "fast_period")?.unwrap_or(12);
+    let slow = optional_positive_int(params, "slow_period")?.unwrap_or(26);
+    optional_positive_int(params, "signal_period")?;
+
+    ensure_fast_below_slow(fast, slow)
+}
+
+/// MACD signal-line crossover strategy.
+///
+/// Buys while the MACD line is above its signal line and sells while it is
+/// below, once both EMAs and the signal line have warmed up.
+pub struct MacdCrossover {
+    warmup: usize,
+    macd: MovingAverageConvergenceDivergence,
+    bars_seen: usize,
+}
+
+impl MacdCrossover {
+    pub fn new(
+        fast_period: usize,
+        slow_period: usize,
+        signal_period: usize,
+    ) -> Result<Self, String> {
+        let macd = MovingAverageConvergenceDivergence::new(fast_period, slow_period, signal_period)
+            .map_err(|e| {
+                format!(
+                    "Invalid MACD periods ({}, {}, {}): {}",
+                    fast_period, slow_period, signal_period, e
+                )
+            })?;
+        Ok(Self {
+            warmup: slow_period + signal_period,
+            macd,
+            bars_seen: 0,
+        })
+    }
+
+    /// Build from JSON parameters (`fast_period`, `slow_period`, `signal_period`).
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        Self::new(
+            param_usize(params, "fast_period")?,
+            param_usize(params, "slow_period")?,
+            param_usize(params, "signal_period")?,
+        )
+    }
+}
+
+impl StrategyLogic for MacdCrossover {
+    fn update(&mut self, data: &OHLCV) -> Option<SignalType> {
+        let output = self.macd.next(data.close);
+        self.bars_seen += 1;
+
+        if self.bars_seen < self.warmup {
+            return None;
+        }
+
+        if output.macd > output.signal {
+            Some(SignalType::Buy)
+        } else {
+            Some(SignalType::Sell)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn candle(i: i64, close: f64) -> OHLCV {
+        OHLCV::new(
+            Utc.timestamp_opt(i * 60, 0).unwrap(),
+            close,
+            close,
+            close,
+            close,
+            1.0,
+        )
+    }
+
+    #[test]
+    fn test_macd_waits_for_warmup_then_follows_trend() {
+        let mut strategy = MacdCrossover::new(3, 6, 3).unwrap();
+        let signals: Vec<_> = (0..20)
+            .map(|i| strategy.update(&candle(i, 100.0 + (i * i) as f64)))
+            .collect();
+
+        assert!(signals[..8].iter().all(Option::is_none));
+        assert_eq!(signals.last().copied().flatten(), Some(SignalType::Buy));
+    }
+
+    #[test]
+    fn test_macd_sells_in_downtrend() {
+        let mut strategy = MacdCrossover::new(3, 6, 3).unwrap();
+        let last = (0..20)
+            .map(|i| strategy.update(&candle(i, 1000.0 - (i * i) as f64)))
+            .last()
+            .flatten();
+        assert_eq!(last, Some(SignalType::Sell));
+    }
+}
@@ -0,0 +1,77 @@
+//! Concrete `StrategyLogic` implementations and the registry that exposes them.
+//!
+//! Each strategy consumes one candle at a time and emits signals once it has
+//! seen enough history to compute its indicators. Every module exposes
+//! a `definition()` that is registered in `StrategyRegistry::builtin()`.
+//! How much to trade is decided separately by `PositionSizer`, and risk
+//! exits in backtests by `ExitRules`.
+
+pub mod bollinger;
+pub mod exits;
+pub mod logistic;
+pub mod macd;
+pub mod moving_average;
+pub mod pairs;
+pub mod registry;
+pub mod rsi;
+pub mod sizing;
+
+pub use bollinger::{BollingerBandStrategy, BollingerMode};
+pub use exits::{ExitRules, StopTracker};
+pub use logistic::LogisticRegressionStrategy;
+pub use macd::MacdCrossover;
+pub use moving_average::MovingAverageCrossover;
+pub use pairs::PairsTrading;
+pub use registry::{BuiltStrategy, StrategyDefinition, StrategyKindInfo, StrategyRegistry};
+pub use rsi::RsiMeanReversion;
+pub use sizing::{PositionSizer, SizingMethod};
+
+use serde_json::Value;
+
+/// Read an integer parameter that the registry guarantees via defaults.
+pub(crate) fn param_usize(params: &Value, key: &str) -> Result<usize, String> {
+    params[key]
+        .as_u64()
+        .map(|v| v as usize)
+        .ok_or_else(|| format!("'{}' must be a non-negative integer", key))
+}
+
+/// Read a numeric parameter that the registry guarantees via defaults.
+pub(crate) fn param_f64(params: &Value, key: &str) -> Result<f64, String> {
+    params[key]
+        .as_f64()
+        .ok_or_else(|| format!("'{}' must be a number", key))
+}
+
+/// Read an optional positive integer parameter, rejecting any other JSON value.
+pub(crate) fn optional_positive_int(params: &Value, key: &str) -> Result<Option<u64>, String> {
+    match params.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_u64()
+            .filter(|&v| v > 0)
+            .map(Some)
+            .ok_or_else(|| format!("'{}' must be a positive integer", key)),
+    }
+}
+
+/// Read an optional numeric parameter, rejecting any other JSON value.
+pub(crate) fn optional_number(params: &Value, key: &str) -> Result<Option<f64>, String> {
+    match params.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_f64()
+            .map(Some)
+            .ok_or_else(|| format!("'{}' must be a number", key)),
+    }
+}
+
+pub(crate) fn ensure_fast_below_slow(fast: u64, slow: u64) -> Result<(), String> {
+    if fast >= slow {
+        return Err(format!(
+            "'fast_period' ({}) must be less than 'slow_period' ({})",
+            fast, slow
+        ));
+    }
+    Ok(())
+}
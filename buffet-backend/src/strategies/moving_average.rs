@@ -0,0 +1,129 @@
+use crate::actors::messages::SignalType;
+use crate::actors::strategy::StrategyLogic;
+use crate::models::market_data::OHLCV;
+use crate::models::strategy::StrategyType;
+use crate::strategies::{StrategyDefinition, ensure_fast_below_slow, param_usize};
+use polars::prelude::{Duration, Expr, NULL, RollingOptions, col, lit, when};
+use serde_json::{Value, json};
+
+pub fn definition() -> StrategyDefinition {
+    StrategyDefinition {
+        kind: "sma_crossover",
+        strategy_type: StrategyType::Classical,
+        is_default: true,
+        description: "Long while the fast SMA is above the slow SMA",
+        parameter_schema: || {
+            json!({
+                "type": "object",
+                "required": ["fast_period", "slow_period"],
+                "properties": {
+                    "fast_period": { "type": "integer", "minimum": 1 },
+                    "slow_period": { "type": "integer", "minimum": 2 }
+                }
+            })
+        },
+        default_parameters: || json!({ "fast_period": 10, "slow_period": 20 }),
+        validate,
+        warmup: |params| params["slow_period"].as_u64().unwrap_or(0) as usize,
+        build: |params| Ok(Box::new(MovingAverageCrossover::from_params(params)?)),
+        vectorized: Some(vectorized),
+        needs_short: false,
+    }
+}
+
+/// Both periods are required for SMA crossover, with `fast_period < slow_period`.
+fn validate(params: &Value) -> Result<(), String> {
+    let fast = params
+        .get("fast_period")
+        .ok_or_else(|| "Classical strategy requires 'fast_period' parameter".to_string())?;
+    let slow = params
+        .get("slow_period")
+        .ok_or_else(|| "Classical strategy requires 'slow_period' parameter".to_string())?;
+
+    let fast_val = fast
+        .as_u64()
+        .filter(|&v| v > 0)
+        .ok_or_else(|| "'fast_period' must be a positive integer".to_string())?;
+
+    let slow_val = slow
+        .as_u64()
+        .filter(|&v| v > 0)
+        .ok_or_else(|| "'slow_period' must be a positive integer".to_string())?;
+
+    ensure_fast_below_slow(fast_val, slow_val)
+}
+
+/// Long (1.0) while the fast SMA of `close` is above the slow one, flat
+/// (-1.0) otherwise, null until both are defined.
+fn vectorized(params: &Value) -> Result<Expr, String> {
+    let sma = |period: usize| {
+        col("close").rolling_mean(RollingOptions {
+            window_size: Duration::new(period as i64),
+            min_periods: period,
+            ..Default::default()
+        })
+    };
+    let fast = sma(param_usize(params, "fast_period")?);
+    let slow = sma(param_usize(params, "slow_period")?);
+
+    Ok(when(fast.clone().gt(slow.clone()))
+        .then(lit(1.0))
+        .when(fast.lt_eq(slow))
+        .then(lit(-1.0))
+        .otherwise(lit(NULL)))
+}
+
+// Simple Moving Average Crossover Strategy
+pub struct MovingAverageCrossover {
+    fast_period: usize,
+    slow_period: usize,
+    prices: Vec<f64>,
+}
+
+impl MovingAverageCrossover {
+    pub fn new(fast_period: usize, slow_period: usize) -> Self {
+        Self {
+            fast_period,
+            slow_period,
+            prices: Vec::new(),
+        }
+    }
+
+    /// Build from JSON parameters (`fast_period`, `slow_period`).
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        Ok(Self::new(
+            param_usize(params, "fast_period")?,
+            param_usize(params, "slow_period")?,
+        ))
+    }
+
+    fn calculate_sma(&self, period: usize) -> Option<f64> {
+        if self.prices.len() < period {
+            return None;
+        }
+        let sum: f64 = self.prices.iter().rev().take(period).sum();
+        Some(sum / period as f64)
+    }
+}
+
+impl StrategyLogic for MovingAverageCrossover {
+    fn update(&mut self, data: &OHLCV) -> Option<SignalType> {
+        self.prices.push(data.close);
+
+        // Keep only enough history for the slow period
+        if self.prices.len() > self.slow_period + 1 {
+            self.prices.remove(0);
+        }
+
+        let fast_ma = self.calculate_sma(self.fast_period)?;
+        let slow_ma = self.calculate_sma(self.slow_period)?;
+
+        // Target state: long while fast is above slow, flat otherwise.
+        // The executor turns a change of state into the actual crossover order.
+        if fast_ma > slow_ma {
+            Some(SignalType::Buy)
+        } else {
+            Some(SignalType::Sell)
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use crate::actors::messages::SignalType;
+use crate::actors::strategy::{SignalKind, StrategyLogic, StrategySignal};
+use crate::models::market_data::OHLCV;
+use crate::models::strategy::StrategyType;
+use crate::strategies::{
+    StrategyDefinition, optional_number, optional_positive_int, param_f64, param_usize,
+};
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+
+pub fn definition() -> StrategyDefinition {
+    StrategyDefinition {
+        kind: "pairs",
+        strategy_type: StrategyType::Statistical,
+        is_default: true,
+        description: "Trade the z-score of a rolling OLS spread between two symbols",
+        parameter_schema: || {
+            json!({
+                "type": "object",
+                "required": ["symbol_a", "symbol_b"],
+                "properties": {
+                    "symbol_a": { "type": "string" },
+                    "symbol_b": { "type": "string" },
+                    "lookback": { "type": "integer", "minimum": 3 },
+                    "entry_z": { "type": "number", "exclusiveMinimum": 0 },
+                    "exit_z": { "type": "number", "minimum": 0 }
+                }
+            })
+        },
+        default_parameters: || json!({ "lookback": 60, "entry_z": 2.0, "exit_z": 0.5 }),
+        validate,
+        warmup: |params| params["lookback"].as_u64().unwrap_or(0) as usize,
+        build: |params| Ok(Box::new(PairsTrading::from_params(params)?)),
+        vectorized: None,
+        needs_short: true,
+    }
+}
+
+/// Two distinct leg symbols, `lookback >= 3` and `entry_z > exit_z >= 0`.
+fn validate(params: &Value) -> Result<(), String> {
+    let leg = |key: &str| {
+        params
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| format!("Pairs strategy requires '{}' as a non-empty string", key))
+    };
+    let symbol_a = leg("symbol_a")?;
+    let symbol_b = leg("symbol_b")?;
+    if symbol_a == symbol_b {
+        return Err("'symbol_a' and 'symbol_b' must be different symbols".to_string());
+    }
+
+    if optional_positive_int(params, "lookback")?.is_some_and(|v| v < 3) {
+        return Err("'lookback' must be at least 3".to_string());
+    }
+
+    let entry_z = optional_number(params, "entry_z")?.unwrap_or(2.0);
+    let exit_z = optional_number(params, "exit_z")?.unwrap_or(0.5);
+    if exit_z < 0.0 || entry_z <= exit_z {
+        return Err(format!(
+            "'exit_z' ({}) must be non-negative and less than 'entry_z' ({})",
+            exit_z, entry_z
+        ));
+    }
+
+    Ok(())
+}
+
+/// Which side of the spread the strategy currently holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpreadPosition {
+    Flat,
+    /// Long `symbol_a`, short `symbol_b` — entered when the spread is cheap.
+    Long,
+    /// Short `symbol_a`, long `symbol_b` — entered when the spread is rich.
+    Short,
+}
+
+/// Statistical pairs-trading strategy.
+///
+/// Regresses `symbol_a` on `symbol_b` over a rolling window to estimate the
+/// hedge ratio, then trades the z-score of the residual spread: it opens the
+/// spread when |z| exceeds `entry_z` and closes it once |z| falls back inside
+/// `exit_z`. Bars for the two legs are paired by timestamp.
+///
+/// Entries hold `beta` units of `symbol_b` for every unit of `symbol_a`, so
+/// the `symbol_b` signal carries the hedge ratio rather than being sized on
+/// its own.
+pub struct PairsTrading {
+    symbol_a: String,
+    symbol_b: String,
+    lookback: usize,
+    entry_z: f64,
+    exit_z: f64,
+    last_a: Option<(DateTime<Utc>, f64)>,
+    last_b: Option<(DateTime<Utc>, f64)>,
+    prices_a: VecDeque<f64>,
+    prices_b: VecDeque<f64>,
+    position: SpreadPosition,
+    /// Sides each leg was opened on, which the exit reverses.
+    open_legs: Option<(SignalType, SignalType)>,
+}
+
+impl PairsTrading {
+    pub fn new(
+        symbol_a: String,
+        symbol_b: String,
+        lookback: usize,
+        entry_z: f64,
+        exit_z: f64,
+    ) -> Result<Self, String> {
+        if lookback < 3 {
+            return Err(format!(
+                "Pairs lookback must be at least 3, got {}",
+                lookback
+            ));
+        }
+        Ok(Self {
+            symbol_a,
+            symbol_b,
+            lookback,
+            entry_z,
+            exit_z,
+            last_a: None,
+            last_b: None,
+            prices_a: VecDeque::with_capacity(lookback + 1),
+            prices_b: VecDeque::with_capacity(lookback + 1),
+            position: SpreadPosition::Flat,
+            open_legs: None,
+        })
+    }
+
+    /// Build from JSON parameters (`symbol_a`, `symbol_b`, `lookback`, `entry_z`, `exit_z`).
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        let leg = |key: &str| {
+            params[key]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| format!("'{}' must be a string", key))
+        };
+        Self::new(
+            leg("symbol_a")?,
+            leg("symbol_b")?,
+            param_usize(params, "lookback")?,
+            param_f64(params, "entry_z")?,
+            param_f64(params, "exit_z")?,
+        )
+    }
+
+    /// Current spread z-score and hedge ratio, once a full window of paired
+    /// prices is available.
+    fn z_score(&self) -> Option<(f64, f64)> {
+        if self.prices_a.len() < self.lookback {
+            return None;
+        }
+        let (alpha, beta) = ols(&self.prices_a, &self.prices_b)?;
+
+        let spreads: Vec<f64> = self
+            .prices_a
+            .iter()
+            .zip(&self.prices_b)
+            .map(|(a, b)| a - beta * b - alpha)
+            .collect();
+        let n = spreads.len() as f64;
+        let mean = spreads.iter().sum::<f64>() / n;
+        let std_dev = (spreads.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+        if std_dev == 0.0 {
+            return None;
+        }
+
+        Some(((spreads[spreads.len() - 1] - mean) / std_dev, beta))
+    }
+
+    /// Open `symbol_a` on side `a`, hedged with `|beta|` units of `symbol_b`
+    /// per unit: on the other side when the legs move together, on the
+    /// same side when they move against each other.
+    fn open(&mut self, a: SignalType, beta: f64) -> Vec<StrategySignal> {
+        let b = if beta > 0.0 { reverse(a) } else { a };
+        self.open_legs = Some((a, b));
+        vec![
+            StrategySignal::new(&self.symbol_a, a),
+            StrategySignal::new(&self.symbol_b, b).hedging(&self.symbol_a, beta.abs()),
+        ]
+    }
+
+    /// Reverse both legs as they were opened.
+    fn close(&mut self) -> Vec<StrategySignal> {
+        let Some((a, b)) = self.open_legs.take() else {
+            return Vec::new();
+        };
+        vec![
+            StrategySignal::new(&self.symbol_a, reverse(a)),
+            StrategySignal::new(&self.symbol_b, reverse(b)),
+        ]
+    }
+}
+
+fn reverse(side: SignalType) -> SignalType {
+    match side {
+        SignalType::Buy => SignalType::Sell,
+        SignalType::Sell => SignalType::Buy,
+        SignalType::Hold => SignalType::Hold,
+    }
+}
+
+/// Ordinary least squares fit of `y = alpha + beta * x`.
+fn ols(y: &VecDeque<f64>, x: &VecDeque<f64>) -> Option<(f64, f64)> {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    let cov: f64 = x
+        .iter()
+        .zip(y)
+        .map(|(xi, yi)| (xi - mean_x) * (yi - mean_y))
+        .sum();
+    let var: f64 = x.iter().map(|xi| (xi - mean_x).powi(2)).sum();
+    if var == 0.0 {
+        return None;
+    }
+    let beta = cov / var;
+    Some((mean_y - beta * mean_x, beta))
+}
+
+impl StrategyLogic for PairsTrading {
+    fn on_bar(&mut self, symbol: &str, data: &OHLCV) -> Vec<StrategySignal> {
+        if symbol == self.symbol_a {
+            self.last_a = Some((data.timestamp, data.close));
+        } else if symbol == self.symbol_b {
+            self.last_b = Some((data.timestamp, data.close));
+        } else {
+            return Vec::new();
+        }
+
+        // Only act once both legs have printed a bar for the same timestamp
+        let (Some((ts_a, price_a)), Some((ts_b, price_b))) = (self.last_a, self.last_b) else {
+            return Vec::new();
+        };
+        if ts_a != ts_b {
+            return Vec::new();
+        }
+        self.last_a = None;
+        self.last_b = None;
+
+        self.prices_a.push_back(price_a);
+        self.prices_b.push_back(price_b);
+        if self.prices_a.len() > self.lookback {
+            self.prices_a.pop_front();
+            self.prices_b.pop_front();
+        }
+
+        let Some((z, beta)) = self.z_score() else {
+            return Vec::new();
+        };
+
+        match self.position {
+            SpreadPosition::Flat if z > self.entry_z => {
+                self.position = SpreadPosition::Short;
+                self.open(SignalType::Sell, beta)
+            }
+            SpreadPosition::Flat if z < -self.entry_z => {
+                self.position = SpreadPosition::Long;
+                self.open(SignalType::Buy, beta)
+            }
+            SpreadPosition::Long if z >= -self.exit_z => {
+                self.position = SpreadPosition::Flat;
+                self.close()
+            }
+            SpreadPosition::Short if z <= self.exit_z => {
+                self.position = SpreadPosition::Flat;
+                self.close()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        vec![self.symbol_a.clone(), self.symbol_b.clone()]
+    }
+
+    /// Entries and exits are transitions of the spread state machine.
+    fn signal_kind(&self) -> SignalKind {
+        SignalKind::Event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn candle(i: i64, close: f64) -> OHLCV {
+        OHLCV::new(
+            Utc.timestamp_opt(i * 60, 0).unwrap(),
+            close,
+            close,
+            close,
+            close,
+            1.0,
+        )
+    }
+
+    fn feed(strategy: &mut PairsTrading, i: i64, a: f64, b: f64) -> Vec<StrategySignal> {
+        let mut signals = strategy.on_bar("AAA", &candle(i, a));
+        signals.extend(strategy.on_bar("BBB", &candle(i, b)));
+        signals
+    }
+
+    /// Leg B oscillates; leg A tracks 2x leg B with a small alternating residual.
+    fn warm_up(strategy: &mut PairsTrading, bars: i64) {
+        for i in 0..bars {
+            let b = 50.0 + (i % 7) as f64;
+            let noise = if i % 2 == 0 { 0.1 } else { -0.1 };
+            assert!(feed(strategy, i, 2.0 * b + noise, b).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_opens_and_closes_spread_on_z_thresholds() {
+        let mut strategy = PairsTrading::new("AAA".into(), "BBB".into(), 20, 2.0, 0.5).unwrap();
+        warm_up(&mut strategy, 20);
+
+        // Leg A jumps far above its hedge value: spread is rich → short A, long B
+        let open = feed(&mut strategy, 20, 2.0 * 53.0 + 5.0, 53.0);
+        assert_eq!(open.len(), 2);
+        assert_eq!(open[0].symbol, "AAA");
+        assert_eq!(open[0].signal_type, SignalType::Sell);
+        assert_eq!(open[1].symbol, "BBB");
+        assert_eq!(open[1].signal_type, SignalType::Buy);
+        // Leg B is hedged at the fitted ratio of about 2 units per unit of A
+        assert_eq!(open[0].hedge, None);
+        let hedge = open[1].hedge.as_ref().expect("hedge leg");
+        assert_eq!(hedge.symbol, "AAA");
+        assert!((hedge.ratio - 2.0).abs() < 0.1, "ratio {}", hedge.ratio);
+
+        // Spread reverts → close both legs
+        let mut closed = Vec::new();
+        for i in 21..40 {
+            closed = feed(&mut strategy, i, 2.0 * 52.0, 52.0);
+            if !closed.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[0].signal_type, SignalType::Buy);
+        assert_eq!(closed[1].signal_type, SignalType::Sell);
+        assert!(closed.iter().all(|s| s.hedge.is_none()));
+    }
+
+    #[test]
+    fn test_inversely_related_legs_open_on_the_same_side() {
+        let mut strategy = PairsTrading::new("AAA".into(), "BBB".into(), 20, 2.0, 0.5).unwrap();
+        for i in 0..20 {
+            let b = 50.0 + (i % 7) as f64;
+            let noise = if i % 2 == 0 { 0.1 } else { -0.1 };
+            assert!(feed(&mut strategy, i, 200.0 - 2.0 * b + noise, b).is_empty());
+        }
+
+        let open = feed(&mut strategy, 20, 200.0 - 2.0 * 53.0 + 5.0, 53.0);
+        assert_eq!(open[0].signal_type, SignalType::Sell);
+        assert_eq!(open[1].signal_type, SignalType::Sell);
+        assert!((open[1].hedge.as_ref().unwrap().ratio - 2.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_ignores_unrelated_symbols_and_unpaired_bars() {
+        let mut strategy = PairsTrading::new("AAA".into(), "BBB".into(), 3, 2.0, 0.5).unwrap();
+        assert!(strategy.on_bar("CCC", &candle(0, 10.0)).is_empty());
+        assert!(strategy.on_bar("AAA", &candle(0, 10.0)).is_empty());
+        assert!(strategy.on_bar("BBB", &candle(1, 5.0)).is_empty());
+        assert!(strategy.prices_a.is_empty());
+    }
+
+    #[test]
+    fn test_validate_rejects_same_leg_twice() {
+        let err = validate(&json!({"symbol_a": "AAA", "symbol_b": "AAA"})).unwrap_err();
+        assert!(err.contains("different"));
+        assert!(validate(&json!({"symbol_a": "AAA", "symbol_b": "BBB"})).is_ok());
+    }
+}
@@ -0,0 +1,288 @@
+//! Single source of truth for every strategy kind the platform can run.
+//!
+//! Live execution (`StrategyExecutorActor`), backtests (`BacktestActor`) and
+//! parameter validation all resolve strategies through `StrategyRegistry`, so a
+//! new strategy only needs a `StrategyDefinition` registered in `builtin()`.
+
+use super::exits::ExitRules;
+use super::sizing::PositionSizer;
+use crate::actors::strategy::StrategyLogic;
+use crate::models::strategy::StrategyType;
+use polars::prelude::Expr;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+/// Builds a strategy's signal series as one Polars expression.
+pub type SignalExpr = fn(&Value) -> Result<Expr, String>;
+
+/// Everything the registry needs to know about one strategy kind.
+pub struct StrategyDefinition {
+    /// Unique name matched against the `kind` parameter (e.g. `"rsi"`).
+    pub kind: &'static str,
+    /// Strategy family this kind belongs to.
+    pub strategy_type: StrategyType,
+    /// Used when a strategy of `strategy_type` does not specify a `kind`.
+    pub is_default: bool,
+    pub description: &'static str,
+    /// JSON schema describing the accepted parameters.
+    pub parameter_schema: fn() -> Value,
+    /// Parameters applied underneath the user's values before building.
+    pub default_parameters: fn() -> Value,
+    /// Validate user-supplied parameters (before defaults are applied).
+    pub validate: fn(&Value) -> Result<(), String>,
+    /// Number of candles needed before the strategy can emit a signal.
+    pub warmup: fn(&Value) -> usize,
+    /// Construct the strategy from parameters with defaults applied.
+    pub build: fn(&Value) -> Result<Box<dyn StrategyLogic>, String>,
+    /// The same signals as one Polars expression over a whole series, for
+    /// the vectorized engine (see [`crate::utils::vectorized`]); `None` for
+    /// strategies only the event loop can run.
+    pub vectorized: Option<SignalExpr>,
+    /// Entries sell short (e.g. one leg of a pair), so backtests must allow it.
+    pub needs_short: bool,
+}
+
+/// A strategy instance resolved and constructed through the registry.
+pub struct BuiltStrategy {
+    pub kind: &'static str,
+    /// Parameters with defaults applied, as used to build `logic`.
+    pub parameters: Value,
+    pub warmup: usize,
+    pub logic: Box<dyn StrategyLogic>,
+    /// Sizing policy from the `sizing` parameter block.
+    pub sizer: PositionSizer,
+    /// Risk exits from the `exits` parameter block.
+    pub exits: ExitRules,
+    /// Signal expression for the vectorized engine, if the kind has one.
+    pub vectorized: Option<Expr>,
+    pub needs_short: bool,
+}
+
+/// Public description of a registered strategy kind, served by the API.
+#[derive(Debug, Serialize)]
+pub struct StrategyKindInfo {
+    pub kind: &'static str,
+    pub strategy_type: String,
+    pub is_default: bool,
+    pub description: &'static str,
+    pub parameter_schema: Value,
+    pub default_parameters: Value,
+}
+
+#[derive(Default)]
+pub struct StrategyRegistry {
+    definitions: Vec<StrategyDefinition>,
+}
+
+impl StrategyRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry containing every strategy shipped with the backend.
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(super::moving_average::definition());
+        registry.register(super::rsi::definition());
+        registry.register(super::bollinger::definition());
+        registry.register(super::macd::definition());
+        registry.register(super::pairs::definition());
+        registry.register(super::logistic::definition());
+        registry
+    }
+
+    /// Process-wide registry shared by all actors and handlers.
+    pub fn global() -> &'static StrategyRegistry {
+        static REGISTRY: OnceLock<StrategyRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(Self::builtin)
+    }
+
+    /// Add a definition. Panics if the kind is already registered, since that
+    /// is a programming error rather than a runtime condition.
+    pub fn register(&mut self, definition: StrategyDefinition) {
+        assert!(
+            self.get(definition.kind).is_none(),
+            "strategy kind '{}' registered twice",
+            definition.kind
+        );
+        self.definitions.push(definition);
+    }
+
+    pub fn get(&self, kind: &str) -> Option<&StrategyDefinition> {
+        self.definitions.iter().find(|d| d.kind == kind)
+    }
+
+    pub fn kinds(&self) -> Vec<StrategyKindInfo> {
+        self.definitions
+            .iter()
+            .map(|d| StrategyKindInfo {
+                kind: d.kind,
+                strategy_type: d.strategy_type.to_string(),
+                is_default: d.is_default,
+                description: d.description,
+                parameter_schema: (d.parameter_schema)(),
+                default_parameters: (d.default_parameters)(),
+            })
+            .collect()
+    }
+
+    /// Find the definition for `params["kind"]`, or the default kind for
+    /// `strategy_type` when no kind is given.
+    pub fn resolve(
+        &self,
+        strategy_type: &StrategyType,
+        params: &Value,
+    ) -> Result<&StrategyDefinition, String> {
+        let definition = match params.get("kind") {
+            None | Some(Value::Null) => self
+                .definitions
+                .iter()
+                .find(|d| d.strategy_type == *strategy_type && d.is_default)
+                .ok_or_else(|| {
+                    format!(
+                        "No default strategy registered for type '{}'",
+                        strategy_type
+                    )
+                })?,
+            Some(kind) => {
+                let kind = kind
+                    .as_str()
+                    .ok_or_else(|| "'kind' must be a string".to_string())?;
+                self.get(kind)
+                    .ok_or_else(|| format!("Unknown strategy kind: {}", kind))?
+            }
+        };
+
+        if definition.strategy_type != *strategy_type {
+            return Err(format!(
+                "Strategy kind '{}' is a {} strategy, not {}",
+                definition.kind, definition.strategy_type, strategy_type
+            ));
+        }
+
+        Ok(definition)
+    }
+
+    /// Validate user-supplied parameters for a strategy type.
+    ///
+    /// Parameters without a `kind` for a type that has no registered
+    /// strategies are accepted as-is.
+    pub fn validate(&self, strategy_type: &StrategyType, params: &Value) -> Result<(), String> {
+        let has_kind = !matches!(params.get("kind"), None | Some(Value::Null));
+        let type_registered = self
+            .definitions
+            .iter()
+            .any(|d| d.strategy_type == *strategy_type);
+        if !has_kind && !type_registered {
+            return Ok(());
+        }
+        let definition = self.resolve(strategy_type, params)?;
+        (definition.validate)(params)?;
+        PositionSizer::validate(params)?;
+        ExitRules::validate(params)
+    }
+
+    /// Resolve and construct a strategy from the strings stored on a `Strategy` row.
+    pub fn build(&self, strategy_type: &str, parameters: &str) -> Result<BuiltStrategy, String> {
+        let strategy_type = strategy_type.parse::<StrategyType>()?;
+        let params: Value = serde_json::from_str(parameters)
+            .map_err(|e| format!("Invalid strategy parameters: {}", e))?;
+
+        let definition = self.resolve(&strategy_type, &params)?;
+        let parameters = with_defaults((definition.default_parameters)(), &params, definition.kind);
+
+        Ok(BuiltStrategy {
+            kind: definition.kind,
+            warmup: (definition.warmup)(&parameters),
+            logic: (definition.build)(&parameters)?,
+            sizer: PositionSizer::from_params(&parameters)?,
+            exits: ExitRules::from_params(&parameters)?,
+            vectorized: definition
+                .vectorized
+                .map(|vectorized| vectorized(&parameters))
+                .transpose()?,
+            needs_short: definition.needs_short,
+            parameters,
+        })
+    }
+}
+
+/// Overlay `params` on top of `defaults` (shallow, per top-level key) and pin `kind`.
+fn with_defaults(mut defaults: Value, params: &Value, kind: &str) -> Value {
+    if let (Some(merged), Some(overrides)) = (defaults.as_object_mut(), params.as_object()) {
+        for (key, value) in overrides {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    if let Some(merged) = defaults.as_object_mut() {
+        merged.insert("kind".to_string(), Value::String(kind.to_string()));
+    }
+    defaults
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_builtin_kinds_are_unique_and_buildable_from_defaults() {
+        let registry = StrategyRegistry::builtin();
+        for info in registry.kinds() {
+            // Extra keys are ignored by strategies that don't use them
+            let params = json!({"kind": info.kind, "symbol_a": "AAA", "symbol_b": "BBB"});
+            let built = registry
+                .build(&info.strategy_type, &params.to_string())
+                .unwrap_or_else(|e| panic!("{} failed to build: {}", info.kind, e));
+            assert_eq!(built.kind, info.kind);
+            assert!(built.warmup > 0);
+        }
+    }
+
+    #[test]
+    fn test_missing_kind_resolves_to_type_default() {
+        let registry = StrategyRegistry::builtin();
+        let built = registry
+            .build("classical", r#"{"fast_period": 3, "slow_period": 7}"#)
+            .unwrap();
+        assert_eq!(built.kind, "sma_crossover");
+        assert_eq!(built.warmup, 7);
+    }
+
+    #[test]
+    fn test_defaults_fill_missing_parameters() {
+        let registry = StrategyRegistry::builtin();
+        let built = registry
+            .build("classical", r#"{"kind": "rsi", "period": 5}"#)
+            .unwrap();
+        assert_eq!(built.parameters["period"], 5);
+        assert_eq!(built.parameters["oversold"], 30.0);
+        assert_eq!(built.warmup, 6);
+    }
+
+    #[test]
+    fn test_kind_must_match_strategy_type() {
+        let registry = StrategyRegistry::builtin();
+        let err = registry
+            .resolve(&StrategyType::MLBased, &json!({"kind": "macd"}))
+            .err()
+            .unwrap();
+        assert!(err.contains("not ml_based"));
+    }
+
+    #[test]
+    fn test_sizing_block_is_validated_and_built() {
+        let registry = StrategyRegistry::builtin();
+        let bad = json!({"kind": "rsi", "sizing": {"method": "martingale"}});
+        assert!(registry.validate(&StrategyType::Classical, &bad).is_err());
+
+        let built = registry
+            .build(
+                "classical",
+                r#"{"kind": "rsi", "sizing": {"method": "fixed_notional", "notional": 500}}"#,
+            )
+            .unwrap();
+        assert_eq!(built.sizer.quantity("BTC", 0.0, 100.0), 5.0);
+    }
+}
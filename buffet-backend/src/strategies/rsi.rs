@@ -0,0 +1,157 @@
+use crate::actors::messages::SignalType;
+use crate::actors::strategy::StrategyLogic;
+use crate::models::market_data::OHLCV;
+use crate::models::strategy::StrategyType;
+use crate::strategies::{
+    StrategyDefinition, optional_number, optional_positive_int, param_f64, param_usize,
+};
+use serde_json::{Value, json};
+use ta::Next;
+use ta::indicators::RelativeStrengthIndex;
+
+pub fn definition() -> StrategyDefinition {
+    StrategyDefinition {
+        kind: "rsi",
+        strategy_type: StrategyType::Classical,
+        is_default: false,
+        description: "Buy when RSI is oversold, sell when it is overbought",
+        parameter_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "period": { "type": "integer", "minimum": 1 },
+                    "oversold": { "type": "number", "minimum": 0, "maximum": 100 },
+                    "overbought": { "type": "number", "minimum": 0, "maximum": 100 }
+                }
+            })
+        },
+        default_parameters: || json!({ "period": 14, "oversold": 30.0, "overbought": 70.0 }),
+        validate,
+        warmup: |params| params["period"].as_u64().unwrap_or(0) as usize + 1,
+        build: |params| Ok(Box::new(RsiMeanReversion::from_params(params)?)),
+        vectorized: None,
+        needs_short: false,
+    }
+}
+
+/// Optional `period`, and `oversold < overbought` within 0..100.
+fn validate(params: &Value) -> Result<(), String> {
+    optional_positive_int(params, "period")?;
+    let oversold = optional_number(params, "oversold")?.unwrap_or(30.0);
+    let overbought = optional_number(params, "overbought")?.unwrap_or(70.0);
+
+    if !(0.0..=100.0).contains(&oversold) || !(0.0..=100.0).contains(&overbought) {
+        return Err("'oversold' and 'overbought' must be between 0 and 100".to_string());
+    }
+    if oversold >= overbought {
+        return Err(format!(
+            "'oversold' ({}) must be less than 'overbought' ({})",
+            oversold, overbought
+        ));
+    }
+
+    Ok(())
+}
+
+/// RSI mean-reversion strategy.
+///
+/// Buys when the RSI drops below `oversold` and sells when it rises above
+/// `overbought`. Emits nothing while the RSI sits between the two thresholds.
+pub struct RsiMeanReversion {
+    period: usize,
+    oversold: f64,
+    overbought: f64,
+    rsi: RelativeStrengthIndex,
+    bars_seen: usize,
+}
+
+impl RsiMeanReversion {
+    pub fn new(period: usize, oversold: f64, overbought: f64) -> Result<Self, String> {
+        let rsi = RelativeStrengthIndex::new(period)
+            .map_err(|e| format!("Invalid RSI period {}: {}", period, e))?;
+        Ok(Self {
+            period,
+            oversold,
+            overbought,
+            rsi,
+            bars_seen: 0,
+        })
+    }
+
+    /// Build from JSON parameters (`period`, `oversold`, `overbought`).
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        Self::new(
+            param_usize(params, "period")?,
+            param_f64(params, "oversold")?,
+            param_f64(params, "overbought")?,
+        )
+    }
+}
+
+impl StrategyLogic for RsiMeanReversion {
+    fn update(&mut self, data: &OHLCV) -> Option<SignalType> {
+        let rsi = self.rsi.next(data.close);
+        self.bars_seen += 1;
+
+        // The RSI is seeded on the first bar, so wait for a full period of price changes
+        if self.bars_seen <= self.period {
+            return None;
+        }
+
+        if rsi < self.oversold {
+            Some(SignalType::Buy)
+        } else if rsi > self.overbought {
+            Some(SignalType::Sell)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn candle(i: i64, close: f64) -> OHLCV {
+        OHLCV::new(
+            Utc.timestamp_opt(i * 60, 0).unwrap(),
+            close,
+            close,
+            close,
+            close,
+            1.0,
+        )
+    }
+
+    #[test]
+    fn test_rsi_waits_for_warmup() {
+        let mut strategy = RsiMeanReversion::new(3, 30.0, 70.0).unwrap();
+        for i in 0..3 {
+            assert_eq!(strategy.update(&candle(i, 100.0 - i as f64)), None);
+        }
+    }
+
+    #[test]
+    fn test_rsi_buys_when_oversold() {
+        let mut strategy = RsiMeanReversion::new(3, 30.0, 70.0).unwrap();
+        let signals: Vec<_> = (0..8)
+            .map(|i| strategy.update(&candle(i, 100.0 - 2.0 * i as f64)))
+            .collect();
+        assert_eq!(signals.last().copied().flatten(), Some(SignalType::Buy));
+    }
+
+    #[test]
+    fn test_rsi_sells_when_overbought() {
+        let mut strategy = RsiMeanReversion::new(3, 30.0, 70.0).unwrap();
+        let signals: Vec<_> = (0..8)
+            .map(|i| strategy.update(&candle(i, 100.0 + 2.0 * i as f64)))
+            .collect();
+        assert_eq!(signals.last().copied().flatten(), Some(SignalType::Sell));
+    }
+
+    #[test]
+    fn test_rsi_rejects_zero_period() {
+        assert!(RsiMeanReversion::new(0, 30.0, 70.0).is_err());
+    }
+}
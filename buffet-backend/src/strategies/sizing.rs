@@ -0,0 +1,404 @@
+//! Position sizing shared by live execution and backtests.
+//!
+//! A strategy's sizing policy lives under the `sizing` key of its
+//! `parameters`, e.g. `{"sizing": {"method": "percent_equity", "fraction": 0.25}}`.
+//! Without one, every entry trades a single unit, live and in backtests,
+//! and backtests record it as the `sizing` of their `run_config`.
+//!
+//! Equity-based methods size live orders against the fixed `capital`
+//! allocated to the strategy. Backtests size against the run's running
+//! equity instead, grossed up by its margin model's leverage
+//! (`equity / initial_margin`), so the two only agree for an unlevered run
+//! that has not yet gained or lost.
+
+use crate::models::market_data::OHLCV;
+use serde_json::{Value, json};
+use std::collections::{HashMap, VecDeque};
+use ta::Next;
+use ta::indicators::AverageTrueRange;
+
+/// How many units to trade when a strategy opens a position.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SizingMethod {
+    /// A constant number of units.
+    FixedQuantity { quantity: f64 },
+    /// A constant cash amount, converted to units at the entry price.
+    FixedNotional { notional: f64 },
+    /// A fraction of current equity.
+    PercentEquity { fraction: f64 },
+    /// Risk `risk_fraction` of equity on a move of `atr_multiple` ATRs.
+    VolatilityTarget {
+        risk_fraction: f64,
+        atr_period: usize,
+        atr_multiple: f64,
+    },
+    /// A fraction of the Kelly bet estimated from the last `lookback` trades.
+    /// Until `min_trades` have closed, `initial_fraction` of equity is used.
+    Kelly {
+        fraction: f64,
+        lookback: usize,
+        min_trades: usize,
+        initial_fraction: f64,
+    },
+}
+
+impl SizingMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SizingMethod::FixedQuantity { .. } => "fixed_quantity",
+            SizingMethod::FixedNotional { .. } => "fixed_notional",
+            SizingMethod::PercentEquity { .. } => "percent_equity",
+            SizingMethod::VolatilityTarget { .. } => "volatility_target",
+            SizingMethod::Kelly { .. } => "kelly",
+        }
+    }
+
+    fn uses_equity(&self) -> bool {
+        !matches!(
+            self,
+            SizingMethod::FixedQuantity { .. } | SizingMethod::FixedNotional { .. }
+        )
+    }
+}
+
+/// Stateful sizer: tracks ATR per symbol and recent trade returns for Kelly.
+pub struct PositionSizer {
+    method: SizingMethod,
+    /// Equity allocated to the strategy in live trading. Backtests size
+    /// against their own running equity instead.
+    capital: Option<f64>,
+    atr: HashMap<String, (AverageTrueRange, f64)>,
+    trade_returns: VecDeque<f64>,
+}
+
+fn positive(config: &Value, key: &str, default: f64) -> Result<f64, String> {
+    match config.get(key) {
+        None => Ok(default),
+        Some(v) => v
+            .as_f64()
+            .filter(|v| *v > 0.0)
+            .ok_or_else(|| format!("'sizing.{}' must be a positive number", key)),
+    }
+}
+
+fn fraction(config: &Value, key: &str, default: f64) -> Result<f64, String> {
+    let value = positive(config, key, default)?;
+    if value > 1.0 {
+        return Err(format!("'sizing.{}' must be at most 1", key));
+    }
+    Ok(value)
+}
+
+fn count(config: &Value, key: &str, default: usize) -> Result<usize, String> {
+    match config.get(key) {
+        None => Ok(default),
+        Some(v) => v
+            .as_u64()
+            .filter(|v| *v > 0)
+            .map(|v| v as usize)
+            .ok_or_else(|| format!("'sizing.{}' must be a positive integer", key)),
+    }
+}
+
+impl Default for PositionSizer {
+    /// One unit per entry.
+    fn default() -> Self {
+        Self::new(SizingMethod::FixedQuantity { quantity: 1.0 }, None)
+    }
+}
+
+impl PositionSizer {
+    pub fn new(method: SizingMethod, capital: Option<f64>) -> Self {
+        Self {
+            method,
+            capital,
+            atr: HashMap::new(),
+            trade_returns: VecDeque::new(),
+        }
+    }
+
+    /// Parse `params["sizing"]`; a missing block means one unit per entry.
+    pub fn from_params(params: &Value) -> Result<Self, String> {
+        let config = match params.get("sizing") {
+            None | Some(Value::Null) => return Ok(Self::default()),
+            Some(config) if config.is_object() => config,
+            Some(_) => return Err("'sizing' must be an object".to_string()),
+        };
+
+        let method = match config.get("method").and_then(Value::as_str) {
+            Some("fixed_quantity") => SizingMethod::FixedQuantity {
+                quantity: positive(config, "quantity", 1.0)?,
+            },
+            Some("fixed_notional") => SizingMethod::FixedNotional {
+                notional: config
+                    .get("notional")
+                    .and_then(Value::as_f64)
+                    .filter(|v| *v > 0.0)
+                    .ok_or_else(|| "'sizing.notional' must be a positive number".to_string())?,
+            },
+            Some("percent_equity") => SizingMethod::PercentEquity {
+                fraction: fraction(config, "fraction", 1.0)?,
+            },
+            Some("volatility_target") => SizingMethod::VolatilityTarget {
+                risk_fraction: fraction(config, "risk_fraction", 0.01)?,
+                atr_period: count(config, "atr_period", 14)?,
+                atr_multiple: positive(config, "atr_multiple", 2.0)?,
+            },
+            Some("kelly") => {
+                let lookback = count(config, "lookback", 50)?;
+                let min_trades = count(config, "min_trades", 10)?;
+                if min_trades > lookback {
+                    return Err(format!(
+                        "'sizing.min_trades' ({}) cannot exceed 'sizing.lookback' ({})",
+                        min_trades, lookback
+                    ));
+                }
+                SizingMethod::Kelly {
+                    fraction: fraction(config, "fraction", 0.5)?,
+                    lookback,
+                    min_trades,
+                    initial_fraction: fraction(config, "initial_fraction", 0.1)?,
+                }
+            }
+            Some(other) => return Err(format!("Unknown sizing method: {}", other)),
+            None => return Err("'sizing.method' is required".to_string()),
+        };
+
+        let capital = match config.get("capital") {
+            None => None,
+            Some(_) => Some(positive(config, "capital", 0.0)?),
+        };
+
+        Ok(Self::new(method, capital))
+    }
+
+    /// Validate a sizing block without keeping the sizer.
+    pub fn validate(params: &Value) -> Result<(), String> {
+        Self::from_params(params).map(|_| ())
+    }
+
+    pub fn method(&self) -> &SizingMethod {
+        &self.method
+    }
+
+    pub fn capital(&self) -> Option<f64> {
+        self.capital
+    }
+
+    /// Equity to size against in live trading, where there is no simulated
+    /// account. `None` when the method needs equity but no `capital` is set.
+    pub fn live_equity(&self) -> Option<f64> {
+        if self.method.uses_equity() {
+            self.capital
+        } else {
+            Some(0.0)
+        }
+    }
+
+    /// Feed a candle for `symbol` so volatility-based sizing stays current.
+    pub fn on_bar(&mut self, symbol: &str, data: &OHLCV) {
+        if let SizingMethod::VolatilityTarget { atr_period, .. } = self.method {
+            let (atr, last) = self.atr.entry(symbol.to_string()).or_insert_with(|| {
+                (
+                    AverageTrueRange::new(atr_period).expect("atr_period validated positive"),
+                    0.0,
+                )
+            });
+            *last = atr.next(data);
+        }
+    }
+
+    /// Record the fractional return of a closed trade (used by Kelly sizing).
+    pub fn record_trade(&mut self, pct_return: f64) {
+        if let SizingMethod::Kelly { lookback, .. } = self.method {
+            self.trade_returns.push_back(pct_return);
+            if self.trade_returns.len() > lookback {
+                self.trade_returns.pop_front();
+            }
+        }
+    }
+
+    /// Kelly fraction `p - (1 - p) / b` from recent trades, floored at zero.
+    fn kelly_fraction(&self) -> f64 {
+        let wins: Vec<f64> = self
+            .trade_returns
+            .iter()
+            .copied()
+            .filter(|r| *r > 0.0)
+            .collect();
+        let losses: Vec<f64> = self
+            .trade_returns
+            .iter()
+            .copied()
+            .filter(|r| *r < 0.0)
+            .collect();
+        if wins.is_empty() {
+            return 0.0;
+        }
+        if losses.is_empty() {
+            return 1.0;
+        }
+
+        let p = wins.len() as f64 / self.trade_returns.len() as f64;
+        let avg_win = wins.iter().sum::<f64>() / wins.len() as f64;
+        let avg_loss = -losses.iter().sum::<f64>() / losses.len() as f64;
+        let b = avg_win / avg_loss;
+        (p - (1.0 - p) / b).clamp(0.0, 1.0)
+    }
+
+    /// Units to open for `symbol` at `price` given the current `equity`.
+    /// Returns 0.0 when the policy cannot size a position yet.
+    pub fn quantity(&self, symbol: &str, equity: f64, price: f64) -> f64 {
+        if price <= 0.0 {
+            return 0.0;
+        }
+
+        let quantity = match &self.method {
+            SizingMethod::FixedQuantity { quantity } => *quantity,
+            SizingMethod::FixedNotional { notional } => notional / price,
+            SizingMethod::PercentEquity { fraction } => equity * fraction / price,
+            SizingMethod::VolatilityTarget {
+                risk_fraction,
+                atr_multiple,
+                ..
+            } => match self.atr.get(symbol) {
+                Some((_, atr)) if *atr > 0.0 => equity * risk_fraction / (atr * atr_multiple),
+                _ => 0.0,
+            },
+            SizingMethod::Kelly {
+                fraction,
+                min_trades,
+                initial_fraction,
+                ..
+            } => {
+                let bet = if self.trade_returns.len() < *min_trades {
+                    *initial_fraction
+                } else {
+                    fraction * self.kelly_fraction()
+                };
+                equity * bet / price
+            }
+        };
+
+        quantity.max(0.0)
+    }
+
+    /// Snapshot of the policy for `run_config`.
+    pub fn describe(&self) -> Value {
+        let mut config = match &self.method {
+            SizingMethod::FixedQuantity { quantity } => json!({ "quantity": quantity }),
+            SizingMethod::FixedNotional { notional } => json!({ "notional": notional }),
+            SizingMethod::PercentEquity { fraction } => json!({ "fraction": fraction }),
+            SizingMethod::VolatilityTarget {
+                risk_fraction,
+                atr_period,
+                atr_multiple,
+            } => json!({
+                "risk_fraction": risk_fraction,
+                "atr_period": atr_period,
+                "atr_multiple": atr_multiple,
+            }),
+            SizingMethod::Kelly {
+                fraction,
+                lookback,
+                min_trades,
+                initial_fraction,
+            } => json!({
+                "fraction": fraction,
+                "lookback": lookback,
+                "min_trades": min_trades,
+                "initial_fraction": initial_fraction,
+            }),
+        };
+        config["method"] = json!(self.method.name());
+        if let Some(capital) = self.capital {
+            config["capital"] = json!(capital);
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn sizer(sizing: Value) -> PositionSizer {
+        PositionSizer::from_params(&json!({ "sizing": sizing })).unwrap()
+    }
+
+    #[test]
+    fn test_default_is_one_unit() {
+        let sizer = PositionSizer::from_params(&json!({})).unwrap();
+        assert_eq!(sizer.quantity("BTC", 10_000.0, 50.0), 1.0);
+        assert_eq!(sizer.live_equity(), Some(0.0));
+    }
+
+    #[test]
+    fn test_notional_and_percent_equity() {
+        let notional = sizer(json!({"method": "fixed_notional", "notional": 1000.0}));
+        assert!((notional.quantity("BTC", 0.0, 50.0) - 20.0).abs() < 1e-9);
+
+        let percent = sizer(json!({"method": "percent_equity", "fraction": 0.25}));
+        assert!((percent.quantity("BTC", 10_000.0, 50.0) - 50.0).abs() < 1e-9);
+        // Equity-based sizing has no live equity without allocated capital
+        assert_eq!(percent.live_equity(), None);
+    }
+
+    #[test]
+    fn test_volatility_target_uses_atr() {
+        let mut sizer = sizer(json!({
+            "method": "volatility_target",
+            "risk_fraction": 0.01,
+            "atr_period": 3,
+            "atr_multiple": 2.0
+        }));
+        assert_eq!(sizer.quantity("BTC", 10_000.0, 100.0), 0.0);
+
+        for i in 0..10 {
+            let bar = OHLCV::new(
+                Utc.timestamp_opt(i * 60, 0).unwrap(),
+                100.0,
+                101.0,
+                99.0,
+                100.0,
+                1.0,
+            );
+            sizer.on_bar("BTC", &bar);
+        }
+        // ATR converges to 2.0: risk 100 over a 4.0 stop distance
+        assert!((sizer.quantity("BTC", 10_000.0, 100.0) - 25.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_kelly_fraction_from_trade_history() {
+        let mut sizer = sizer(json!({
+            "method": "kelly",
+            "fraction": 1.0,
+            "lookback": 10,
+            "min_trades": 4,
+            "initial_fraction": 0.1
+        }));
+        assert!((sizer.quantity("BTC", 1000.0, 10.0) - 10.0).abs() < 1e-9);
+
+        // 50% win rate with wins twice the size of losses: f* = 0.5 - 0.5 / 2
+        for r in [0.02, -0.01, 0.02, -0.01] {
+            sizer.record_trade(r);
+        }
+        assert!((sizer.quantity("BTC", 1000.0, 10.0) - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_invalid_sizing_rejected() {
+        for sizing in [
+            json!("all_in"),
+            json!({}),
+            json!({"method": "martingale"}),
+            json!({"method": "fixed_notional"}),
+            json!({"method": "percent_equity", "fraction": 1.5}),
+            json!({"method": "kelly", "lookback": 5, "min_trades": 10}),
+        ] {
+            assert!(PositionSizer::validate(&json!({ "sizing": sizing })).is_err());
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::{AppError, Result};
-use crate::models::market_data::OHLCV;
+use crate::models::market_data::{CorporateAction, OHLCV, PriceAdjustment};
+use crate::utils::adjustments::back_adjust;
 use sqlx::{Pool, Postgres, types::chrono};
 use tracing::{info, warn};
 
@@ -46,14 +47,13 @@ impl TimescaleDb {
         .map_err(AppError::Database)?;
 
         // Try to convert to hypertable
-        let is_hypertable: (bool,) = match sqlx::query_as(
+        // Lookup fails when this is not a TimescaleDB instance; treat as "not a hypertable"
+        let is_hypertable: (bool,) = sqlx::query_as(
             "SELECT count(*) > 0 FROM timescaledb_information.hypertables WHERE hypertable_name = 'ohlcv'"
         )
         .fetch_one(&self.pool)
-        .await {
-            Ok(val) => val,
-            Err(_) => (false,), // Probably not a TimescaleDB instance
-        };
+        .await
+        .unwrap_or_default();
 
         if !is_hypertable.0 {
             match sqlx::query("SELECT create_hypertable('ohlcv', 'time', if_not_exists => TRUE)")
@@ -65,6 +65,22 @@ impl TimescaleDb {
             }
         }
 
+        // Splits and dividends, keyed by ex-date
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS corporate_actions (
+                time TIMESTAMPTZ NOT NULL,
+                symbol TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                value DOUBLE PRECISION NOT NULL,
+                PRIMARY KEY (symbol, time, kind)
+            )
+        "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::Database)?;
+
         Ok(())
     }
 
@@ -116,4 +132,100 @@ impl TimescaleDb {
 
         Ok(rows)
     }
+
+    /// Query market data back-adjusted for the corporate actions up to `end`,
+    /// i.e. restated in the shares outstanding at `end`.
+    pub async fn query_adjusted_ohlcv(
+        &self,
+        symbol: &str,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+        adjustment: PriceAdjustment,
+    ) -> Result<Vec<OHLCV>> {
+        let bars = self.query_ohlcv(symbol, start, end).await?;
+        if adjustment == PriceAdjustment::Raw {
+            return Ok(bars);
+        }
+        let actions = self.query_corporate_actions(symbol, start, end).await?;
+        Ok(back_adjust(&bars, &actions, adjustment))
+    }
+
+    /// Insert splits and dividends, replacing any recorded for the same
+    /// ex-date.
+    pub async fn insert_corporate_actions(
+        &self,
+        symbol: &str,
+        actions: &[CorporateAction],
+    ) -> Result<()> {
+        for action in actions {
+            sqlx::query(
+                r#"
+                INSERT INTO corporate_actions (time, symbol, kind, value)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (symbol, time, kind) DO UPDATE SET value = EXCLUDED.value
+                "#,
+            )
+            .bind(action.timestamp)
+            .bind(symbol)
+            .bind(action.kind.to_string())
+            .bind(action.value)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::Database)?;
+        }
+        Ok(())
+    }
+
+    /// The asset type `symbol`'s bars were stored under, if it has any
+    pub async fn query_asset_type(&self, symbol: &str) -> Result<Option<String>> {
+        let row: Option<(String,)> = sqlx::query_as(
+            r#"
+            SELECT asset_type
+            FROM ohlcv
+            WHERE symbol = $1
+            ORDER BY time DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(symbol)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        Ok(row.map(|(asset_type,)| asset_type))
+    }
+
+    /// Query the splits and dividends that went ex between `start` and `end`
+    pub async fn query_corporate_actions(
+        &self,
+        symbol: &str,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<CorporateAction>> {
+        let rows: Vec<(chrono::DateTime<chrono::Utc>, String, f64)> = sqlx::query_as(
+            r#"
+            SELECT time, kind, value
+            FROM corporate_actions
+            WHERE symbol = $1 AND time >= $2 AND time <= $3
+            ORDER BY time ASC
+            "#,
+        )
+        .bind(symbol)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::Database)?;
+
+        rows.into_iter()
+            .map(|(timestamp, kind, value)| {
+                let kind = kind.parse().map_err(AppError::InternalServerError)?;
+                Ok(CorporateAction {
+                    timestamp,
+                    kind,
+                    value,
+                })
+            })
+            .collect()
+    }
 }
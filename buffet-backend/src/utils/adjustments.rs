@@ -0,0 +1,131 @@
+use crate::models::market_data::{CorporateAction, CorporateActionKind, OHLCV, PriceAdjustment};
+
+/// `bars` (oldest first) back-adjusted for the `actions` that went ex after
+/// each bar, so the history lines up with the latest prices.
+///
+/// A split divides earlier prices by its ratio and multiplies earlier volume
+/// by it. Under `Total`, a dividend also scales earlier prices by
+/// `1 - amount / close` of the last bar before its ex-date.
+pub fn back_adjust(
+    bars: &[OHLCV],
+    actions: &[CorporateAction],
+    adjustment: PriceAdjustment,
+) -> Vec<OHLCV> {
+    let mut adjusted = bars.to_vec();
+    if adjustment == PriceAdjustment::Raw || actions.is_empty() {
+        return adjusted;
+    }
+
+    let mut actions: Vec<&CorporateAction> = actions.iter().collect();
+    actions.sort_by_key(|action| action.timestamp);
+    let mut later = actions.into_iter().rev().peekable();
+    let mut price_factor = 1.0;
+    let mut volume_factor = 1.0;
+
+    for bar in adjusted.iter_mut().rev() {
+        while let Some(action) = later.next_if(|action| action.timestamp > bar.timestamp) {
+            match action.kind {
+                CorporateActionKind::Split if action.value > 0.0 => {
+                    price_factor /= action.value;
+                    volume_factor *= action.value;
+                }
+                CorporateActionKind::Dividend
+                    if adjustment == PriceAdjustment::Total
+                        && action.value > 0.0
+                        && action.value < bar.close =>
+                {
+                    price_factor *= 1.0 - action.value / bar.close;
+                }
+                _ => {}
+            }
+        }
+        bar.open *= price_factor;
+        bar.high *= price_factor;
+        bar.low *= price_factor;
+        bar.close *= price_factor;
+        bar.volume *= volume_factor;
+    }
+    adjusted
+}
+
+/// The dividends among `actions`, restated per share after the splits that
+/// followed them so they match a split-adjusted series. Oldest first.
+pub fn restate_dividends(actions: &[CorporateAction]) -> Vec<CorporateAction> {
+    let mut actions: Vec<&CorporateAction> = actions.iter().collect();
+    actions.sort_by_key(|action| action.timestamp);
+
+    let mut later_splits = 1.0;
+    let mut dividends = Vec::new();
+    for action in actions.into_iter().rev() {
+        match action.kind {
+            CorporateActionKind::Split if action.value > 0.0 => later_splits *= action.value,
+            CorporateActionKind::Dividend => dividends.push(CorporateAction::dividend(
+                action.timestamp,
+                action.value / later_splits,
+            )),
+            _ => {}
+        }
+    }
+    dividends.reverse();
+    dividends
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn bars(closes: &[f64]) -> Vec<OHLCV> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| OHLCV::new(start + Duration::days(i as i64), c, c, c, c, 100.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_is_removed_from_history() {
+        let raw = bars(&[400.0, 404.0, 101.0, 102.0]);
+        let actions = [CorporateAction::split(raw[2].timestamp, 4.0)];
+
+        let adjusted = back_adjust(&raw, &actions, PriceAdjustment::Splits);
+        let closes: Vec<f64> = adjusted.iter().map(|b| b.close).collect();
+        assert_eq!(closes, vec![100.0, 101.0, 101.0, 102.0]);
+        assert_eq!(adjusted[0].volume, 400.0);
+        assert_eq!(adjusted[3].volume, 100.0);
+
+        let unchanged = back_adjust(&raw, &actions, PriceAdjustment::Raw);
+        assert_eq!(unchanged[0].close, 400.0);
+    }
+
+    #[test]
+    fn test_total_adjustment_reinvests_dividends() {
+        let raw = bars(&[50.0, 100.0, 98.0]);
+        let actions = [CorporateAction::dividend(raw[2].timestamp, 2.0)];
+
+        let splits_only = back_adjust(&raw, &actions, PriceAdjustment::Splits);
+        assert_eq!(splits_only[1].close, 100.0);
+
+        // Holding through the ex-date returns 0% on the total series
+        let total = back_adjust(&raw, &actions, PriceAdjustment::Total);
+        assert!((total[1].close - 98.0).abs() < 1e-9);
+        assert!((total[0].close - 49.0).abs() < 1e-9);
+        assert_eq!(total[2].close, 98.0);
+    }
+
+    #[test]
+    fn test_dividends_are_restated_in_post_split_shares() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let actions = [
+            CorporateAction::dividend(start, 0.8),
+            CorporateAction::split(start + Duration::days(10), 4.0),
+            CorporateAction::dividend(start + Duration::days(20), 0.25),
+        ];
+        let dividends = restate_dividends(&actions);
+        assert_eq!(dividends.len(), 2);
+        assert!((dividends[0].value - 0.2).abs() < 1e-12);
+        assert_eq!(dividends[1].value, 0.25);
+        assert!(dividends[0].timestamp < dividends[1].timestamp);
+    }
+}
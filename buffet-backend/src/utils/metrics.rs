@@ -1,3 +1,11 @@
+use crate::calendar::TradingCalendar;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Length of a year, for annualising metrics and accruing borrow fees,
+/// interest and funding alike.
+pub const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 3600.0;
+
 /// Calculate Sharpe Ratio.
 ///
 /// sharpe = (mean_return - risk_free_rate) / std_dev_return
@@ -84,6 +92,367 @@ pub fn calculate_profit_factor(trades_pnl: &[f64]) -> f64 {
     }
 }
 
+/// Bar-to-bar returns of an equity curve, skipping non-positive bars.
+pub fn bar_returns(equity_curve: &[f64]) -> Vec<f64> {
+    equity_curve
+        .windows(2)
+        .filter(|w| w[0] > 0.0)
+        .map(|w| w[1] / w[0] - 1.0)
+        .collect()
+}
+
+/// Median spacing of `timestamps` in seconds, i.e. their bar size.
+///
+/// Returns `None` when fewer than two distinct timestamps are given.
+pub fn median_spacing_secs(timestamps: &[DateTime<Utc>]) -> Option<f64> {
+    let mut spacings: Vec<i64> = timestamps
+        .windows(2)
+        .map(|w| (w[1] - w[0]).num_seconds())
+        .filter(|&s| s > 0)
+        .collect();
+    if spacings.is_empty() {
+        return None;
+    }
+    spacings.sort_unstable();
+    Some(spacings[spacings.len() / 2] as f64)
+}
+
+/// Bars per year implied by the median spacing of `timestamps`, in
+/// calendar time.
+///
+/// Returns 0.0 when fewer than two distinct timestamps are given.
+pub fn periods_per_year(timestamps: &[DateTime<Utc>]) -> f64 {
+    median_spacing_secs(timestamps).map_or(0.0, |spacing| SECONDS_PER_YEAR / spacing)
+}
+
+/// Bars per year of `timestamps` traded on `calendar`: daily bars of a
+/// stock count ~252 a year, not 365.
+pub fn calendar_periods_per_year(timestamps: &[DateTime<Utc>], calendar: &TradingCalendar) -> f64 {
+    median_spacing_secs(timestamps).map_or(0.0, |spacing| calendar.periods_per_year(spacing))
+}
+
+/// Compound annual growth rate from `initial` to `last` over `years`.
+pub fn calculate_cagr(initial: f64, last: f64, years: f64) -> f64 {
+    if initial <= 0.0 || years <= 0.0 {
+        return 0.0;
+    }
+    if last <= 0.0 {
+        return -1.0;
+    }
+    (last / initial).powf(1.0 / years) - 1.0
+}
+
+/// Standard deviation of per-bar `returns`, scaled to a year.
+pub fn calculate_annualized_volatility(returns: &[f64], periods_per_year: f64) -> f64 {
+    let n = returns.len() as f64;
+    if n < 2.0 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    variance.sqrt() * periods_per_year.sqrt()
+}
+
+/// Sharpe ratio of per-bar `returns` (zero risk-free rate), scaled to a year.
+pub fn calculate_annualized_sharpe_ratio(returns: &[f64], periods_per_year: f64) -> f64 {
+    calculate_sharpe_ratio(returns, 0.0) * periods_per_year.sqrt()
+}
+
+/// Calculate Sortino Ratio of per-bar `returns`, scaled to a year.
+///
+/// sortino = mean_return / downside_deviation, where the downside deviation
+/// only counts returns below zero.
+pub fn calculate_sortino_ratio(returns: &[f64], periods_per_year: f64) -> f64 {
+    if returns.is_empty() {
+        return 0.0;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let downside = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / n).sqrt();
+    if downside == 0.0 {
+        return 0.0;
+    }
+    mean / downside * periods_per_year.sqrt()
+}
+
+/// Calculate Calmar Ratio: annualised return per unit of maximum drawdown.
+pub fn calculate_calmar_ratio(cagr: f64, max_drawdown: f64) -> f64 {
+    if max_drawdown <= 0.0 {
+        return 0.0;
+    }
+    cagr / max_drawdown
+}
+
+/// How long the equity curve spent under water.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DrawdownDurations {
+    /// Longest time from a peak until equity regained it (or the curve
+    /// ended), in seconds.
+    pub max_duration_secs: i64,
+    /// Time from the trough of the deepest drawdown back to its peak, in
+    /// seconds. `None` if the curve never recovered.
+    pub recovery_time_secs: Option<i64>,
+}
+
+/// Durations of the drawdowns of `equity`, sampled at `timestamps`.
+pub fn calculate_drawdown_durations(
+    timestamps: &[DateTime<Utc>],
+    equity: &[f64],
+) -> DrawdownDurations {
+    let mut durations = DrawdownDurations {
+        max_duration_secs: 0,
+        recovery_time_secs: Some(0),
+    };
+    let (Some(&first_time), Some(&first_value)) = (timestamps.first(), equity.first()) else {
+        return durations;
+    };
+
+    let (mut peak, mut peak_time) = (first_value, first_time);
+    let (mut trough, mut trough_time) = (first_value, first_time);
+    let mut deepest = 0.0;
+
+    for (&time, &value) in timestamps.iter().zip(equity) {
+        if value >= peak {
+            if trough < peak {
+                let depth = (peak - trough) / peak;
+                durations.max_duration_secs = durations
+                    .max_duration_secs
+                    .max((time - peak_time).num_seconds());
+                if depth > deepest {
+                    deepest = depth;
+                    durations.recovery_time_secs = Some((time - trough_time).num_seconds());
+                }
+            }
+            (peak, peak_time) = (value, time);
+            (trough, trough_time) = (value, time);
+        } else if value < trough {
+            (trough, trough_time) = (value, time);
+        }
+    }
+
+    // Still under water at the end of the curve
+    if trough < peak {
+        let last_time = timestamps[timestamps.len().min(equity.len()) - 1];
+        durations.max_duration_secs = durations
+            .max_duration_secs
+            .max((last_time - peak_time).num_seconds());
+        if (peak - trough) / peak > deepest {
+            durations.recovery_time_secs = None;
+        }
+    }
+    durations
+}
+
+/// Per-trade statistics from a slice of per-trade PnL values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradeStats {
+    /// Mean PnL of winning trades.
+    pub average_win: f64,
+    /// Mean PnL of losing trades (negative).
+    pub average_loss: f64,
+    /// Mean PnL per trade.
+    pub expectancy: f64,
+    pub largest_win: f64,
+    /// PnL of the worst trade (negative).
+    pub largest_loss: f64,
+    /// Longest run of losing trades in a row.
+    pub max_consecutive_losses: i64,
+}
+
+impl TradeStats {
+    pub fn from_pnl(trades_pnl: &[f64]) -> Self {
+        fn mean<'a>(values: impl Iterator<Item = &'a f64>) -> f64 {
+            let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+            if count == 0 { 0.0 } else { sum / count as f64 }
+        }
+
+        let mut streak = 0;
+        let mut max_consecutive_losses = 0;
+        for &pnl in trades_pnl {
+            streak = if pnl < 0.0 { streak + 1 } else { 0 };
+            max_consecutive_losses = max_consecutive_losses.max(streak);
+        }
+
+        Self {
+            average_win: mean(trades_pnl.iter().filter(|&&pnl| pnl > 0.0)),
+            average_loss: mean(trades_pnl.iter().filter(|&&pnl| pnl < 0.0)),
+            expectancy: mean(trades_pnl.iter()),
+            largest_win: trades_pnl.iter().copied().fold(0.0, f64::max),
+            largest_loss: trades_pnl.iter().copied().fold(0.0, f64::min),
+            max_consecutive_losses,
+        }
+    }
+}
+
+/// Performance metrics of a run beyond the headline figures, derived from
+/// its equity curve and closed trades.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tearsheet {
+    pub cagr: f64,
+    pub annualized_volatility: f64,
+    /// Sharpe ratio of bar returns, annualised from the bar frequency.
+    pub annualized_sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    pub calmar_ratio: f64,
+    pub max_drawdown_duration_secs: i64,
+    pub recovery_time_secs: Option<i64>,
+    /// Share of bars with an open position.
+    pub exposure_time: f64,
+    /// Traded notional over the run as a multiple of average equity.
+    pub turnover: f64,
+    #[serde(flatten)]
+    pub trades: TradeStats,
+}
+
+impl Tearsheet {
+    /// Compute the tearsheet of an equity curve, given as parallel
+    /// `timestamps`, `equity` and `exposure` slices, and its trades.
+    /// Ratios are annualised by the bars a year of `calendar` holds.
+    pub fn compute(
+        timestamps: &[DateTime<Utc>],
+        equity: &[f64],
+        exposure: &[f64],
+        trades_pnl: &[f64],
+        traded_notional: f64,
+        calendar: &TradingCalendar,
+    ) -> Self {
+        let (Some(&initial), Some(&last)) = (equity.first(), equity.last()) else {
+            return Self::default();
+        };
+        let years = match (timestamps.first(), timestamps.last()) {
+            (Some(first), Some(end)) => (*end - *first).num_seconds() as f64 / SECONDS_PER_YEAR,
+            _ => 0.0,
+        };
+        let periods = calendar_periods_per_year(timestamps, calendar);
+        let returns = bar_returns(equity);
+        let cagr = finite(calculate_cagr(initial, last, years));
+        let durations = calculate_drawdown_durations(timestamps, equity);
+        let average_equity = equity.iter().sum::<f64>() / equity.len() as f64;
+
+        Self {
+            cagr,
+            annualized_volatility: calculate_annualized_volatility(&returns, periods),
+            annualized_sharpe_ratio: calculate_annualized_sharpe_ratio(&returns, periods),
+            sortino_ratio: calculate_sortino_ratio(&returns, periods),
+            calmar_ratio: finite(calculate_calmar_ratio(cagr, calculate_max_drawdown(equity))),
+            max_drawdown_duration_secs: durations.max_duration_secs,
+            recovery_time_secs: durations.recovery_time_secs,
+            exposure_time: if exposure.is_empty() {
+                0.0
+            } else {
+                exposure.iter().filter(|&&e| e > 0.0).count() as f64 / exposure.len() as f64
+            },
+            turnover: if average_equity > 0.0 {
+                traded_notional / average_equity
+            } else {
+                0.0
+            },
+            trades: TradeStats::from_pnl(trades_pnl),
+        }
+    }
+}
+
+/// Performance relative to a benchmark equity curve.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkComparison {
+    /// Total return of the benchmark over the run.
+    pub benchmark_return: f64,
+    /// Annualised return not explained by exposure to the benchmark.
+    pub alpha: f64,
+    /// Sensitivity of bar returns to the benchmark's.
+    pub beta: f64,
+    /// Annualised active return per unit of tracking error.
+    pub information_ratio: f64,
+    /// Annualised standard deviation of active (strategy minus benchmark) returns.
+    pub tracking_error: f64,
+    /// Total return minus the benchmark's.
+    pub excess_return: f64,
+}
+
+impl BenchmarkComparison {
+    /// Compare `equity` with `benchmark`, both sampled at `timestamps` of
+    /// `calendar`.
+    pub fn compute(
+        timestamps: &[DateTime<Utc>],
+        equity: &[f64],
+        benchmark: &[f64],
+        calendar: &TradingCalendar,
+    ) -> Self {
+        let total_return = |curve: &[f64]| match (curve.first(), curve.last()) {
+            (Some(&first), Some(&last)) if first > 0.0 => last / first - 1.0,
+            _ => 0.0,
+        };
+        let benchmark_return = total_return(benchmark);
+        let excess_return = total_return(equity) - benchmark_return;
+
+        let (returns, benchmark_returns): (Vec<f64>, Vec<f64>) = equity
+            .windows(2)
+            .zip(benchmark.windows(2))
+            .filter(|(s, b)| s[0] > 0.0 && b[0] > 0.0)
+            .map(|(s, b)| (s[1] / s[0] - 1.0, b[1] / b[0] - 1.0))
+            .unzip();
+        let n = returns.len() as f64;
+        if n < 2.0 {
+            return Self {
+                benchmark_return,
+                excess_return,
+                ..Self::default()
+            };
+        }
+
+        let periods = calendar_periods_per_year(timestamps, calendar);
+        let mean = returns.iter().sum::<f64>() / n;
+        let benchmark_mean = benchmark_returns.iter().sum::<f64>() / n;
+        let covariance = returns
+            .iter()
+            .zip(&benchmark_returns)
+            .map(|(r, b)| (r - mean) * (b - benchmark_mean))
+            .sum::<f64>()
+            / (n - 1.0);
+        let benchmark_variance = benchmark_returns
+            .iter()
+            .map(|b| (b - benchmark_mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        let beta = if benchmark_variance > 0.0 {
+            covariance / benchmark_variance
+        } else {
+            0.0
+        };
+
+        let active: Vec<f64> = returns
+            .iter()
+            .zip(&benchmark_returns)
+            .map(|(r, b)| r - b)
+            .collect();
+        let tracking_error = calculate_annualized_volatility(&active, periods);
+        let information_ratio = if tracking_error > 0.0 {
+            (mean - benchmark_mean) * periods / tracking_error
+        } else {
+            0.0
+        };
+
+        Self {
+            benchmark_return,
+            alpha: (mean - beta * benchmark_mean) * periods,
+            beta,
+            information_ratio,
+            tracking_error,
+            excess_return,
+        }
+    }
+}
+
+/// Clamp an overflowed value to the largest finite one (SQLite REAL limitation).
+/// Short, strongly trending runs compound to infinity once annualised.
+fn finite(value: f64) -> f64 {
+    if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(f64::MIN, f64::MAX)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,4 +496,89 @@ mod tests {
     fn test_profit_factor_empty() {
         assert_eq!(calculate_profit_factor(&[]), 0.0);
     }
+
+    #[test]
+    fn test_cagr_compounds_over_years() {
+        assert!((calculate_cagr(100.0, 121.0, 2.0) - 0.1).abs() < 1e-9);
+        assert_eq!(calculate_cagr(100.0, 0.0, 1.0), -1.0);
+        assert_eq!(calculate_cagr(100.0, 110.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_periods_per_year_from_median_spacing() {
+        let start = Utc::now();
+        let daily: Vec<DateTime<Utc>> = [0, 1, 2, 5, 6]
+            .iter()
+            .map(|&d| start + chrono::Duration::days(d))
+            .collect();
+        assert!((periods_per_year(&daily) - 365.25).abs() < 1e-9);
+        assert_eq!(periods_per_year(&daily[..1]), 0.0);
+
+        // Daily bars of a stock annualise by its trading days
+        let nyse = TradingCalendar::nyse();
+        let periods = calendar_periods_per_year(&daily, &nyse);
+        assert_eq!(periods, nyse.trading_days_per_year());
+        assert!(periods < 253.0);
+    }
+
+    #[test]
+    fn test_sortino_only_penalises_downside() {
+        let returns = vec![0.02, -0.01, 0.03, -0.01];
+        // mean 0.0075, downside deviation sqrt(0.0002 / 4)
+        let sortino = calculate_sortino_ratio(&returns, 1.0);
+        assert!((sortino - 0.0075 / 0.00005f64.sqrt()).abs() < 1e-9);
+        assert_eq!(calculate_sortino_ratio(&[0.01, 0.02], 252.0), 0.0);
+    }
+
+    #[test]
+    fn test_drawdown_durations() {
+        let start = Utc::now();
+        let times: Vec<DateTime<Utc>> =
+            (0..7).map(|h| start + chrono::Duration::hours(h)).collect();
+        // Shallow dip recovered in 2h, then a deeper one: trough at 4h,
+        // back to the peak at 6h
+        let equity = [100.0, 95.0, 100.0, 90.0, 80.0, 95.0, 100.0];
+        let d = calculate_drawdown_durations(&times, &equity);
+        assert_eq!(d.max_duration_secs, 4 * 3600);
+        assert_eq!(d.recovery_time_secs, Some(2 * 3600));
+
+        let d = calculate_drawdown_durations(&times[..5], &equity[..5]);
+        assert_eq!(d.max_duration_secs, 2 * 3600);
+        assert_eq!(d.recovery_time_secs, None);
+    }
+
+    #[test]
+    fn test_trade_stats() {
+        let stats = TradeStats::from_pnl(&[100.0, -50.0, -30.0, 200.0, -20.0, -10.0, -40.0]);
+        assert!((stats.average_win - 150.0).abs() < 1e-9);
+        assert!((stats.average_loss + 30.0).abs() < 1e-9);
+        assert!((stats.expectancy - 150.0 / 7.0).abs() < 1e-9);
+        assert_eq!(stats.largest_win, 200.0);
+        assert_eq!(stats.largest_loss, -50.0);
+        assert_eq!(stats.max_consecutive_losses, 3);
+        assert_eq!(TradeStats::from_pnl(&[]), TradeStats::default());
+    }
+
+    #[test]
+    fn test_benchmark_comparison() {
+        let start = Utc::now();
+        let times: Vec<DateTime<Utc>> = (0..5).map(|d| start + chrono::Duration::days(d)).collect();
+        let benchmark = [100.0, 102.0, 101.0, 104.0, 103.0];
+        let crypto = TradingCalendar::crypto();
+
+        // Holding the benchmark at double size: beta 2, and a benchmark
+        // matched exactly has no tracking error
+        let doubled: Vec<f64> = benchmark.iter().map(|b| 2.0 * b - 100.0).collect();
+        let c = BenchmarkComparison::compute(&times, &doubled, &benchmark, &crypto);
+        assert!((c.benchmark_return - 0.03).abs() < 1e-9);
+        assert!((c.excess_return - 0.03).abs() < 1e-9);
+        assert!(c.beta > 1.9 && c.beta < 2.1);
+
+        let c = BenchmarkComparison::compute(&times, &benchmark, &benchmark, &crypto);
+        assert!((c.beta - 1.0).abs() < 1e-9);
+        assert!(c.alpha.abs() < 1e-9);
+        assert_eq!(c.tracking_error, 0.0);
+        assert_eq!(c.information_ratio, 0.0);
+        assert_eq!(c.excess_return, 0.0);
+    }
 }
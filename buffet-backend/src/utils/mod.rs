@@ -1 +1,4 @@
+pub mod adjustments;
 pub mod metrics;
+pub mod monte_carlo;
+pub mod vectorized;
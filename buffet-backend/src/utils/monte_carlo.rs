@@ -0,0 +1,263 @@
+use crate::utils::metrics::{calculate_max_drawdown, calculate_sharpe_ratio};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// How each Monte Carlo path is drawn from a backtest's history.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MonteCarloMethod {
+    /// Draw trades with replacement.
+    #[default]
+    TradeResample,
+    /// Replay every trade once in a random order. Final equity is unchanged;
+    /// only the path, and so the drawdown, varies.
+    TradeShuffle,
+    /// Circular block bootstrap of bar-to-bar equity returns, which keeps
+    /// short-range autocorrelation within each block.
+    BlockBootstrap,
+}
+
+impl std::fmt::Display for MonteCarloMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MonteCarloMethod::TradeResample => write!(f, "trade_resample"),
+            MonteCarloMethod::TradeShuffle => write!(f, "trade_shuffle"),
+            MonteCarloMethod::BlockBootstrap => write!(f, "block_bootstrap"),
+        }
+    }
+}
+
+/// Percentile bands of one metric across all simulated paths.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PercentileBands {
+    pub p5: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+    pub mean: f64,
+}
+
+impl PercentileBands {
+    /// Bands of `values`, with linear interpolation between ranks.
+    pub fn from_values(values: &mut [f64]) -> Self {
+        values.sort_by(f64::total_cmp);
+        let mean = if values.is_empty() {
+            0.0
+        } else {
+            values.iter().sum::<f64>() / values.len() as f64
+        };
+        Self {
+            p5: percentile(values, 0.05),
+            p25: percentile(values, 0.25),
+            p50: percentile(values, 0.50),
+            p75: percentile(values, 0.75),
+            p95: percentile(values, 0.95),
+            mean,
+        }
+    }
+}
+
+/// The `q` quantile of already sorted `values`.
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        1 => sorted[0],
+        n => {
+            let rank = q * (n - 1) as f64;
+            let lower = rank.floor() as usize;
+            let upper = rank.ceil() as usize;
+            sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+        }
+    }
+}
+
+/// Distributions of the simulated paths' outcomes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MonteCarloSummary {
+    pub final_equity: PercentileBands,
+    pub max_drawdown: PercentileBands,
+    pub sharpe_ratio: PercentileBands,
+    /// Share of paths ending below the initial balance.
+    pub probability_of_loss: f64,
+}
+
+/// A trade as the simulation sees it: its PnL moves equity, its return
+/// feeds the Sharpe ratio (as `calculate_sharpe_ratio` does for backtests).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeSample {
+    pub pnl: f64,
+    pub percentage_return: f64,
+}
+
+/// Outcome of one path.
+struct PathOutcome {
+    final_equity: f64,
+    max_drawdown: f64,
+    sharpe_ratio: f64,
+}
+
+fn summarise(outcomes: Vec<PathOutcome>, initial_balance: f64) -> MonteCarloSummary {
+    let losses = outcomes
+        .iter()
+        .filter(|o| o.final_equity < initial_balance)
+        .count();
+    let probability_of_loss = if outcomes.is_empty() {
+        0.0
+    } else {
+        losses as f64 / outcomes.len() as f64
+    };
+
+    let mut final_equity: Vec<f64> = outcomes.iter().map(|o| o.final_equity).collect();
+    let mut max_drawdown: Vec<f64> = outcomes.iter().map(|o| o.max_drawdown).collect();
+    let mut sharpe_ratio: Vec<f64> = outcomes.iter().map(|o| o.sharpe_ratio).collect();
+    MonteCarloSummary {
+        final_equity: PercentileBands::from_values(&mut final_equity),
+        max_drawdown: PercentileBands::from_values(&mut max_drawdown),
+        sharpe_ratio: PercentileBands::from_values(&mut sharpe_ratio),
+        probability_of_loss,
+    }
+}
+
+/// Replay `trades` `simulations` times, either resampled with replacement
+/// or shuffled, adding each trade's PnL to the balance.
+pub fn simulate_trades(
+    trades: &[TradeSample],
+    initial_balance: f64,
+    simulations: usize,
+    shuffle: bool,
+    rng: &mut ChaCha8Rng,
+) -> MonteCarloSummary {
+    let mut path: Vec<TradeSample> = trades.to_vec();
+    let mut equity = Vec::with_capacity(trades.len() + 1);
+    let mut returns = Vec::with_capacity(trades.len());
+
+    let outcomes = (0..simulations)
+        .map(|_| {
+            if shuffle {
+                path.shuffle(rng);
+            } else {
+                for slot in path.iter_mut() {
+                    *slot = trades[rng.random_range(0..trades.len())];
+                }
+            }
+
+            equity.clear();
+            equity.push(initial_balance);
+            let mut balance = initial_balance;
+            for trade in &path {
+                balance += trade.pnl;
+                equity.push(balance);
+            }
+            returns.clear();
+            returns.extend(path.iter().map(|t| t.percentage_return));
+
+            PathOutcome {
+                final_equity: balance,
+                max_drawdown: calculate_max_drawdown(&equity),
+                sharpe_ratio: calculate_sharpe_ratio(&returns, 0.0),
+            }
+        })
+        .collect();
+
+    summarise(outcomes, initial_balance)
+}
+
+/// Rebuild `simulations` equity curves from blocks of `block_size`
+/// consecutive bar returns, starting at random offsets and wrapping around.
+pub fn simulate_bar_blocks(
+    bar_returns: &[f64],
+    initial_balance: f64,
+    simulations: usize,
+    block_size: usize,
+    rng: &mut ChaCha8Rng,
+) -> MonteCarloSummary {
+    let n = bar_returns.len();
+    let block_size = block_size.clamp(1, n.max(1));
+    let mut returns = Vec::with_capacity(n);
+    let mut equity = Vec::with_capacity(n + 1);
+
+    let outcomes = (0..simulations)
+        .map(|_| {
+            returns.clear();
+            while returns.len() < n {
+                let start = rng.random_range(0..n);
+                let take = block_size.min(n - returns.len());
+                returns.extend((0..take).map(|i| bar_returns[(start + i) % n]));
+            }
+
+            equity.clear();
+            equity.push(initial_balance);
+            let mut balance = initial_balance;
+            for r in &returns {
+                balance *= 1.0 + r;
+                equity.push(balance);
+            }
+
+            PathOutcome {
+                final_equity: balance,
+                max_drawdown: calculate_max_drawdown(&equity),
+                sharpe_ratio: calculate_sharpe_ratio(&returns, 0.0),
+            }
+        })
+        .collect();
+
+    summarise(outcomes, initial_balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::metrics::bar_returns;
+    use rand::SeedableRng;
+
+    fn trades() -> Vec<TradeSample> {
+        [50.0, -20.0, 30.0, -40.0, 10.0]
+            .iter()
+            .map(|&pnl| TradeSample {
+                pnl,
+                percentage_return: pnl / 1000.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_percentiles_interpolate_between_ranks() {
+        let mut values = vec![5.0, 1.0, 4.0, 2.0, 3.0];
+        let bands = PercentileBands::from_values(&mut values);
+        assert_eq!(bands.p50, 3.0);
+        assert_eq!(bands.p25, 2.0);
+        assert!((bands.p5 - 1.2).abs() < 1e-9);
+        assert_eq!(bands.mean, 3.0);
+    }
+
+    #[test]
+    fn test_shuffle_keeps_final_equity_but_varies_drawdown() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let summary = simulate_trades(&trades(), 1000.0, 500, true, &mut rng);
+
+        assert!((summary.final_equity.p5 - 1030.0).abs() < 1e-9);
+        assert!((summary.final_equity.p95 - 1030.0).abs() < 1e-9);
+        assert!(summary.max_drawdown.p95 > summary.max_drawdown.p5);
+        assert_eq!(summary.probability_of_loss, 0.0);
+    }
+
+    #[test]
+    fn test_same_seed_reproduces_results() {
+        let run = |seed| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            simulate_trades(&trades(), 1000.0, 200, false, &mut rng)
+        };
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+
+        let returns = bar_returns(&[100.0, 101.0, 99.0, 102.0, 103.0, 101.0]);
+        let blocks = |seed| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            simulate_bar_blocks(&returns, 1000.0, 200, 2, &mut rng)
+        };
+        assert_eq!(blocks(1), blocks(1));
+    }
+}
@@ -0,0 +1,640 @@
+//! Vectorized backtest engine: a whole run simulated column-wise with Polars
+//! lazy expressions instead of bar by bar, for fast research runs and
+//! parameter sweeps.
+//!
+//! Signals come from the expression a strategy's registry definition
+//! declares, evaluated over the signal (dividend-adjusted) prices in the
+//! columns `open`, `high`, `low`, `close` and `volume`: 1.0 for a buy, -1.0
+//! for a sell and null for no signal. Positions, fills, costs and equity
+//! then follow from shifted and cumulative columns, with the fill prices,
+//! slippage, commission, borrow fees and dividends of the event loop in
+//! `BacktestActor`, so both engines report the same metrics.
+//!
+//! That leaves out everything path-dependent: a run trades one symbol,
+//! a fixed quantity per position, without risk exits, market impact,
+//! participation caps or a margin account. Such runs are rejected with the
+//! reason and belong to the event loop. Fixed-quantity sizing is the
+//! default, so only strategies that size by notional, equity or
+//! volatility have to name it to run here.
+
+use crate::broker::commission::CommissionSchedule;
+use crate::broker::{CommissionModel, FillModel, Liquidity, MarginModel, SlippageModel};
+use crate::models::backtest::{Backtest, BacktestTrade, EquityPoint, ExitReason};
+use crate::models::market_data::OHLCV;
+use crate::models::order::OrderSide;
+use crate::strategies::{BuiltStrategy, SizingMethod};
+use crate::utils::metrics::SECONDS_PER_YEAR;
+use chrono::{DateTime, Utc};
+use polars::prelude::*;
+
+/// What the vectorized engine runs of a strategy: its signal expression and
+/// the units every position holds.
+pub struct VectorizedStrategy {
+    signals: Expr,
+    quantity: f64,
+}
+
+impl VectorizedStrategy {
+    /// The vectorized form of `built` trading `symbol_count` symbols under
+    /// the given models, or why the engine cannot run it.
+    pub fn new(
+        built: &BuiltStrategy,
+        symbol_count: usize,
+        slippage: &SlippageModel,
+        margin: &MarginModel,
+    ) -> Result<Self, String> {
+        let unsupported =
+            |what: String| Err(format!("The vectorized engine does not support {}", what));
+
+        if symbol_count != 1 {
+            return unsupported("backtests of several symbols".to_string());
+        }
+        let Some(signals) = built.vectorized.clone() else {
+            return unsupported(format!("'{}' strategies", built.kind));
+        };
+        let SizingMethod::FixedQuantity { quantity } = built.sizer.method() else {
+            return unsupported(format!("'{}' sizing", built.sizer.method().name()));
+        };
+        if !built.exits.is_empty() {
+            return unsupported("risk exits".to_string());
+        }
+        if *slippage != SlippageModel::default() {
+            return unsupported("market impact or participation caps".to_string());
+        }
+        if *margin != MarginModel::default() {
+            return unsupported("margin accounts".to_string());
+        }
+
+        Ok(Self {
+            signals,
+            quantity: *quantity,
+        })
+    }
+}
+
+/// Equity curve and trades of a vectorized run, ready to be scored and
+/// persisted like the event loop's.
+pub struct VectorizedRun {
+    /// Equity at `start_time` and at the close of every bar from then on.
+    pub equity_curve: Vec<EquityPoint>,
+    /// Every round trip in entry order, not yet persisted.
+    pub trades: Vec<BacktestTrade>,
+    pub final_balance: f64,
+}
+
+/// Bars as a frame: signal prices under their plain names, the traded
+/// (split-adjusted) prices as `trade_*`, and the cash dividend per unit
+/// paid to positions held into each bar.
+fn bars_frame(candles: &[OHLCV], signals: &[OHLCV], dividends: &[f64]) -> PolarsResult<DataFrame> {
+    let column =
+        |bars: &[OHLCV], field: fn(&OHLCV) -> f64| bars.iter().map(field).collect::<Vec<f64>>();
+    df!(
+        "timestamp" => candles.iter().map(|c| c.timestamp.timestamp_micros()).collect::<Vec<i64>>(),
+        "open" => column(signals, |c| c.open),
+        "high" => column(signals, |c| c.high),
+        "low" => column(signals, |c| c.low),
+        "close" => column(signals, |c| c.close),
+        "volume" => column(signals, |c| c.volume),
+        "trade_open" => column(candles, |c| c.open),
+        "trade_high" => column(candles, |c| c.high),
+        "trade_low" => column(candles, |c| c.low),
+        "trade_close" => column(candles, |c| c.close),
+        "dividend" => dividends,
+    )
+}
+
+/// `model`'s charge for filling `quantity` units at `price` as a market
+/// order, as an expression; `sell` marks sell fills. Mirrors
+/// [`CommissionModel::commission`].
+fn commission(model: &CommissionModel, sell: Expr, quantity: Expr, price: Expr) -> Expr {
+    let value = quantity.clone() * price;
+    let lesser = |a: Expr, b: Expr| when(a.clone().lt(b.clone())).then(a).otherwise(b);
+    let greater = |a: Expr, b: Expr| when(a.clone().gt(b.clone())).then(a).otherwise(b);
+
+    let charge = match &model.schedule {
+        CommissionSchedule::Percentage { rate } => value.clone() * lit(*rate),
+        CommissionSchedule::PerShare {
+            rate,
+            minimum,
+            maximum,
+        } => {
+            let fee = greater(quantity.clone() * lit(*rate), lit(*minimum));
+            match maximum {
+                Some(maximum) => lesser(fee, lit(*maximum)),
+                None => fee,
+            }
+        }
+        CommissionSchedule::Tiered { tiers } => {
+            let mut fee = lit(0.0);
+            let mut lower = 0.0;
+            for tier in tiers {
+                let upper = match tier.up_to {
+                    Some(up_to) => lesser(lit(up_to), value.clone()),
+                    None => value.clone(),
+                };
+                fee = fee
+                    + when(upper.clone().gt(lit(lower)))
+                        .then((upper - lit(lower)) * lit(tier.rate))
+                        .otherwise(lit(0.0));
+                lower = tier.up_to.unwrap_or(f64::INFINITY);
+            }
+            fee
+        }
+        // Market orders always take liquidity
+        CommissionSchedule::MakerTaker { taker_rate, .. } => value.clone() * lit(*taker_rate),
+        CommissionSchedule::PerTicket { fee } => lit(*fee),
+    };
+
+    let per_share = quantity.clone() * lit(model.fees.sell_per_share);
+    let per_share = match model.fees.sell_per_share_max {
+        Some(maximum) => lesser(per_share, lit(maximum)),
+        None => per_share,
+    };
+    let fees = quantity.clone() * lit(model.fees.exchange_per_share)
+        + when(sell)
+            .then(value * lit(model.fees.sell_value_rate) + per_share)
+            .otherwise(lit(0.0));
+
+    when(quantity.gt(lit(0.0)))
+        .then(charge + fees)
+        .otherwise(lit(0.0))
+}
+
+fn polars_error(e: PolarsError) -> String {
+    format!("Vectorized simulation failed: {}", e)
+}
+
+fn floats(frame: &DataFrame, name: &str) -> PolarsResult<Vec<Option<f64>>> {
+    Ok(frame.column(name)?.f64()?.into_iter().collect())
+}
+
+fn times(frame: &DataFrame, name: &str) -> PolarsResult<Vec<Option<DateTime<Utc>>>> {
+    Ok(frame
+        .column(name)?
+        .i64()?
+        .into_iter()
+        .map(|micros| micros.and_then(DateTime::from_timestamp_micros))
+        .collect())
+}
+
+/// Simulate `backtest` on one symbol's split-adjusted `candles`, the
+/// `signals` its strategy sees and the `dividends` paid into each bar.
+pub fn simulate(
+    backtest: &Backtest,
+    strategy: &VectorizedStrategy,
+    symbol: &str,
+    candles: &[OHLCV],
+    signals: &[OHLCV],
+    dividends: &[f64],
+) -> Result<VectorizedRun, String> {
+    let bars = bars_frame(candles, signals, dividends).map_err(polars_error)?;
+    let commission_model = backtest.commission_model();
+    let fill_model = backtest.fill_model();
+    let slippage = backtest.slippage_bps / 10_000.0;
+    // Bars are stored to the microsecond, so round a finer `start_time` up
+    // to keep the event loop's `timestamp < start_time` warm-up cut-off
+    let nanos = backtest.start_time.timestamp_subsec_nanos();
+    let start = backtest.start_time.timestamp_micros() + i64::from(!nanos.is_multiple_of(1_000));
+
+    // Target position: the latest signal since `start_time` (warm-up bars
+    // never trade), where a sell means flat unless shorting is allowed
+    let signal = when(col("timestamp").lt(lit(start)))
+        .then(lit(NULL))
+        .otherwise(strategy.signals.clone());
+    let signal = if backtest.allow_short {
+        signal
+    } else {
+        when(signal.clone().lt(lit(0.0)))
+            .then(lit(0.0))
+            .otherwise(signal)
+    };
+    let target = signal.forward_fill(None).fill_null(lit(0.0)) * lit(strategy.quantity);
+
+    // Position after each bar's fills, and the price they fill at
+    let (position, price) = match fill_model {
+        FillModel::NextBarOpen => (target.shift(lit(1)).fill_null(lit(0.0)), col("trade_open")),
+        FillModel::SameBarClose => (target, col("trade_close")),
+        FillModel::Vwap => (
+            target,
+            (col("trade_high") + col("trade_low") + col("trade_close")) / lit(3.0),
+        ),
+    };
+
+    // Any change of position closes what was held and opens the new side
+    let changed = col("position").neq(col("held"));
+    let slipped = |sell: Expr| {
+        when(sell)
+            .then(col("price") * lit(1.0 - slippage))
+            .otherwise(col("price") * lit(1.0 + slippage))
+    };
+    let cash_flow = |sell: Expr, quantity: &str, price: &str, commission: &str| {
+        let notional = col(price) * col(quantity);
+        when(sell)
+            .then(notional.clone())
+            .otherwise(lit(0.0) - notional)
+            - col(commission)
+    };
+    let exit_sell = col("held").gt(lit(0.0));
+    let entry_sell = col("position").lt(lit(0.0));
+
+    let frame = bars
+        .lazy()
+        .with_columns([
+            position.alias("position"),
+            price.alias("price"),
+            ((col("timestamp") - col("timestamp").shift(lit(1))).cast(DataType::Float64)
+                / lit(1e6)
+                / lit(SECONDS_PER_YEAR))
+            .fill_null(lit(0.0))
+            .alias("years"),
+        ])
+        .with_columns([col("position")
+            .shift(lit(1))
+            .fill_null(lit(0.0))
+            .alias("held")])
+        .with_columns([
+            when(col("held").neq(lit(0.0)).and(changed.clone()))
+                .then(col("held").abs())
+                .otherwise(lit(0.0))
+                .alias("exit_qty"),
+            when(col("position").neq(lit(0.0)).and(changed))
+                .then(col("position").abs())
+                .otherwise(lit(0.0))
+                .alias("entry_qty"),
+            slipped(exit_sell.clone()).alias("exit_price"),
+            slipped(entry_sell.clone()).alias("entry_price"),
+            // Dividends on what was held into the bar; shorts pay them
+            (col("held") * col("dividend")).alias("dividend_cash"),
+            // Borrow fees on a short for the time since the previous bar
+            when(col("held").lt(lit(0.0)))
+                .then(
+                    col("held").abs()
+                        * col("trade_close")
+                        * lit(backtest.borrow_fee_rate)
+                        * col("years"),
+                )
+                .otherwise(lit(0.0))
+                .alias("borrow"),
+        ])
+        .with_columns([
+            commission(
+                &commission_model,
+                exit_sell.clone(),
+                col("exit_qty"),
+                col("exit_price"),
+            )
+            .alias("exit_commission"),
+            commission(
+                &commission_model,
+                entry_sell.clone(),
+                col("entry_qty"),
+                col("entry_price"),
+            )
+            .alias("entry_commission"),
+        ])
+        .with_columns([
+            cash_flow(exit_sell, "exit_qty", "exit_price", "exit_commission").alias("exit_flow"),
+            cash_flow(
+                entry_sell.clone(),
+                "entry_qty",
+                "entry_price",
+                "entry_commission",
+            )
+            .alias("entry_flow"),
+            // Cash a long pays in full, or a short posts as margin
+            (col("entry_qty")
+                * col("entry_price")
+                * when(entry_sell)
+                    .then(lit(backtest.short_margin_rate))
+                    .otherwise(lit(1.0))
+                + col("entry_commission"))
+            .alias("entry_cost"),
+            // Trades are numbered by entry, and own the bars they are held into
+            col("entry_qty")
+                .gt(lit(0.0))
+                .cast(DataType::Int64)
+                .cum_sum(false)
+                .alias("trade"),
+        ])
+        .with_columns([
+            (lit(backtest.initial_balance)
+                + (col("dividend_cash") - col("borrow") + col("exit_flow") + col("entry_flow"))
+                    .cum_sum(false))
+            .alias("cash"),
+            when(col("held").neq(lit(0.0)))
+                .then(col("trade").shift(lit(1)))
+                .otherwise(lit(NULL))
+                .alias("held_trade"),
+        ])
+        .with_columns([
+            (col("cash") - col("entry_flow")).alias("cash_before_entry"),
+            (col("cash") + col("position") * col("trade_close")).alias("equity"),
+        ])
+        .with_columns([when(col("equity").gt(lit(0.0)))
+            .then((col("position") * col("trade_close")).abs() / col("equity"))
+            .otherwise(lit(0.0))
+            .alias("exposure")])
+        .collect()
+        .map_err(polars_error)?;
+
+    // The event loop scales an unaffordable entry down to the cash left,
+    // which a fixed quantity per position cannot express
+    let shortfall = frame
+        .clone()
+        .lazy()
+        .filter(
+            col("entry_qty")
+                .gt(lit(0.0))
+                .and(col("entry_cost").gt(col("cash_before_entry"))),
+        )
+        .limit(1)
+        .collect()
+        .map_err(polars_error)?;
+    if shortfall.height() > 0 {
+        let time = times(&shortfall, "timestamp").map_err(polars_error)?[0];
+        let cost = floats(&shortfall, "entry_cost").map_err(polars_error)?[0];
+        let free = floats(&shortfall, "cash_before_entry").map_err(polars_error)?[0];
+        return Err(format!(
+            "The entry at {} costs {:.2} with only {:.2} free; the vectorized engine cannot \
+             scale it down, so run this backtest with the event engine",
+            time.unwrap_or(backtest.start_time),
+            cost.unwrap_or(0.0),
+            free.unwrap_or(0.0)
+        ));
+    }
+
+    let entries = frame
+        .clone()
+        .lazy()
+        .filter(col("entry_qty").gt(lit(0.0)))
+        .select([
+            col("trade"),
+            col("timestamp").alias("entry_time"),
+            col("position").alias("units"),
+            col("entry_price"),
+            col("entry_commission"),
+        ]);
+    let exits = frame
+        .clone()
+        .lazy()
+        .filter(col("exit_qty").gt(lit(0.0)))
+        .select([
+            col("held_trade").alias("trade"),
+            col("timestamp").alias("exit_time"),
+            col("exit_price"),
+            col("exit_commission"),
+        ]);
+    let costs = frame
+        .clone()
+        .lazy()
+        .filter(col("held_trade").is_not_null())
+        .group_by([col("held_trade")])
+        .agg([col("borrow").sum(), col("dividend_cash").sum()])
+        .select([
+            col("held_trade").alias("trade"),
+            col("borrow"),
+            col("dividend_cash"),
+        ]);
+    let round_trips = entries
+        .left_join(exits, col("trade"), col("trade"))
+        .left_join(costs, col("trade"), col("trade"))
+        .sort("trade", SortOptions::default())
+        .collect()
+        .map_err(polars_error)?;
+
+    let curve = frame
+        .clone()
+        .lazy()
+        .filter(col("timestamp").gt_eq(lit(start)))
+        .select([col("timestamp"), col("equity"), col("exposure")])
+        .collect()
+        .map_err(polars_error)?;
+    let mut equity_curve = vec![EquityPoint::new(
+        backtest.start_time,
+        backtest.initial_balance,
+        0.0,
+    )];
+    let (timestamps, equity, exposure) = (
+        times(&curve, "timestamp").map_err(polars_error)?,
+        floats(&curve, "equity").map_err(polars_error)?,
+        floats(&curve, "exposure").map_err(polars_error)?,
+    );
+    for ((timestamp, equity), exposure) in timestamps.into_iter().zip(equity).zip(exposure) {
+        if let Some(timestamp) = timestamp {
+            equity_curve.push(EquityPoint::new(
+                timestamp,
+                equity.unwrap_or(0.0),
+                exposure.unwrap_or(0.0),
+            ));
+        }
+    }
+
+    let mut final_balance = floats(&frame, "cash")
+        .map_err(polars_error)?
+        .last()
+        .copied()
+        .flatten()
+        .unwrap_or(backtest.initial_balance);
+    let last_time = candles.last().map_or(backtest.end_time, |c| c.timestamp);
+    let last_close = candles.last().map_or(0.0, |c| c.close);
+
+    let entry_times = times(&round_trips, "entry_time").map_err(polars_error)?;
+    let exit_times = times(&round_trips, "exit_time").map_err(polars_error)?;
+    let units = floats(&round_trips, "units").map_err(polars_error)?;
+    let entry_prices = floats(&round_trips, "entry_price").map_err(polars_error)?;
+    let exit_prices = floats(&round_trips, "exit_price").map_err(polars_error)?;
+    let borrow = floats(&round_trips, "borrow").map_err(polars_error)?;
+    let dividend_cash = floats(&round_trips, "dividend_cash").map_err(polars_error)?;
+    let entry_commissions = floats(&round_trips, "entry_commission").map_err(polars_error)?;
+    let exit_commissions = floats(&round_trips, "exit_commission").map_err(polars_error)?;
+
+    let mut trades = Vec::with_capacity(round_trips.height());
+    for i in 0..round_trips.height() {
+        let units = units[i].unwrap_or(0.0);
+        let side = if units > 0.0 {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        };
+        let quantity = units.abs();
+        let entry_price = entry_prices[i].unwrap_or(0.0);
+
+        // A position still open when the data runs out closes at the last close
+        let mut exit_commission = exit_commissions[i].unwrap_or(0.0);
+        let (exit_price, exit_time, exit_reason) = match (exit_prices[i], exit_times[i]) {
+            (Some(price), Some(time)) => (price, time, ExitReason::Signal),
+            _ => {
+                let exit_side = match side {
+                    OrderSide::Buy => OrderSide::Sell,
+                    OrderSide::Sell => OrderSide::Buy,
+                };
+                let price = match exit_side {
+                    OrderSide::Sell => last_close * (1.0 - slippage),
+                    OrderSide::Buy => last_close * (1.0 + slippage),
+                };
+                let notional = price * quantity;
+                let fee =
+                    commission_model.commission(&exit_side, quantity, price, Liquidity::Taker);
+                final_balance += match exit_side {
+                    OrderSide::Sell => notional - fee,
+                    OrderSide::Buy => -notional - fee,
+                };
+                if let Some(last) = equity_curve.last_mut() {
+                    last.equity = final_balance;
+                    last.exposure = 0.0;
+                }
+                exit_commission = fee;
+                (price, last_time, ExitReason::EndOfData)
+            }
+        };
+
+        // Commission on both fills is charged to the trade
+        let costs = borrow[i].unwrap_or(0.0) - dividend_cash[i].unwrap_or(0.0)
+            + entry_commissions[i].unwrap_or(0.0)
+            + exit_commission;
+        let mut trade = BacktestTrade::open(
+            &backtest.id,
+            symbol,
+            &side.to_string(),
+            quantity,
+            entry_price,
+            entry_times[i].unwrap_or(backtest.start_time),
+        );
+        trade.close(exit_price, exit_time, costs, exit_reason);
+        trades.push(trade);
+    }
+
+    Ok(VectorizedRun {
+        equity_curve,
+        trades,
+        final_balance,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::StrategyRegistry;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn candles(closes: &[f64]) -> Vec<OHLCV> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| {
+                OHLCV::new(
+                    Utc.timestamp_opt(i as i64 * 60, 0).unwrap(),
+                    close,
+                    close * 1.01,
+                    close * 0.99,
+                    close,
+                    100.0,
+                )
+            })
+            .collect()
+    }
+
+    /// Signals of the strategy built from `params`, streamed bar by bar and
+    /// evaluated as its expression.
+    fn both_signals(params: serde_json::Value, data: &[OHLCV]) -> (Vec<f64>, Vec<f64>) {
+        let registry = StrategyRegistry::builtin();
+        let mut built = registry.build("classical", &params.to_string()).unwrap();
+        let streamed = data
+            .iter()
+            .map(|bar| match built.logic.update(bar) {
+                Some(crate::actors::messages::SignalType::Buy) => 1.0,
+                Some(crate::actors::messages::SignalType::Sell) => -1.0,
+                _ => 0.0,
+            })
+            .collect();
+
+        let frame = bars_frame(data, data, &vec![0.0; data.len()])
+            .unwrap()
+            .lazy()
+            .select([built
+                .vectorized
+                .unwrap()
+                .fill_null(lit(0.0))
+                .alias("signal")])
+            .collect()
+            .unwrap();
+        let vectorized = floats(&frame, "signal")
+            .unwrap()
+            .into_iter()
+            .map(|v| v.unwrap())
+            .collect();
+        (streamed, vectorized)
+    }
+
+    #[test]
+    fn test_signal_expressions_match_streaming_strategies() {
+        let closes: Vec<f64> = (0..120)
+            .map(|i| 100.0 + 10.0 * (i as f64 / 7.0).sin() + (i % 5) as f64)
+            .collect();
+        let data = candles(&closes);
+
+        for params in [
+            json!({ "fast_period": 3, "slow_period": 8 }),
+            json!({ "kind": "bollinger", "period": 10, "std_dev": 1.5 }),
+            json!({ "kind": "bollinger", "period": 6, "std_dev": 1.0, "mode": "breakout" }),
+        ] {
+            let (streamed, vectorized) = both_signals(params.clone(), &data);
+            assert_eq!(streamed, vectorized, "signals differ for {}", params);
+            assert!(streamed.iter().any(|&s| s > 0.0) && streamed.iter().any(|&s| s < 0.0));
+        }
+    }
+
+    #[test]
+    fn test_commission_expression_matches_every_schedule() {
+        let quantities = [0.0, 3.0, 150.0, 2_000.0];
+        let frame = df!(
+            "quantity" => quantities.to_vec(),
+            "price" => vec![10.0; quantities.len()],
+        )
+        .unwrap();
+
+        for model in [
+            json!({ "type": "percentage", "rate": 0.001 }),
+            json!({ "type": "per_share", "rate": 0.005, "minimum": 1.0, "maximum": 5.0 }),
+            json!({ "type": "tiered", "tiers": [
+                { "up_to": 1000.0, "rate": 0.002 },
+                { "up_to": 10000.0, "rate": 0.001 },
+                { "rate": 0.0005 }
+            ] }),
+            json!({ "type": "maker_taker", "maker_rate": -0.0002, "taker_rate": 0.0007 }),
+            json!({ "type": "per_ticket", "fee": 1.5, "sell_value_rate": 0.0000278,
+                    "sell_per_share": 0.000166, "sell_per_share_max": 0.3,
+                    "exchange_per_share": 0.0003 }),
+        ] {
+            let model: CommissionModel = serde_json::from_value(model).unwrap();
+            for side in [OrderSide::Buy, OrderSide::Sell] {
+                let charged = frame
+                    .clone()
+                    .lazy()
+                    .select([commission(
+                        &model,
+                        lit(side == OrderSide::Sell),
+                        col("quantity"),
+                        col("price"),
+                    )
+                    .alias("fee")])
+                    .collect()
+                    .unwrap();
+                let charged = floats(&charged, "fee").unwrap();
+                for (quantity, fee) in quantities.iter().zip(charged) {
+                    let expected = model.commission(&side, *quantity, 10.0, Liquidity::Taker);
+                    assert!(
+                        (fee.unwrap() - expected).abs() < 1e-9,
+                        "{:?} {:?} of {}: {:?} != {}",
+                        model.schedule,
+                        side,
+                        quantity,
+                        fee,
+                        expected
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use crate::helpers::spawn_app;
-use buffet_backend::models::backtest::{Backtest, BacktestTrade, CreateBacktestDto};
-use buffet_backend::models::market_data::OHLCV;
+use buffet_backend::models::backtest::{Backtest, BacktestTrade, CreateBacktestDto, EquityPoint};
+use buffet_backend::models::market_data::{CorporateAction, OHLCV, PriceAdjustment};
 use buffet_backend::models::strategy::{CreateStrategyDto, Strategy, StrategyType};
+use buffet_backend::models::sweep::SweepDetail;
+use buffet_backend::models::walk_forward::{WalkForward, WalkForwardDetail};
 use buffet_backend::tsdb::TimescaleDb;
-use chrono::{Duration, Utc};
+use buffet_backend::utils::metrics::{Tearsheet, bar_returns, calculate_sharpe_ratio};
+use chrono::{Duration, TimeZone, Utc};
 use serde_json::json;
 
 #[tokio::test]
@@ -24,7 +27,7 @@ async fn test_backtest_execution_flow() {
 
     let response = app
         .api_client
-        .post(&format!("{}/api/strategies", &app.address))
+        .post(format!("{}/api/strategies", &app.address))
         .json(&strategy_dto)
         .send()
         .await
@@ -62,17 +65,30 @@ async fn test_backtest_execution_flow() {
     // 3. Run backtest
     let backtest_dto = CreateBacktestDto {
         strategy_id: strategy.id,
-        symbol: symbol.clone(),
+        symbol: Some(symbol.clone()),
+        symbols: None,
         start_time: now - Duration::hours(1),
         end_time: now + Duration::hours(1),
         initial_balance: 1000.0,
         commission_rate: None,
+        commission_model: None,
+        slippage_model: None,
+        margin_model: None,
+        calendar: None,
+        engine: None,
         slippage_bps: None,
+        allow_short: None,
+        borrow_fee_rate: None,
+        short_margin_rate: None,
+        fill_model: None,
+        parameters: None,
+        warmup_start_time: None,
+        benchmark_symbol: None,
     };
 
     let response = app
         .api_client
-        .post(&format!("{}/api/backtests", &app.address))
+        .post(format!("{}/api/backtests", &app.address))
         .json(&backtest_dto)
         .send()
         .await
@@ -87,7 +103,7 @@ async fn test_backtest_execution_flow() {
         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
         let response = app
             .api_client
-            .get(&format!(
+            .get(format!(
                 "{}/api/backtests/{}",
                 &app.address, backtest_res.id
             ))
@@ -114,13 +130,2639 @@ async fn test_backtest_execution_flow() {
     // 5. Verify trades
     let response = app
         .api_client
-        .get(&format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
         .send()
         .await
         .expect("Failed to get trades");
 
     let trades: Vec<BacktestTrade> = response.json().await.expect("Failed to parse trades");
-    assert!(trades.len() > 0);
+    assert!(!trades.is_empty());
 
     // Cleanup - not strictly necessary as we use isolated DBs
 }
+
+/// Symbols are unique per run, since the test TSDB is shared and never cleared.
+fn unique_symbol(prefix: &str) -> String {
+    format!(
+        "{}_{}",
+        prefix,
+        &uuid::Uuid::new_v4().simple().to_string()[..8]
+    )
+}
+
+async fn insert_prices(app: &crate::helpers::TestApp, symbol: &str, prices: &[f64]) {
+    insert_prices_until(app, symbol, prices, Utc::now()).await;
+}
+
+/// One bar a minute, the last a minute before `now`, so several symbols can
+/// share timestamps.
+async fn insert_prices_until(
+    app: &crate::helpers::TestApp,
+    symbol: &str,
+    prices: &[f64],
+    now: chrono::DateTime<Utc>,
+) {
+    let data: Vec<OHLCV> = prices
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| OHLCV {
+            timestamp: now - Duration::minutes((prices.len() - i) as i64),
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 100.0,
+        })
+        .collect();
+
+    TimescaleDb::new(app.tsdb_pool.clone())
+        .insert_ohlcv(symbol, "crypto", &data)
+        .await
+        .expect("Failed to insert mock data");
+}
+
+async fn wait_for_backtest(app: &crate::helpers::TestApp, id: &str) -> Backtest {
+    for _ in 0..20 {
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        let backtest: Backtest = app
+            .api_client
+            .get(format!("{}/api/backtests/{}", &app.address, id))
+            .send()
+            .await
+            .expect("Failed to get backtest status")
+            .json()
+            .await
+            .expect("Failed to parse backtest");
+
+        match backtest.status.as_str() {
+            "completed" => return backtest,
+            "failed" => panic!("Backtest failed: {:?}", backtest.error_message),
+            _ => {}
+        }
+    }
+    panic!("Backtest timed out");
+}
+
+#[tokio::test]
+async fn test_portfolio_backtest_uses_strategy_symbols() {
+    let app = spawn_app().await;
+
+    let symbol_a = unique_symbol("PF_A");
+    let symbol_b = unique_symbol("PF_B");
+    let strategy_dto = CreateStrategyDto {
+        name: "Portfolio MA Crossover".to_string(),
+        strategy_type: StrategyType::Classical,
+        parameters: json!({
+            "fast_period": 2,
+            "slow_period": 4,
+            "sizing": { "method": "percent_equity", "fraction": 0.5 }
+        }),
+        status: None,
+        symbols: Some(vec![symbol_a.clone(), symbol_b.clone()]),
+    };
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&strategy_dto)
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let prices = [
+        10.0, 11.0, 12.0, 11.0, 10.0, 9.0, 8.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0,
+    ];
+    insert_prices(&app, &symbol_a, &prices).await;
+    let inverted: Vec<f64> = prices.iter().map(|p| 22.0 - p).collect();
+    insert_prices(&app, &symbol_b, &inverted).await;
+
+    // No symbol given: the backtest covers the strategy's own symbols
+    let now = Utc::now();
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest");
+    assert_eq!(response.status(), 202);
+    let created: Backtest = response.json().await.expect("Failed to parse backtest");
+    assert_eq!(
+        created.symbol_list(),
+        vec![symbol_a.clone(), symbol_b.clone()]
+    );
+
+    let b = wait_for_backtest(&app, &created.id).await;
+    let run_config: serde_json::Value =
+        serde_json::from_str(b.run_config.as_deref().unwrap()).unwrap();
+    assert_eq!(run_config["symbols"], json!([symbol_a, symbol_b]));
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    assert!(trades.iter().any(|t| t.symbol == symbol_a));
+    assert!(trades.iter().any(|t| t.symbol == symbol_b));
+}
+
+#[tokio::test]
+async fn test_backtest_without_any_symbol_returns_400() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "No symbols".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let now = Utc::now();
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "start_time": now - Duration::hours(1),
+            "end_time": now,
+            "initial_balance": 1000.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest");
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_short_selling_profits_from_falling_prices() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Short MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let prices = [
+        20.0, 19.0, 18.0, 17.0, 16.0, 15.0, 14.0, 13.0, 12.0, 11.0, 10.0,
+    ];
+    let symbol = unique_symbol("SHORT_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let now = Utc::now();
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "allow_short": true,
+            "borrow_fee_rate": 0.05
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest");
+    assert_eq!(response.status(), 202);
+    let created: Backtest = response.json().await.expect("Failed to parse backtest");
+
+    let b = wait_for_backtest(&app, &created.id).await;
+    assert!(b.total_return.unwrap() > 0.0);
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].side, "sell");
+    assert!(trades[0].pnl.unwrap() > 0.0);
+    assert!(trades[0].percentage_return.unwrap() > 0.0);
+}
+
+#[tokio::test]
+async fn test_stop_loss_exit_is_recorded_on_trade() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Stopped MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({
+                "fast_period": 2,
+                "slow_period": 4,
+                "exits": { "stop_loss_pct": 0.05 }
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // Crossover on 13 enters at the next open (14), then a gap down well
+    // through the 5% stop
+    let prices = [10.0, 11.0, 12.0, 13.0, 14.0, 10.0, 9.0, 8.0];
+    let symbol = unique_symbol("STOP_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let now = Utc::now();
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest");
+    let created: Backtest = response.json().await.expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].exit_reason.as_deref(), Some("stop_loss"));
+    // Filled at the gapped open (less slippage), not at the stop level
+    assert!(trades[0].exit_price.unwrap() < 10.0);
+}
+
+#[tokio::test]
+async fn test_next_bar_open_fills_after_the_signal_bar() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Next Open MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // The crossover prints on the close of 13
+    let prices = [10.0, 11.0, 12.0, 13.0, 14.0, 15.0];
+    let symbol = unique_symbol("OPEN_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let now = Utc::now();
+    let mut entries = Vec::new();
+    for fill_model in ["same_bar_close", "next_bar_open"] {
+        let created: Backtest = app
+            .api_client
+            .post(format!("{}/api/backtests", &app.address))
+            .json(&json!({
+                "strategy_id": strategy.id,
+                "symbol": symbol,
+                "start_time": now - Duration::hours(1),
+                "end_time": now + Duration::hours(1),
+                "initial_balance": 1000.0,
+                "commission_rate": 0.0,
+                "slippage_bps": 0.0,
+                "fill_model": fill_model
+            }))
+            .send()
+            .await
+            .expect("Failed to run backtest")
+            .json()
+            .await
+            .expect("Failed to parse backtest");
+        let b = wait_for_backtest(&app, &created.id).await;
+        assert_eq!(b.fill_model, fill_model);
+        let run_config: serde_json::Value =
+            serde_json::from_str(b.run_config.as_deref().unwrap()).unwrap();
+        assert_eq!(run_config["fill_model"], fill_model);
+
+        let trades: Vec<BacktestTrade> = app
+            .api_client
+            .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+            .send()
+            .await
+            .expect("Failed to get trades")
+            .json()
+            .await
+            .expect("Failed to parse trades");
+        assert_eq!(trades.len(), 1);
+        entries.push(trades[0].entry_price);
+    }
+
+    assert_eq!(entries, vec![13.0, 14.0]);
+}
+
+#[tokio::test]
+async fn test_commission_model_charges_tickets_and_sell_fees() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Ticketed MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let prices = [10.0, 11.0, 12.0, 13.0, 14.0, 15.0];
+    let symbol = unique_symbol("FEE_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let now = Utc::now();
+    let request = |commission_model: serde_json::Value| {
+        json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "slippage_bps": 0.0,
+            "fill_model": "same_bar_close",
+            "commission_model": commission_model
+        })
+    };
+
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&request(json!({ "type": "per_ticket", "fee": -1.0 })))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 400);
+
+    let model = json!({ "type": "per_ticket", "fee": 2.5, "sell_value_rate": 0.01 });
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&request(model))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+    let run_config: serde_json::Value =
+        serde_json::from_str(b.run_config.as_deref().unwrap()).unwrap();
+    assert_eq!(run_config["commission_model"]["type"], "per_ticket");
+    assert_eq!(run_config["commission_model"]["fee"], 2.5);
+    assert_eq!(run_config["commission_model"]["sell_value_rate"], 0.01);
+    // No sizing block, so one unit per entry
+    assert_eq!(
+        run_config["sizing"],
+        json!({ "method": "fixed_quantity", "quantity": 1.0 })
+    );
+
+    // One unit bought at 13 and sold at 15: two tickets plus 1% of the sale
+    let final_balance = b.final_balance.unwrap();
+    assert!(
+        (final_balance - (1000.0 + 2.0 - 5.0 - 0.15)).abs() < 1e-9,
+        "final balance {}",
+        final_balance
+    );
+
+    // ...all of which the trade's PnL is charged with
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    assert_eq!(trades.len(), 1);
+    let pnl = trades[0].pnl.unwrap();
+    assert!((pnl - (final_balance - 1000.0)).abs() < 1e-9, "pnl {}", pnl);
+}
+
+#[tokio::test]
+async fn test_orders_above_participation_cap_fill_over_later_bars() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Illiquid MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({
+                "fast_period": 2,
+                "slow_period": 4,
+                "sizing": { "method": "percent_equity", "fraction": 1.0 }
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // The crossover prints on the close of 13; every bar trades 100 units
+    let prices = [10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0, 17.0];
+    let symbol = unique_symbol("THIN_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let now = Utc::now();
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "slippage_model": { "type": "fixed", "max_participation": 0.0 }
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status().as_u16(), 400);
+
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "commission_rate": 0.0,
+            "slippage_bps": 0.0,
+            "fill_model": "same_bar_close",
+            "slippage_model": { "type": "fixed", "max_participation": 0.2 }
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+    let run_config: serde_json::Value =
+        serde_json::from_str(b.run_config.as_deref().unwrap()).unwrap();
+    assert_eq!(run_config["slippage_model"]["max_participation"], 0.2);
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    assert_eq!(trades.len(), 1);
+
+    // 20 units at each of 13, 14 and 15, then the 160 left buys 10 at 16
+    let trade = &trades[0];
+    assert!(
+        (trade.quantity - 70.0).abs() < 1e-6,
+        "quantity {}",
+        trade.quantity
+    );
+    assert!(
+        (trade.entry_price - 1000.0 / 70.0).abs() < 1e-6,
+        "entry {}",
+        trade.entry_price
+    );
+}
+
+#[tokio::test]
+async fn test_entry_signalled_during_a_partial_close_opens_once_it_fills() {
+    let app = spawn_app().await;
+
+    let symbol_a = unique_symbol("THIN_PAIR_A");
+    let symbol_b = unique_symbol("THIN_PAIR_B");
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Illiquid Pair".to_string(),
+            strategy_type: StrategyType::Statistical,
+            parameters: json!({
+                "symbol_a": symbol_a,
+                "symbol_b": symbol_b,
+                "lookback": 10,
+                "sizing": { "method": "fixed_quantity", "quantity": 50.0 }
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // A tracks 2x B, but runs rich on bars 12-13 (short the spread, exited
+    // on bar 14) and then cheap on bar 15 (long the spread). Every bar
+    // trades 100 units, so 20 fill per bar and the exit of A is still
+    // working when the new entry is signalled.
+    let prices_b: Vec<f64> = (0..20).map(|i| 50.0 + (i % 4) as f64).collect();
+    let mut prices_a: Vec<f64> = prices_b
+        .iter()
+        .enumerate()
+        .map(|(i, b)| 2.0 * b + if i % 2 == 0 { 0.1 } else { -0.1 })
+        .collect();
+    prices_a[12] += 3.0;
+    prices_a[13] += 2.0;
+    prices_a[15] -= 10.0;
+    let now = Utc::now();
+    insert_prices_until(&app, &symbol_a, &prices_a, now).await;
+    insert_prices_until(&app, &symbol_b, &prices_b, now).await;
+
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbols": [symbol_a, symbol_b],
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 100000.0,
+            "commission_rate": 0.0,
+            "slippage_bps": 0.0,
+            "allow_short": true,
+            "fill_model": "same_bar_close",
+            "slippage_model": { "type": "fixed", "max_participation": 0.2 }
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    let mut legs_a: Vec<&BacktestTrade> = trades.iter().filter(|t| t.symbol == symbol_a).collect();
+    legs_a.sort_by_key(|t| t.entry_time);
+    assert_eq!(legs_a.len(), 2, "{:?}", legs_a);
+
+    // The short's exit takes three bars; the long opens on the bar it ends
+    let (short, long) = (legs_a[0], legs_a[1]);
+    assert_eq!(short.side, "sell");
+    assert!((short.quantity - 50.0).abs() < 1e-6, "{:?}", short);
+    assert_eq!(long.side, "buy");
+    assert_eq!(Some(long.entry_time), short.exit_time);
+}
+
+#[tokio::test]
+async fn test_stop_loss_above_participation_cap_fills_over_later_bars() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Illiquid Stop MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({
+                "fast_period": 2,
+                "slow_period": 4,
+                "sizing": { "method": "fixed_quantity", "quantity": 50.0 },
+                "exits": { "stop_loss_pct": 0.05 }
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // Buys 50 on the liquid close of 13, then a thin bar dips through the 12.35 stop
+    let now = Utc::now();
+    let bars = [
+        (10.0, 10.0, 1000.0),
+        (11.0, 11.0, 1000.0),
+        (12.0, 12.0, 1000.0),
+        (13.0, 13.0, 1000.0),
+        (14.0, 12.0, 100.0),
+        (15.0, 15.0, 100.0),
+        (16.0, 16.0, 100.0),
+        (17.0, 17.0, 100.0),
+    ];
+    let data: Vec<OHLCV> = bars
+        .iter()
+        .enumerate()
+        .map(|(i, &(close, low, volume))| OHLCV {
+            timestamp: now - Duration::minutes((bars.len() - i) as i64),
+            open: close,
+            high: close,
+            low,
+            close,
+            volume,
+        })
+        .collect();
+    let symbol = unique_symbol("THIN_STOP_BT");
+    TimescaleDb::new(app.tsdb_pool.clone())
+        .insert_ohlcv(&symbol, "crypto", &data)
+        .await
+        .expect("Failed to insert mock data");
+
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "commission_rate": 0.0,
+            "slippage_bps": 0.0,
+            "fill_model": "same_bar_close",
+            "slippage_model": { "type": "fixed", "max_participation": 0.2 }
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+
+    // 20 units at the 12.35 stop, 20 at 15 and the last 10 at 16; the
+    // crossover only buys back in once the position is flat
+    let trade = &trades[0];
+    assert_eq!(trade.exit_reason.as_deref(), Some("stop_loss"));
+    assert!(
+        (trade.quantity - 50.0).abs() < 1e-6,
+        "quantity {}",
+        trade.quantity
+    );
+    let exit_price = trade.exit_price.expect("stop exit should complete");
+    assert!(
+        (exit_price - (20.0 * 12.35 + 20.0 * 15.0 + 10.0 * 16.0) / 50.0).abs() < 1e-6,
+        "exit {exit_price}"
+    );
+    let exit_time = trade.exit_time.unwrap();
+    assert!((exit_time - data[6].timestamp).num_milliseconds().abs() < 1);
+}
+
+#[tokio::test]
+async fn test_splits_are_adjusted_and_dividends_credited() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Split MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // A 4:1 split on the seventh bar looks like a crash in raw quotes
+    let prices = [10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 4.0, 4.25, 4.5];
+    let symbol = unique_symbol("SPLIT_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let tsdb = TimescaleDb::new(app.tsdb_pool.clone());
+    let now = Utc::now();
+    let (start, end) = (now - Duration::hours(1), now + Duration::hours(1));
+    let raw = tsdb.query_ohlcv(&symbol, start, end).await.unwrap();
+    tsdb.insert_corporate_actions(
+        &symbol,
+        &[
+            CorporateAction::split(raw[6].timestamp, 4.0),
+            CorporateAction::dividend(raw[7].timestamp, 0.1),
+        ],
+    )
+    .await
+    .expect("Failed to insert corporate actions");
+
+    // Raw and adjusted series both stay queryable
+    let split_adjusted = tsdb
+        .query_adjusted_ohlcv(&symbol, start, end, PriceAdjustment::Splits)
+        .await
+        .unwrap();
+    assert_eq!(raw[5].close, 15.0);
+    assert_eq!(split_adjusted[5].close, 3.75);
+    assert_eq!(split_adjusted[5].volume, 400.0);
+    let total = tsdb
+        .query_adjusted_ohlcv(&symbol, start, end, PriceAdjustment::Total)
+        .await
+        .unwrap();
+    assert!(total[6].close < split_adjusted[6].close);
+    assert_eq!(total[8].close, 4.5);
+
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": start,
+            "end_time": end,
+            "initial_balance": 1000.0,
+            "commission_rate": 0.0,
+            "slippage_bps": 0.0,
+            "fill_model": "same_bar_close"
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+
+    // Held through the split instead of selling the "crash", in post-split
+    // shares: bought at 13 / 4, marked at 4.5, plus the dividend
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].exit_reason.as_deref(), Some("end_of_data"));
+    assert_eq!(trades[0].entry_price, 3.25);
+    assert!((trades[0].pnl.unwrap() - 1.35).abs() < 1e-9);
+    assert!((b.final_balance.unwrap() - 1001.35).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_leverage_margin_calls_funding_and_interest() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Leveraged MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({
+                "fast_period": 2,
+                "slow_period": 4,
+                "sizing": { "method": "percent_equity", "fraction": 1.0 }
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let now = Utc::now();
+    let run = |symbol: String, margin_model: serde_json::Value| {
+        let app = &app;
+        let strategy_id = strategy.id.clone();
+        async move {
+            let created: Backtest = app
+                .api_client
+                .post(format!("{}/api/backtests", &app.address))
+                .json(&json!({
+                    "strategy_id": strategy_id,
+                    "symbol": symbol,
+                    "start_time": now - Duration::hours(1),
+                    "end_time": now + Duration::hours(1),
+                    "initial_balance": 1000.0,
+                    "commission_rate": 0.0,
+                    "slippage_bps": 0.0,
+                    "fill_model": "same_bar_close",
+                    "margin_model": margin_model
+                }))
+                .send()
+                .await
+                .expect("Failed to run backtest")
+                .json()
+                .await
+                .expect("Failed to parse backtest");
+            wait_for_backtest(app, &created.id).await
+        }
+    };
+
+    let invalid = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": "ANY",
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "margin_model": { "initial_margin": 0.5, "maintenance_margin": 0.6 }
+        }))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(invalid.status().as_u16(), 400);
+
+    // 2x leverage buys at 13; the drop to 8 leaves equity under 25% of the
+    // position, so it is liquidated before the strategy sees the bar. A
+    // funding payment falls due every minute while the position is open.
+    let symbol = unique_symbol("MARGIN_BT");
+    insert_prices(&app, &symbol, &[10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 8.0]).await;
+    let b = run(
+        symbol,
+        json!({
+            "initial_margin": 0.5,
+            "maintenance_margin": 0.25,
+            "funding_rate": 0.001,
+            "funding_interval_hours": 1.0 / 60.0
+        }),
+    )
+    .await;
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].exit_reason.as_deref(), Some("margin_call"));
+    assert!((trades[0].quantity * trades[0].entry_price - 2000.0).abs() < 1e-6);
+
+    let quantity = 2000.0 / 13.0;
+    let funding = quantity * (14.0 + 15.0 + 8.0) * 0.001;
+    let pnl = quantity * (8.0 - 13.0) - funding;
+    assert!((trades[0].pnl.unwrap() - pnl).abs() < 1e-6);
+    assert!((b.final_balance.unwrap() - (1000.0 + pnl)).abs() < 1e-6);
+    let run_config: serde_json::Value =
+        serde_json::from_str(b.run_config.as_deref().unwrap()).unwrap();
+    assert_eq!(run_config["margin_model"]["initial_margin"], 0.5);
+
+    // Idle cash earns interest when the strategy never trades
+    let symbol = unique_symbol("INTEREST_BT");
+    insert_prices(&app, &symbol, &[10.0, 9.0, 8.0, 7.0, 6.0]).await;
+    let b = run(symbol, json!({ "cash_interest_rate": 0.5 })).await;
+    let final_balance = b.final_balance.unwrap();
+    assert!(final_balance > 1000.0);
+    assert!(final_balance < 1000.0 * (1.0 + 0.5 * 2.0 / (365.25 * 24.0)));
+}
+
+#[tokio::test]
+async fn test_stocks_are_flat_by_the_nyse_close() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Intraday MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({
+                "fast_period": 2,
+                "slow_period": 4,
+                "exits": { "flat_by_close_minutes": 0 }
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // The last minutes of 2 January 2024, when the NYSE closed at 21:00 UTC,
+    // and the first of the next session
+    let close = Utc.with_ymd_and_hms(2024, 1, 2, 21, 0, 0).unwrap();
+    let mut bars: Vec<OHLCV> = [10.0, 11.0, 12.0, 13.0, 14.0, 15.0]
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| {
+            let timestamp = close - Duration::minutes(6 - i as i64);
+            OHLCV::new(timestamp, price, price, price, price, 100.0)
+        })
+        .collect();
+    let next_open = Utc.with_ymd_and_hms(2024, 1, 3, 14, 30, 0).unwrap();
+    bars.push(OHLCV::new(next_open, 9.0, 9.0, 9.0, 9.0, 100.0));
+    let symbol = unique_symbol("NYSE_BT");
+    TimescaleDb::new(app.tsdb_pool.clone())
+        .insert_ohlcv(&symbol, "stock", &bars)
+        .await
+        .expect("Failed to insert mock data");
+
+    let request = |calendar: Option<&str>| {
+        json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": close - Duration::hours(1),
+            "end_time": next_open + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "commission_rate": 0.0,
+            "slippage_bps": 0.0,
+            "fill_model": "same_bar_close",
+            "calendar": calendar
+        })
+    };
+
+    let unknown = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&request(Some("atlantis")))
+        .send()
+        .await
+        .expect("Failed to send request");
+    assert_eq!(unknown.status().as_u16(), 400);
+
+    // Bought at 13 and sold on the closing bar at 15, instead of being held
+    // into the next session's drop
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&request(None))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].exit_reason.as_deref(), Some("session_close"));
+    assert_eq!(trades[0].exit_time, Some(close - Duration::minutes(1)));
+    assert!((b.final_balance.unwrap() - 1002.0).abs() < 1e-9);
+
+    // Stocks follow the NYSE calendar unless the run names another
+    let run_config: serde_json::Value =
+        serde_json::from_str(b.run_config.as_deref().unwrap()).unwrap();
+    assert_eq!(run_config["calendars"][&symbol], "nyse");
+    assert_eq!(run_config["exits"]["flat_by_close_minutes"], 0);
+}
+
+#[tokio::test]
+async fn test_sweep_runs_grid_and_ranks_by_objective() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Swept MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let prices = [
+        10.0, 11.0, 12.0, 11.0, 10.0, 9.0, 8.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0, 12.0,
+    ];
+    let symbol = unique_symbol("SWEEP_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    // fast 4 / slow 4 is invalid and is skipped
+    let now = Utc::now();
+    let response = app
+        .api_client
+        .post(format!("{}/api/sweeps", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "grid": {
+                "fast_period": [2, 4],
+                "slow_period": { "start": 4, "end": 6, "step": 2 }
+            },
+            "objective": "total_return"
+        }))
+        .send()
+        .await
+        .expect("Failed to run sweep");
+    assert_eq!(response.status(), 202);
+    let created: SweepDetail = response.json().await.expect("Failed to parse sweep");
+    assert_eq!(created.sweep.run_count, 3);
+    assert_eq!(created.sweep.skipped_count, 1);
+
+    let mut detail = created;
+    for _ in 0..20 {
+        if detail.status != "running" {
+            break;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        detail = app
+            .api_client
+            .get(format!("{}/api/sweeps/{}", &app.address, detail.sweep.id))
+            .send()
+            .await
+            .expect("Failed to get sweep")
+            .json()
+            .await
+            .expect("Failed to parse sweep");
+    }
+    assert_eq!(detail.status, "completed");
+    assert_eq!(detail.completed_count, 3);
+
+    let scores: Vec<f64> = detail.runs.iter().map(|r| r.score.unwrap()).collect();
+    assert!(scores.windows(2).all(|w| w[0] >= w[1]));
+    assert_eq!(detail.runs[0].rank, Some(1));
+    for run in &detail.runs {
+        assert_eq!(
+            run.backtest.sweep_id.as_deref(),
+            Some(detail.sweep.id.as_str())
+        );
+        assert_eq!(run.score, run.backtest.total_return);
+        let config: serde_json::Value =
+            serde_json::from_str(run.backtest.run_config.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            config["parameters"]["slow_period"],
+            run.parameters["slow_period"]
+        );
+    }
+
+    // The strategy itself is untouched
+    let strategy: Strategy = app
+        .api_client
+        .get(format!("{}/api/strategies/{}", &app.address, strategy.id))
+        .send()
+        .await
+        .expect("Failed to get strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+    let parameters: serde_json::Value = serde_json::from_str(&strategy.parameters).unwrap();
+    assert_eq!(parameters["fast_period"], 2);
+}
+
+#[tokio::test]
+async fn test_sweep_that_fails_partway_leaves_no_runs() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Interrupted Sweep MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // The second run of any sweep cannot be written
+    sqlx::query(
+        "CREATE TRIGGER reject_second_run BEFORE INSERT ON backtests \
+         WHEN (SELECT COUNT(*) FROM backtests WHERE sweep_id = NEW.sweep_id) >= 1 \
+         BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let now = Utc::now();
+    let response = app
+        .api_client
+        .post(format!("{}/api/sweeps", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": unique_symbol("PARTIAL_SWEEP_BT"),
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "grid": { "slow_period": [4, 5, 6] }
+        }))
+        .send()
+        .await
+        .expect("Failed to run sweep");
+    assert_eq!(response.status(), 500);
+
+    let sweeps: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM backtest_sweeps")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(sweeps, 0);
+    let runs: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM backtests")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(runs, 0);
+}
+
+#[tokio::test]
+async fn test_walk_forward_stitches_out_of_sample_folds() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Walk-forward MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // 40 one-minute bars of a slow oscillation
+    let prices: Vec<f64> = (0..40)
+        .map(|i| 100.0 + 10.0 * (i as f64 / 4.0).sin())
+        .collect();
+    let symbol = unique_symbol("WF_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    // Two folds with train segments twice as long as test segments
+    let now = Utc::now();
+    let response = app
+        .api_client
+        .post(format!("{}/api/walk-forwards", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::minutes(41),
+            "end_time": now,
+            "initial_balance": 1000.0,
+            "grid": { "fast_period": [2, 3], "slow_period": [4, 6] },
+            "mode": "rolling",
+            "folds": 2,
+            "train_ratio": 2.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run walk-forward");
+    assert_eq!(response.status(), 202);
+    let created: WalkForward = response.json().await.expect("Failed to parse walk-forward");
+
+    let mut detail: Option<WalkForwardDetail> = None;
+    for _ in 0..40 {
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        let current: WalkForwardDetail = app
+            .api_client
+            .get(format!("{}/api/walk-forwards/{}", &app.address, created.id))
+            .send()
+            .await
+            .expect("Failed to get walk-forward")
+            .json()
+            .await
+            .expect("Failed to parse walk-forward");
+        match current.walk_forward.status.as_str() {
+            "completed" => {
+                detail = Some(current);
+                break;
+            }
+            "failed" => panic!(
+                "Walk-forward failed: {:?}",
+                current.walk_forward.error_message
+            ),
+            _ => {}
+        }
+    }
+    let detail = detail.expect("Walk-forward timed out");
+
+    assert_eq!(detail.folds.len(), 2);
+    for fold in &detail.folds {
+        assert!(fold.fold.sweep_id.is_some());
+        assert!(fold.fold.parameters.is_some());
+        let test = fold.test_backtest.as_ref().expect("fold has a test run");
+        assert_eq!(test.status, "completed");
+        assert_eq!(test.start_time, fold.fold.test_start);
+        assert_eq!(test.warmup_start_time, Some(fold.fold.train_start));
+    }
+    // The second fold trades the capital the first ended with
+    let first = detail.folds[0].test_backtest.as_ref().unwrap();
+    let second = detail.folds[1].test_backtest.as_ref().unwrap();
+    assert_eq!(Some(second.initial_balance), first.final_balance);
+    assert_eq!(detail.walk_forward.final_balance, second.final_balance);
+
+    let curve: Vec<serde_json::Value> =
+        serde_json::from_str(detail.walk_forward.equity_curve.as_deref().unwrap()).unwrap();
+    assert_eq!(curve[0]["equity"], 1000.0);
+    assert!(curve.len() > 2);
+}
+
+#[tokio::test]
+async fn test_monte_carlo_is_reproducible_with_a_seed() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Monte Carlo MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // A few oscillations give several round trips
+    let prices: Vec<f64> = (0..40)
+        .map(|i| 100.0 + 10.0 * (i as f64 / 3.0).sin())
+        .collect();
+    let symbol = unique_symbol("MC_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let now = Utc::now();
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+    assert!(b.trade_count.unwrap() >= 2);
+
+    let run = |body: serde_json::Value| {
+        let request = app
+            .api_client
+            .post(format!(
+                "{}/api/backtests/{}/monte-carlo",
+                &app.address, b.id
+            ))
+            .json(&body);
+        async move {
+            let response = request.send().await.expect("Failed to run Monte Carlo");
+            assert_eq!(response.status(), 200);
+            response
+                .json::<serde_json::Value>()
+                .await
+                .expect("Failed to parse report")
+        }
+    };
+
+    let first = run(json!({ "method": "trade_resample", "simulations": 500, "seed": 7 })).await;
+    let second = run(json!({ "method": "trade_resample", "simulations": 500, "seed": 7 })).await;
+    assert_eq!(first, second);
+    assert_eq!(first["seed"], 7);
+    let bands = &first["final_equity"];
+    assert!(bands["p5"].as_f64() <= bands["p50"].as_f64());
+    assert!(bands["p50"].as_f64() <= bands["p95"].as_f64());
+
+    let blocks = run(json!({ "method": "block_bootstrap", "simulations": 200, "seed": 1 })).await;
+    assert!(blocks["block_size"].as_u64().unwrap() >= 1);
+    assert!(blocks["max_drawdown"]["p95"].as_f64().unwrap() >= 0.0);
+
+    // The bootstrap resamples bars, so its observed Sharpe is per bar too
+    let curve: Vec<EquityPoint> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/equity", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get equity curve")
+        .json()
+        .await
+        .expect("Failed to parse equity curve");
+    let equity: Vec<f64> = curve.iter().map(|p| p.equity).collect();
+    let per_bar = calculate_sharpe_ratio(&bar_returns(&equity), 0.0);
+    assert!((blocks["observed_sharpe_ratio"].as_f64().unwrap() - per_bar).abs() < 1e-9);
+    assert_eq!(first["observed_sharpe_ratio"].as_f64(), b.sharpe_ratio);
+}
+
+#[tokio::test]
+async fn test_equity_curve_records_drawdown_and_exposure() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Equity MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({
+                "fast_period": 2,
+                "slow_period": 4,
+                "sizing": { "method": "percent_equity", "fraction": 0.5 },
+                "exits": { "stop_loss_pct": 0.05 }
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // Long from the open of 14 until the gap down stops it out
+    let prices = [10.0, 11.0, 12.0, 13.0, 14.0, 10.0, 9.0, 8.0];
+    let symbol = unique_symbol("EQUITY_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let now = Utc::now();
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+
+    let curve: Vec<EquityPoint> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/equity", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get equity curve")
+        .json()
+        .await
+        .expect("Failed to parse equity curve");
+
+    // The opening balance, then one point per bar
+    assert_eq!(curve.len(), prices.len() + 1);
+    assert_eq!(curve[0].equity, 1000.0);
+    assert!(curve.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert!(curve.iter().any(|p| (p.exposure - 0.5).abs() < 0.05));
+    assert_eq!(curve.last().unwrap().exposure, 0.0);
+    assert_eq!(curve.last().unwrap().equity, b.final_balance.unwrap());
+
+    let deepest = curve.iter().map(|p| p.drawdown).fold(0.0, f64::max);
+    assert!(deepest > 0.0);
+    assert!((deepest - b.max_drawdown.unwrap()).abs() < 1e-9);
+
+    let missing = app
+        .api_client
+        .get(format!("{}/api/backtests/unknown/equity", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(missing.status(), 404);
+}
+
+#[tokio::test]
+async fn test_completed_backtest_reports_tearsheet_metrics() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Tearsheet MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({
+                "fast_period": 2,
+                "slow_period": 4,
+                "sizing": { "method": "percent_equity", "fraction": 0.5 }
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let prices: Vec<f64> = (0..40)
+        .map(|i| 100.0 + 10.0 * (i as f64 / 3.0).sin())
+        .collect();
+    let symbol = unique_symbol("SHEET_BT");
+    insert_prices(&app, &symbol, &prices).await;
+
+    let now = Utc::now();
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    let pnl: Vec<f64> = trades.iter().map(|t| t.pnl.unwrap()).collect();
+    assert!(pnl.len() >= 2);
+
+    let mean = pnl.iter().sum::<f64>() / pnl.len() as f64;
+    assert!((b.expectancy.unwrap() - mean).abs() < 1e-9);
+    assert_eq!(
+        b.largest_win.unwrap(),
+        pnl.iter().copied().fold(0.0, f64::max)
+    );
+    assert_eq!(
+        b.largest_loss.unwrap(),
+        pnl.iter().copied().fold(0.0, f64::min)
+    );
+    assert!(b.average_loss.unwrap() <= 0.0 && b.average_win.unwrap() >= 0.0);
+    assert!(b.max_consecutive_losses.unwrap() <= pnl.len() as i64);
+
+    let exposure_time = b.exposure_time.unwrap();
+    assert!(exposure_time > 0.0 && exposure_time < 1.0);
+    // Each round trip at half of equity trades about its whole value
+    assert!(b.turnover.unwrap() > 0.5 * pnl.len() as f64);
+    assert!(b.annualized_volatility.unwrap() > 0.0);
+    assert!(b.max_drawdown_duration_secs.unwrap() > 0);
+    assert!(b.cagr.unwrap().is_finite());
+}
+
+#[tokio::test]
+async fn test_backtest_is_compared_with_its_benchmark() {
+    let app = &spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Benchmarked MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // The benchmark is marked at the strategy's bar times, so its bars go in
+    // first, a moment earlier than the traded symbol's
+    let index = unique_symbol("BENCH_IDX");
+    insert_prices(
+        app,
+        &index,
+        &[50.0, 55.0, 60.0, 70.0, 80.0, 85.0, 90.0, 100.0],
+    )
+    .await;
+    let symbol = unique_symbol("BENCH_BT");
+    insert_prices(
+        app,
+        &symbol,
+        &[10.0, 11.0, 12.0, 13.0, 14.0, 10.0, 9.0, 8.0],
+    )
+    .await;
+
+    let now = Utc::now();
+    let run = |benchmark: Option<&str>| {
+        let request = app
+            .api_client
+            .post(format!("{}/api/backtests", &app.address))
+            .json(&json!({
+                "strategy_id": strategy.id,
+                "symbol": symbol,
+                "start_time": now - Duration::hours(1),
+                "end_time": now + Duration::hours(1),
+                "initial_balance": 1000.0,
+                "benchmark_symbol": benchmark
+            }));
+        async move {
+            let created: Backtest = request
+                .send()
+                .await
+                .expect("Failed to run backtest")
+                .json()
+                .await
+                .expect("Failed to parse backtest");
+            wait_for_backtest(app, &created.id).await
+        }
+    };
+
+    // Buy-and-hold of the traded symbol by default
+    let held = run(None).await;
+    assert_eq!(held.benchmark_symbol.as_deref(), Some(symbol.as_str()));
+    assert!((held.benchmark_return.unwrap() + 0.2).abs() < 1e-9);
+    assert!((held.excess_return.unwrap() - (held.total_return.unwrap() + 0.2)).abs() < 1e-9);
+
+    let b = run(Some(&index)).await;
+    assert_eq!(b.benchmark_symbol.as_deref(), Some(index.as_str()));
+    assert!((b.benchmark_return.unwrap() - 1.0).abs() < 1e-9);
+    assert!((b.excess_return.unwrap() - (b.total_return.unwrap() - 1.0)).abs() < 1e-9);
+    assert!(b.tracking_error.unwrap() > 0.0);
+    assert!(b.alpha.is_some() && b.beta.is_some() && b.information_ratio.is_some());
+
+    let curve: Vec<EquityPoint> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/equity", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get equity curve")
+        .json()
+        .await
+        .expect("Failed to parse equity curve");
+    assert!(curve.iter().all(|p| p.benchmark_equity.is_some()));
+    assert_eq!(curve[0].benchmark_equity, Some(1000.0));
+    assert!((curve.last().unwrap().benchmark_equity.unwrap() - 2000.0).abs() < 1e-9);
+
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "benchmark_symbol": " "
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn test_backtest_can_be_cancelled_until_it_finishes() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Cancelled MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let prices = [10.0, 11.0, 12.0, 13.0, 14.0, 10.0, 9.0, 8.0];
+    let symbol = unique_symbol("CANCEL_BT");
+    insert_prices(&app, &symbol, &prices).await;
+    let now = Utc::now();
+    let dto = json!({
+        "strategy_id": strategy.id,
+        "symbol": symbol,
+        "start_time": now - Duration::hours(1),
+        "end_time": now + Duration::hours(1),
+        "initial_balance": 1000.0
+    });
+
+    // A run no worker has picked up yet
+    let pending = Backtest::create(serde_json::from_value(dto.clone()).unwrap(), &app.db_pool)
+        .await
+        .expect("Failed to create backtest");
+    let cancel = |id: String| {
+        app.api_client
+            .post(format!("{}/api/backtests/{}/cancel", &app.address, id))
+            .send()
+    };
+
+    let response = cancel(pending.id.clone()).await.expect("Failed to cancel");
+    assert_eq!(response.status(), 200);
+    let cancelled: Backtest = response.json().await.expect("Failed to parse backtest");
+    assert_eq!(cancelled.status, "cancelled");
+    assert_eq!(
+        cancel(pending.id.clone()).await.unwrap().status(),
+        400,
+        "a cancelled run cannot be cancelled again"
+    );
+
+    // A finished run reports its progress and can no longer be cancelled
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&dto)
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+    assert_eq!(b.bars_total, Some(prices.len() as i64));
+    assert_eq!(b.bars_processed, prices.len() as i64);
+    assert_eq!(cancel(b.id.clone()).await.unwrap().status(), 400);
+
+    assert_eq!(cancel("unknown".to_string()).await.unwrap().status(), 404);
+}
+
+#[tokio::test]
+async fn test_orphaned_and_stale_backtests_are_recovered() {
+    use buffet_backend::actors::{BacktestWorkers, TimeSeriesStorageActor};
+    use buffet_backend::config::BacktestRecovery;
+    use kameo::actor::Spawn;
+
+    let app = spawn_app().await;
+    let storage_actor =
+        TimeSeriesStorageActor::spawn(TimeSeriesStorageActor::new(app.tsdb_pool.clone()));
+    let workers = BacktestWorkers::spawn(1, app.db_pool.clone(), storage_actor, 10);
+    let lease = std::time::Duration::from_secs(60);
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Recovered MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let symbol = unique_symbol("RECOVER_BT");
+    insert_prices(
+        &app,
+        &symbol,
+        &[10.0, 11.0, 12.0, 13.0, 14.0, 10.0, 9.0, 8.0],
+    )
+    .await;
+    let now = Utc::now();
+    let create = || async {
+        let dto = json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        });
+        Backtest::create(serde_json::from_value(dto).unwrap(), &app.db_pool)
+            .await
+            .expect("Failed to create backtest")
+    };
+
+    // Left queued by a previous process, and claimed by a worker that hung
+    let queued = create().await;
+    let hung = create().await;
+    let old_lease = Backtest::start(&hung.id, &app.db_pool)
+        .await
+        .unwrap()
+        .expect("Backtest should start");
+
+    // A fresh heartbeat keeps the lease
+    let stale = workers
+        .recover_stale(&app.db_pool, lease, BacktestRecovery::Fail)
+        .await
+        .unwrap();
+    assert_eq!(stale, 0);
+
+    sqlx::query("UPDATE backtests SET heartbeat_at = ? WHERE id = ?")
+        .bind(Utc::now() - Duration::minutes(5))
+        .bind(&hung.id)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let stale = workers
+        .recover_stale(&app.db_pool, lease, BacktestRecovery::Fail)
+        .await
+        .unwrap();
+    assert_eq!(stale, 1);
+    let failed = Backtest::find_by_id(&hung.id, &app.db_pool).await.unwrap();
+    assert_eq!(failed.status, "failed");
+    assert!(failed.error_message.unwrap().contains("no heartbeat"));
+    // The hung worker finds its lease gone at its next heartbeat, and can
+    // no longer save results
+    assert!(
+        !Backtest::record_progress(&hung.id, &old_lease, 1, 8, &app.db_pool)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !Backtest::heartbeat(&hung.id, &old_lease, &app.db_pool)
+            .await
+            .unwrap()
+    );
+    let mut conn = app.db_pool.acquire().await.unwrap();
+    let saved = Backtest::update_results(
+        &hung.id,
+        &old_lease,
+        1100.0,
+        0.1,
+        0.0,
+        0.0,
+        None,
+        0,
+        0.0,
+        0.0,
+        &Tearsheet::default(),
+        &mut conn,
+    )
+    .await
+    .unwrap();
+    assert!(!saved);
+    drop(conn);
+
+    // On startup, unfinished runs are queued again, without the trades an
+    // earlier attempt left behind
+    let stale_trade = BacktestTrade::open(&queued.id, &symbol, "buy", 1.0, 10.0, now);
+    let mut conn = app.db_pool.acquire().await.unwrap();
+    BacktestTrade::save_trades(&queued.id, std::slice::from_ref(&stale_trade), &mut conn)
+        .await
+        .unwrap();
+    drop(conn);
+    let orphans = workers
+        .recover_orphans(&app.db_pool, BacktestRecovery::Resume)
+        .await
+        .unwrap();
+    assert_eq!(orphans, 1);
+    let resumed = wait_for_backtest(&app, &queued.id).await;
+    assert_eq!(resumed.status, "completed");
+    assert!(resumed.heartbeat_at.is_some());
+    let trades = BacktestTrade::find_by_backtest(&queued.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert!(!trades.is_empty());
+    assert!(trades.iter().all(|t| t.id != stale_trade.id));
+
+    // Walk-forwards cannot resume, so they fail instead
+    let walk_forward = WalkForward::create(
+        serde_json::from_value(json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now,
+            "initial_balance": 1000.0,
+            "grid": { "fast_period": [2, 3] },
+            "folds": 2
+        }))
+        .unwrap(),
+        &app.db_pool,
+    )
+    .await
+    .expect("Failed to create walk-forward");
+    let failed = WalkForward::fail_unfinished("interrupted", &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(failed, 1);
+    let walk_forward = WalkForward::find_by_id(&walk_forward.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(walk_forward.status, "failed");
+    assert_eq!(walk_forward.error_message.as_deref(), Some("interrupted"));
+}
+
+#[tokio::test]
+async fn test_fail_recovery_spares_runs_that_finished_or_heartbeated() {
+    use buffet_backend::actors::{BacktestWorkers, TimeSeriesStorageActor};
+    use buffet_backend::config::BacktestRecovery;
+    use kameo::actor::Spawn;
+
+    let app = spawn_app().await;
+    let storage_actor =
+        TimeSeriesStorageActor::spawn(TimeSeriesStorageActor::new(app.tsdb_pool.clone()));
+    let workers = BacktestWorkers::spawn(1, app.db_pool.clone(), storage_actor, 10);
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Finished MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let symbol = unique_symbol("FINISHED_BT");
+    insert_prices(
+        &app,
+        &symbol,
+        &[10.0, 11.0, 12.0, 13.0, 14.0, 10.0, 9.0, 8.0],
+    )
+    .await;
+    let now = Utc::now();
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let completed = wait_for_backtest(&app, &created.id).await;
+    assert_eq!(completed.status, "completed");
+
+    // Read as stale, then finished before the monitor failed it
+    let cutoff = Utc::now() + Duration::minutes(1);
+    assert!(
+        !Backtest::fail_stale(&completed.id, cutoff, "no heartbeat", &app.db_pool)
+            .await
+            .unwrap()
+    );
+    let orphans = workers
+        .recover_orphans(&app.db_pool, BacktestRecovery::Fail)
+        .await
+        .unwrap();
+    assert_eq!(orphans, 0);
+    let kept = Backtest::find_by_id(&completed.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(kept.status, "completed");
+    assert!(kept.error_message.is_none());
+    assert!(kept.final_balance.is_some());
+
+    // Read as stale, then heartbeated before the monitor failed it
+    let running = Backtest::create(
+        serde_json::from_value(json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        }))
+        .unwrap(),
+        &app.db_pool,
+    )
+    .await
+    .expect("Failed to create backtest");
+    Backtest::start(&running.id, &app.db_pool)
+        .await
+        .unwrap()
+        .expect("Backtest should start");
+    let cutoff = Utc::now() - Duration::minutes(1);
+    assert!(
+        !Backtest::fail_stale(&running.id, cutoff, "no heartbeat", &app.db_pool)
+            .await
+            .unwrap()
+    );
+    let kept = Backtest::find_by_id(&running.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(kept.status, "running");
+}
+
+/// Run the same request through both engines and fetch each run's trades and
+/// equity curve.
+async fn run_on_each_engine(
+    app: &crate::helpers::TestApp,
+    request: &serde_json::Value,
+) -> Vec<(Backtest, Vec<BacktestTrade>, Vec<EquityPoint>)> {
+    let mut runs = Vec::new();
+    for engine in ["event", "vectorized"] {
+        let mut request = request.clone();
+        request["engine"] = json!(engine);
+        let response = app
+            .api_client
+            .post(format!("{}/api/backtests", &app.address))
+            .json(&request)
+            .send()
+            .await
+            .expect("Failed to run backtest");
+        assert_eq!(response.status(), 202, "{} engine", engine);
+        let created: Backtest = response.json().await.expect("Failed to parse backtest");
+        let b = wait_for_backtest(app, &created.id).await;
+        let run_config: serde_json::Value =
+            serde_json::from_str(b.run_config.as_deref().unwrap()).unwrap();
+        assert_eq!(run_config["engine"], engine);
+
+        let trades: Vec<BacktestTrade> = app
+            .api_client
+            .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+            .send()
+            .await
+            .expect("Failed to get trades")
+            .json()
+            .await
+            .expect("Failed to parse trades");
+        let curve: Vec<EquityPoint> = app
+            .api_client
+            .get(format!("{}/api/backtests/{}/equity", &app.address, b.id))
+            .send()
+            .await
+            .expect("Failed to get equity curve")
+            .json()
+            .await
+            .expect("Failed to parse equity curve");
+        runs.push((b, trades, curve));
+    }
+    runs
+}
+
+fn assert_close(event: Option<f64>, vectorized: Option<f64>, what: &str) {
+    match (event, vectorized) {
+        (Some(a), Some(b)) => assert!(
+            (a - b).abs() <= 1e-6 * a.abs().max(1.0),
+            "{}: event {} vs vectorized {}",
+            what,
+            a,
+            b
+        ),
+        (a, b) => assert_eq!(a, b, "{}", what),
+    }
+}
+
+/// Both engines must report the same trades, equity curve and metrics.
+fn assert_engines_agree(runs: &[(Backtest, Vec<BacktestTrade>, Vec<EquityPoint>)]) {
+    let (event, event_trades, event_curve) = &runs[0];
+    let (vectorized, trades, curve) = &runs[1];
+    assert!(!event_trades.is_empty(), "the parity case should trade");
+
+    assert_eq!(trades.len(), event_trades.len());
+    for (a, b) in event_trades.iter().zip(trades) {
+        assert_eq!(a.side, b.side);
+        assert_eq!(a.entry_time, b.entry_time);
+        assert_eq!(a.exit_time, b.exit_time);
+        assert_eq!(a.exit_reason, b.exit_reason);
+        assert_close(Some(a.quantity), Some(b.quantity), "quantity");
+        assert_close(Some(a.entry_price), Some(b.entry_price), "entry price");
+        assert_close(a.exit_price, b.exit_price, "exit price");
+        assert_close(a.pnl, b.pnl, "pnl");
+        assert_close(a.percentage_return, b.percentage_return, "return");
+    }
+
+    assert_eq!(curve.len(), event_curve.len());
+    for (a, b) in event_curve.iter().zip(curve) {
+        assert_eq!(a.timestamp, b.timestamp);
+        assert_close(Some(a.equity), Some(b.equity), "equity");
+        assert_close(Some(a.drawdown), Some(b.drawdown), "drawdown");
+        assert_close(Some(a.exposure), Some(b.exposure), "exposure");
+    }
+
+    let metrics = |b: &Backtest| {
+        [
+            ("final_balance", b.final_balance),
+            ("total_return", b.total_return),
+            ("sharpe_ratio", b.sharpe_ratio),
+            ("max_drawdown", b.max_drawdown),
+            ("win_rate", b.win_rate),
+            ("profit_factor", b.profit_factor),
+            ("cagr", b.cagr),
+            ("sortino_ratio", b.sortino_ratio),
+            ("exposure_time", b.exposure_time),
+            ("turnover", b.turnover),
+            ("expectancy", b.expectancy),
+        ]
+    };
+    for ((what, a), (_, b)) in metrics(event).into_iter().zip(metrics(vectorized)) {
+        assert_close(a, b, what);
+    }
+    assert_eq!(event.trade_count, vectorized.trade_count);
+    assert_eq!(
+        event.max_consecutive_losses,
+        vectorized.max_consecutive_losses
+    );
+}
+
+#[tokio::test]
+async fn test_vectorized_engine_matches_the_event_loop() {
+    let app = spawn_app().await;
+
+    let create_strategy = |name: &str, parameters: serde_json::Value| {
+        app.api_client
+            .post(format!("{}/api/strategies", &app.address))
+            .json(&CreateStrategyDto {
+                name: name.to_string(),
+                strategy_type: StrategyType::Classical,
+                parameters,
+                status: None,
+                symbols: None,
+            })
+            .send()
+    };
+    let crossover: Strategy = create_strategy(
+        "Parity MA Crossover",
+        json!({ "fast_period": 3, "slow_period": 7, "sizing": { "method": "fixed_quantity", "quantity": 2.0 } }),
+    )
+    .await
+    .expect("Failed to create strategy")
+    .json()
+    .await
+    .expect("Failed to parse strategy");
+    // Without a sizing block, one unit per position on either engine
+    let bands: Strategy = create_strategy(
+        "Parity Bollinger",
+        json!({ "kind": "bollinger", "period": 5, "std_dev": 1.0 }),
+    )
+    .await
+    .expect("Failed to create strategy")
+    .json()
+    .await
+    .expect("Failed to parse strategy");
+
+    // Bars with distinct opens, highs and lows so every fill model differs
+    let now = Utc::now();
+    let count = 80;
+    let data: Vec<OHLCV> = (0..count)
+        .map(|i| {
+            let t = i as f64;
+            let close = 100.0 + 8.0 * (t / 5.0).sin() + 0.1 * t;
+            OHLCV {
+                timestamp: now - Duration::minutes(count - i),
+                open: close - 0.5 * (t / 2.0).cos(),
+                high: close + 1.0,
+                low: close - 1.25,
+                close,
+                volume: 1000.0,
+            }
+        })
+        .collect();
+    let symbol = unique_symbol("PARITY_BT");
+    TimescaleDb::new(app.tsdb_pool.clone())
+        .insert_ohlcv(&symbol, "crypto", &data)
+        .await
+        .expect("Failed to insert mock data");
+
+    let base = json!({
+        "strategy_id": crossover.id,
+        "symbol": symbol,
+        "start_time": now - Duration::hours(1),
+        "end_time": now + Duration::hours(1),
+        "initial_balance": 1000.0,
+        "commission_rate": 0.001,
+        "slippage_bps": 5.0,
+        "fill_model": "same_bar_close"
+    });
+
+    // Long-only crossover filling on the signal bar's close
+    let runs = run_on_each_engine(&app, &base).await;
+    assert_engines_agree(&runs);
+
+    // Long and short at the next open, paying borrow, after a warm-up window
+    let mut request = base.clone();
+    request["fill_model"] = json!("next_bar_open");
+    request["allow_short"] = json!(true);
+    request["borrow_fee_rate"] = json!(0.2);
+    request["warmup_start_time"] = json!(now - Duration::minutes(count));
+    request["start_time"] = json!(now - Duration::minutes(count / 2));
+    let runs = run_on_each_engine(&app, &request).await;
+    assert!(runs[0].1.iter().any(|t| t.side == "sell"));
+    assert_engines_agree(&runs);
+
+    // Band reversion at the bar's typical price, with per-share commissions
+    let mut request = base.clone();
+    request["strategy_id"] = json!(bands.id);
+    request["fill_model"] = json!("vwap");
+    request["commission_model"] = json!({ "type": "per_share", "rate": 0.01, "minimum": 0.5 });
+    let runs = run_on_each_engine(&app, &request).await;
+    assert_engines_agree(&runs);
+}
+
+#[tokio::test]
+async fn test_vectorized_engine_rejects_what_it_cannot_simulate() {
+    let app = spawn_app().await;
+
+    let create_strategy = |parameters: serde_json::Value| {
+        app.api_client
+            .post(format!("{}/api/strategies", &app.address))
+            .json(&CreateStrategyDto {
+                name: "Unsupported".to_string(),
+                strategy_type: StrategyType::Classical,
+                parameters,
+                status: None,
+                symbols: None,
+            })
+            .send()
+    };
+    let symbol = unique_symbol("UNSUP_BT");
+    insert_prices(&app, &symbol, &[10.0, 11.0, 12.0, 13.0]).await;
+
+    let now = Utc::now();
+    let cases = [
+        (json!({ "kind": "rsi" }), json!({})),
+        (
+            json!({
+                "fast_period": 2,
+                "slow_period": 3,
+                "sizing": { "method": "percent_equity", "fraction": 0.5 }
+            }),
+            json!({}),
+        ),
+        (
+            json!({ "fast_period": 2, "slow_period": 3, "exits": { "stop_loss_pct": 0.05 } }),
+            json!({}),
+        ),
+        (
+            json!({ "fast_period": 2, "slow_period": 3 }),
+            json!({ "symbols": [symbol, unique_symbol("UNSUP_B")] }),
+        ),
+    ];
+    for (parameters, extra) in cases {
+        let strategy: Strategy = create_strategy(parameters.clone())
+            .await
+            .expect("Failed to create strategy")
+            .json()
+            .await
+            .expect("Failed to parse strategy");
+        let mut request = json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0,
+            "engine": "vectorized"
+        });
+        for (key, value) in extra.as_object().unwrap() {
+            request[key] = value.clone();
+        }
+        let response = app
+            .api_client
+            .post(format!("{}/api/backtests", &app.address))
+            .json(&request)
+            .send()
+            .await
+            .expect("Failed to execute request");
+        assert_eq!(response.status(), 400, "{} {}", parameters, extra);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("vectorized engine"), "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn test_pairs_backtests_must_allow_shorts() {
+    let app = spawn_app().await;
+
+    let symbol_a = unique_symbol("PAIR_A");
+    let symbol_b = unique_symbol("PAIR_B");
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Spread Pair".to_string(),
+            strategy_type: StrategyType::Statistical,
+            parameters: json!({ "symbol_a": symbol_a, "symbol_b": symbol_b, "lookback": 5 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let now = Utc::now();
+    let mut request = json!({
+        "strategy_id": strategy.id,
+        "symbols": [symbol_a, symbol_b],
+        "start_time": now - Duration::hours(1),
+        "end_time": now + Duration::hours(1),
+        "initial_balance": 1000.0
+    });
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&request)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status(), 400);
+    let body = response.text().await.unwrap();
+    assert!(body.contains("allow_short"), "{}", body);
+
+    request["allow_short"] = json!(true);
+    let response = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&request)
+        .send()
+        .await
+        .expect("Failed to execute request");
+    assert_eq!(response.status(), 202);
+}
+
+#[tokio::test]
+async fn test_pairs_hedge_leg_is_sized_by_the_hedge_ratio() {
+    let app = spawn_app().await;
+
+    let symbol_a = unique_symbol("HEDGE_A");
+    let symbol_b = unique_symbol("HEDGE_B");
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Hedged Pair".to_string(),
+            strategy_type: StrategyType::Statistical,
+            parameters: json!({
+                "symbol_a": symbol_a,
+                "symbol_b": symbol_b,
+                "lookback": 10,
+                "entry_z": 2.0,
+                "exit_z": 0.5
+            }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    // A tracks 2x B until it jumps on bar 12, when the window's OLS hedge
+    // ratio is about 1.641; it is back in line on the next bar
+    let prices_b: Vec<f64> = (0..16).map(|i| 50.0 + (i % 4) as f64).collect();
+    let mut prices_a: Vec<f64> = prices_b
+        .iter()
+        .enumerate()
+        .map(|(i, b)| 2.0 * b + if i % 2 == 0 { 0.1 } else { -0.1 })
+        .collect();
+    prices_a[12] += 3.0;
+    for i in 13..16 {
+        prices_a[i] = 2.0 * prices_b[i];
+    }
+    let now = Utc::now();
+    insert_prices_until(&app, &symbol_a, &prices_a, now).await;
+    insert_prices_until(&app, &symbol_b, &prices_b, now).await;
+
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbols": [symbol_a, symbol_b],
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 10000.0,
+            "allow_short": true
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+    let b = wait_for_backtest(&app, &created.id).await;
+
+    let trades: Vec<BacktestTrade> = app
+        .api_client
+        .get(format!("{}/api/backtests/{}/trades", &app.address, b.id))
+        .send()
+        .await
+        .expect("Failed to get trades")
+        .json()
+        .await
+        .expect("Failed to parse trades");
+    assert_eq!(trades.len(), 2, "{:?}", trades);
+    let leg_a = trades.iter().find(|t| t.symbol == symbol_a).unwrap();
+    let leg_b = trades.iter().find(|t| t.symbol == symbol_b).unwrap();
+
+    // One unit of the rich leg sold against the hedge ratio of the other
+    assert_eq!(leg_a.side, "sell");
+    assert_eq!(leg_a.quantity, 1.0);
+    assert_eq!(leg_b.side, "buy");
+    assert!(
+        (leg_b.quantity - 1.641).abs() < 0.001,
+        "hedge leg quantity {}",
+        leg_b.quantity
+    );
+}
+
+#[tokio::test]
+async fn test_failed_save_leaves_no_partial_trades() {
+    let app = spawn_app().await;
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Unsaved MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let symbol = unique_symbol("UNSAVED_BT");
+    insert_prices(
+        &app,
+        &symbol,
+        &[10.0, 11.0, 12.0, 13.0, 14.0, 10.0, 9.0, 8.0, 12.0, 14.0],
+    )
+    .await;
+
+    // The results are the last thing written, after every trade
+    sqlx::query(
+        "CREATE TRIGGER reject_results BEFORE UPDATE OF final_balance ON backtests \
+         BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let now = Utc::now();
+    let created: Backtest = app
+        .api_client
+        .post(format!("{}/api/backtests", &app.address))
+        .json(&json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        }))
+        .send()
+        .await
+        .expect("Failed to run backtest")
+        .json()
+        .await
+        .expect("Failed to parse backtest");
+
+    let mut backtest = created;
+    for _ in 0..20 {
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        backtest = Backtest::find_by_id(&backtest.id, &app.db_pool)
+            .await
+            .unwrap();
+        if backtest.status == "failed" {
+            break;
+        }
+    }
+    assert_eq!(backtest.status, "failed");
+    assert!(backtest.error_message.unwrap().contains("disk full"));
+    assert!(backtest.final_balance.is_none());
+
+    let trades = BacktestTrade::find_by_backtest(&backtest.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert!(trades.is_empty());
+    let curve = EquityPoint::find_by_backtest(&backtest.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert!(curve.is_empty());
+}
+
+#[tokio::test]
+async fn test_runs_are_failed_only_under_their_own_lease() {
+    use buffet_backend::actors::{BacktestWorkers, TimeSeriesStorageActor};
+    use kameo::actor::Spawn;
+
+    let app = spawn_app().await;
+    let storage_actor =
+        TimeSeriesStorageActor::spawn(TimeSeriesStorageActor::new(app.tsdb_pool.clone()));
+    let workers = BacktestWorkers::spawn(1, app.db_pool.clone(), storage_actor, 10);
+
+    let strategy: Strategy = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&CreateStrategyDto {
+            name: "Leased MA Crossover".to_string(),
+            strategy_type: StrategyType::Classical,
+            parameters: json!({ "fast_period": 2, "slow_period": 4 }),
+            status: None,
+            symbols: None,
+        })
+        .send()
+        .await
+        .expect("Failed to create strategy")
+        .json()
+        .await
+        .expect("Failed to parse strategy");
+
+    let symbol = unique_symbol("LEASED_BT");
+    let now = Utc::now();
+    let create = || async {
+        let dto = json!({
+            "strategy_id": strategy.id,
+            "symbol": symbol,
+            "start_time": now - Duration::hours(1),
+            "end_time": now + Duration::hours(1),
+            "initial_balance": 1000.0
+        });
+        Backtest::create(serde_json::from_value(dto).unwrap(), &app.db_pool)
+            .await
+            .expect("Failed to create backtest")
+    };
+
+    // A worker that lost its run to another cannot fail the new attempt
+    let reassigned = create().await;
+    let old_lease = Backtest::start(&reassigned.id, &app.db_pool)
+        .await
+        .unwrap()
+        .expect("Backtest should start");
+    let new_lease = Backtest::start(&reassigned.id, &app.db_pool)
+        .await
+        .unwrap()
+        .expect("Backtest should restart");
+    assert!(
+        !Backtest::fail(&reassigned.id, &old_lease, "stale", &app.db_pool)
+            .await
+            .unwrap()
+    );
+    let running = Backtest::find_by_id(&reassigned.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(running.status, "running");
+    assert!(running.error_message.is_none());
+    assert!(
+        Backtest::fail(&reassigned.id, &new_lease, "current", &app.db_pool)
+            .await
+            .unwrap()
+    );
+    let failed = Backtest::find_by_id(&reassigned.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(failed.status, "failed");
+    assert_eq!(failed.error_message.as_deref(), Some("current"));
+
+    // A run whose strategy is gone fails instead of being left running
+    let orphaned = create().await;
+    let mut conn = app.db_pool.acquire().await.unwrap();
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE backtests SET strategy_id = 'deleted' WHERE id = ?")
+        .bind(&orphaned.id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+    drop(conn);
+
+    assert!(workers.run_and_wait(orphaned.id.clone()).await.is_err());
+    let failed = Backtest::find_by_id(&orphaned.id, &app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(failed.status, "failed");
+    assert!(failed.error_message.is_some());
+}
@@ -6,7 +6,7 @@ async fn health_check_works() {
 
     let response = app
         .api_client
-        .get(&format!("{}/api/health", app.address))
+        .get(format!("{}/api/health", app.address))
         .send()
         .await
         .expect("Failed to execute request");
@@ -99,8 +99,14 @@ pub async fn spawn_app() -> TestApp {
         ),
         mailbox::bounded(100),
     );
-    let backtest_actor = buffet_backend::actors::BacktestActor::spawn_with_mailbox(
-        buffet_backend::actors::BacktestActor::new(db_pool.clone(), storage_actor.clone()),
+    let backtest_workers = buffet_backend::actors::BacktestWorkers::spawn(
+        2,
+        db_pool.clone(),
+        storage_actor.clone(),
+        100,
+    );
+    let walk_forward_actor = buffet_backend::actors::WalkForwardActor::spawn_with_mailbox(
+        buffet_backend::actors::WalkForwardActor::new(db_pool.clone(), backtest_workers.clone()),
         mailbox::bounded(100),
     );
 
@@ -111,7 +117,8 @@ pub async fn spawn_app() -> TestApp {
         collector_actor.clone(),
         strategy_actor.clone(),
         execution_actor.clone(),
-        backtest_actor.clone(),
+        backtest_workers.clone(),
+        walk_forward_actor.clone(),
     );
     let app = routes::create_router(
         db_pool.clone(),
@@ -119,7 +126,8 @@ pub async fn spawn_app() -> TestApp {
         collector_actor.clone(),
         strategy_actor.clone(),
         execution_actor.clone(),
-        backtest_actor.clone(),
+        backtest_workers.clone(),
+        walk_forward_actor.clone(),
     );
 
     // Start the server
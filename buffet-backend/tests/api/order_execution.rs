@@ -1,7 +1,10 @@
 use crate::helpers::spawn_app;
+use async_trait::async_trait;
 use buffet_backend::actors::messages::{MarketDataUpdate, SignalType};
 use buffet_backend::actors::strategy::StrategyLogic;
 use buffet_backend::actors::{OrderExecutionActor, StrategyExecutorActor};
+use buffet_backend::broker::{Broker, BrokerError, FillResult};
+use buffet_backend::models::order::OrderSide;
 use kameo::actor::Spawn;
 use kameo::mailbox;
 
@@ -19,6 +22,34 @@ impl StrategyLogic for MockStrategy {
     }
 }
 
+struct RejectingBroker;
+
+#[async_trait]
+impl Broker for RejectingBroker {
+    async fn submit_market_order(
+        &self,
+        _symbol: &str,
+        _side: &OrderSide,
+        _quantity: f64,
+    ) -> Result<FillResult, BrokerError> {
+        Err(BrokerError::Rejected("market closed".to_string()))
+    }
+
+    async fn submit_limit_order(
+        &self,
+        _symbol: &str,
+        _side: &OrderSide,
+        _quantity: f64,
+        _limit_price: f64,
+    ) -> Result<FillResult, BrokerError> {
+        Err(BrokerError::Rejected("market closed".to_string()))
+    }
+
+    fn name(&self) -> &str {
+        "RejectingBroker"
+    }
+}
+
 #[tokio::test]
 async fn test_order_creation_from_signal() {
     // 1. Spawn app
@@ -86,3 +117,114 @@ async fn test_order_creation_from_signal() {
     assert_eq!(orders[0].side, "buy");
     assert_eq!(orders[0].status, "filled"); // mocked execution fills immediately
 }
+
+#[tokio::test]
+async fn test_repeated_target_signal_orders_once() {
+    let app = spawn_app().await;
+
+    let execution_actor = OrderExecutionActor::spawn_with_mailbox(
+        OrderExecutionActor::new(app.db_pool.clone()),
+        mailbox::bounded(10),
+    );
+
+    let mut executor = StrategyExecutorActor::new(app.db_pool.clone(), execution_actor.clone());
+    executor.register_strategy(
+        "mock_trending_strategy".to_string(),
+        Box::new(MockStrategy {
+            should_signal: true,
+        }),
+    );
+
+    let strategy_actor_ref =
+        StrategyExecutorActor::spawn_with_mailbox(executor, mailbox::bounded(10));
+
+    // The strategy asserts "be long" on every bar of a trend
+    for _ in 0..3 {
+        let candle = buffet_backend::models::market_data::OHLCV {
+            timestamp: chrono::Utc::now(),
+            open: 100.0,
+            high: 100.0,
+            low: 100.0,
+            close: 100.0,
+            volume: 100.0,
+        };
+
+        strategy_actor_ref
+            .tell(MarketDataUpdate {
+                symbol: "SOL".to_string(),
+                data: candle,
+            })
+            .send()
+            .await
+            .expect("Failed to send MarketDataUpdate");
+    }
+
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    let saved_signals = sqlx::query!(
+        "SELECT * FROM signals WHERE strategy_id = ?",
+        "mock_trending_strategy"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch signals");
+    assert_eq!(saved_signals.len(), 1);
+
+    let orders = sqlx::query!("SELECT * FROM orders WHERE symbol = ?", "SOL")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch orders");
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].side, "buy");
+}
+
+#[tokio::test]
+async fn test_rejected_target_order_is_retried() {
+    let app = spawn_app().await;
+
+    let execution_actor = OrderExecutionActor::spawn_with_mailbox(
+        OrderExecutionActor::with_broker(app.db_pool.clone(), Box::new(RejectingBroker)),
+        mailbox::bounded(10),
+    );
+
+    let mut executor = StrategyExecutorActor::new(app.db_pool.clone(), execution_actor.clone());
+    executor.register_strategy(
+        "mock_rejected_strategy".to_string(),
+        Box::new(MockStrategy {
+            should_signal: true,
+        }),
+    );
+
+    let strategy_actor_ref =
+        StrategyExecutorActor::spawn_with_mailbox(executor, mailbox::bounded(10));
+
+    for _ in 0..2 {
+        let candle = buffet_backend::models::market_data::OHLCV {
+            timestamp: chrono::Utc::now(),
+            open: 100.0,
+            high: 100.0,
+            low: 100.0,
+            close: 100.0,
+            volume: 100.0,
+        };
+
+        strategy_actor_ref
+            .tell(MarketDataUpdate {
+                symbol: "ADA".to_string(),
+                data: candle,
+            })
+            .send()
+            .await
+            .expect("Failed to send MarketDataUpdate");
+    }
+
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    // Nothing filled, so the strategy is still flat and asks again
+    let orders = sqlx::query!("SELECT * FROM orders WHERE symbol = ?", "ADA")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch orders");
+    assert_eq!(orders.len(), 2);
+    assert!(orders.iter().all(|o| o.status == "rejected"));
+}
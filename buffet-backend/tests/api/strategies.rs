@@ -8,7 +8,7 @@ async fn create_strategy_returns_201_and_valid_json() {
     // Act
     let response = app
         .api_client
-        .post(&format!("{}/api/strategies", &app.address))
+        .post(format!("{}/api/strategies", &app.address))
         .json(&serde_json::json!({
             "name": "Moving Average Cross",
             "strategy_type": "Classical",
@@ -38,7 +38,7 @@ async fn list_strategies_returns_all_stored_strategies() {
     // Create a strategy with valid Classical parameters
     let create_response = app
         .api_client
-        .post(&format!("{}/api/strategies", &app.address))
+        .post(format!("{}/api/strategies", &app.address))
         .json(&serde_json::json!({
             "name": "List Test Strategy",
             "strategy_type": "Classical",
@@ -56,7 +56,7 @@ async fn list_strategies_returns_all_stored_strategies() {
     // Act
     let response = app
         .api_client
-        .get(&format!("{}/api/strategies", &app.address))
+        .get(format!("{}/api/strategies", &app.address))
         .send()
         .await
         .expect("Failed to execute request");
@@ -84,7 +84,7 @@ async fn create_strategy_with_invalid_parameters_returns_400() {
     // fast_period >= slow_period should fail
     let response = app
         .api_client
-        .post(&format!("{}/api/strategies", &app.address))
+        .post(format!("{}/api/strategies", &app.address))
         .json(&serde_json::json!({
             "name": "Bad Params",
             "strategy_type": "Classical",
@@ -106,7 +106,7 @@ async fn create_strategy_missing_required_parameters_returns_400() {
 
     let response = app
         .api_client
-        .post(&format!("{}/api/strategies", &app.address))
+        .post(format!("{}/api/strategies", &app.address))
         .json(&serde_json::json!({
             "name": "Missing Params",
             "strategy_type": "Classical",
@@ -126,7 +126,7 @@ async fn activate_and_deactivate_strategy() {
     // Create a strategy
     let response = app
         .api_client
-        .post(&format!("{}/api/strategies", &app.address))
+        .post(format!("{}/api/strategies", &app.address))
         .json(&serde_json::json!({
             "name": "Lifecycle Strategy",
             "strategy_type": "Classical",
@@ -147,7 +147,7 @@ async fn activate_and_deactivate_strategy() {
     // Activate
     let response = app
         .api_client
-        .put(&format!("{}/api/strategies/{}/activate", &app.address, id))
+        .put(format!("{}/api/strategies/{}/activate", &app.address, id))
         .send()
         .await
         .expect("Failed to activate strategy");
@@ -159,7 +159,7 @@ async fn activate_and_deactivate_strategy() {
     // Verify it appears in the active list
     let response = app
         .api_client
-        .get(&format!("{}/api/strategies?status=active", &app.address))
+        .get(format!("{}/api/strategies?status=active", &app.address))
         .send()
         .await
         .expect("Failed to list active strategies");
@@ -171,7 +171,7 @@ async fn activate_and_deactivate_strategy() {
     // Deactivate
     let response = app
         .api_client
-        .put(&format!(
+        .put(format!(
             "{}/api/strategies/{}/deactivate",
             &app.address, id
         ))
@@ -191,7 +191,7 @@ async fn get_strategy_by_id_returns_full_details() {
     // Create
     let response = app
         .api_client
-        .post(&format!("{}/api/strategies", &app.address))
+        .post(format!("{}/api/strategies", &app.address))
         .json(&serde_json::json!({
             "name": "Detail Strategy",
             "strategy_type": "Classical",
@@ -212,7 +212,7 @@ async fn get_strategy_by_id_returns_full_details() {
     // Fetch by ID
     let response = app
         .api_client
-        .get(&format!("{}/api/strategies/{}", &app.address, id))
+        .get(format!("{}/api/strategies/{}", &app.address, id))
         .send()
         .await
         .expect("Failed to get strategy");
@@ -232,7 +232,7 @@ async fn delete_strategy_returns_204() {
     // Create
     let response = app
         .api_client
-        .post(&format!("{}/api/strategies", &app.address))
+        .post(format!("{}/api/strategies", &app.address))
         .json(&serde_json::json!({
             "name": "To Delete",
             "strategy_type": "Classical",
@@ -251,7 +251,7 @@ async fn delete_strategy_returns_204() {
     // Delete
     let response = app
         .api_client
-        .delete(&format!("{}/api/strategies/{}", &app.address, id))
+        .delete(format!("{}/api/strategies/{}", &app.address, id))
         .send()
         .await
         .expect("Failed to delete");
@@ -261,10 +261,58 @@ async fn delete_strategy_returns_204() {
     // Verify gone
     let response = app
         .api_client
-        .get(&format!("{}/api/strategies/{}", &app.address, id))
+        .get(format!("{}/api/strategies/{}", &app.address, id))
         .send()
         .await
         .expect("Failed to get");
 
     assert_eq!(response.status().as_u16(), 404);
 }
+
+#[tokio::test]
+async fn list_strategy_kinds_returns_registry_entries() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/api/strategies/kinds", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let kinds: Vec<serde_json::Value> = response.json().await.expect("Failed to parse JSON");
+
+    let rsi = kinds
+        .iter()
+        .find(|k| k["kind"] == "rsi")
+        .expect("rsi kind should be registered");
+    assert_eq!(rsi["strategy_type"], "classical");
+    assert_eq!(rsi["default_parameters"]["period"], 14);
+    assert!(rsi["parameter_schema"]["properties"]["oversold"].is_object());
+}
+
+#[tokio::test]
+async fn create_strategy_with_kind_of_other_type_returns_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/api/strategies", &app.address))
+        .json(&serde_json::json!({
+            "name": "Mismatched Kind",
+            "strategy_type": "MLBased",
+            "parameters": { "kind": "rsi" }
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}